@@ -0,0 +1,638 @@
+/*
+ * gum-netem
+ *
+ * UDP proxy between clients and server, which makes the network bad on purpose.
+ * Latency, jitter, loss, duplication, reordering and bandwidth cap are set per
+ * direction: up is from client to server, down from server to client. Every
+ * client gets an upstream socket of its own, so server sees each client as a
+ * separate address.
+ *
+ * Random decisions come from a seeded generator, one per direction. With the same
+ * seed and the same packets, the same packets are dropped, duplicated and delayed,
+ * and the log is the same. Queueing delay of bandwidth cap depends on timing, so
+ * it is left out of the log.
+ *
+ * Usage: cargo run --bin gum-netem -- --listen 127.0.0.1:22222 --server 127.0.0.1:11111
+ *            [--seed 1] [--profile lossy]
+ *
+ * Commands from stdin switch conditions at runtime:
+ *   profile <name>          clean, lossy, mobile or awful
+ *   up <key>=<value> ...    latency, jitter (ms), loss, duplicate, reorder (0..1),
+ *   down <key>=<value> ...  bandwidth (bytes per second, 0 is unlimited)
+ *   show
+ *   quit
+ */
+use std::cmp::Ordering as CmpOrdering;
+use std::collections::hash_map::Entry;
+use std::collections::{BinaryHeap, HashMap};
+use std::env;
+use std::io::{self, BufRead, ErrorKind};
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const READ_TIMEOUT: Duration = Duration::from_millis(100);
+const RECEIVE_BUFFER_SIZE: usize = 65536;
+// Reordered packet is held back this much longer than the others.
+const REORDER_DELAY: Duration = Duration::from_millis(50);
+// Seeds of the directions differ, so that up and down make different decisions.
+const DOWN_SEED_MASK: u64 = 0x9e37_79b9_7f4a_7c15;
+
+// SplitMix64, small and good enough for network conditions.
+struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1).
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Conditions {
+    latency: Duration,
+    jitter: Duration,
+    loss: f64,
+    duplicate: f64,
+    reorder: f64,
+    // Bytes per second, None is unlimited.
+    bandwidth: Option<u64>,
+}
+
+impl Conditions {
+    fn clean() -> Conditions {
+        Conditions {
+            latency: Duration::from_millis(0),
+            jitter: Duration::from_millis(0),
+            loss: 0.0,
+            duplicate: 0.0,
+            reorder: 0.0,
+            bandwidth: None,
+        }
+    }
+
+    fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let invalid = || format!("Invalid value '{}' for {}.", value, key);
+        match key {
+            "latency" => {
+                self.latency = Duration::from_millis(value.parse().map_err(|_| invalid())?)
+            }
+            "jitter" => self.jitter = Duration::from_millis(value.parse().map_err(|_| invalid())?),
+            "loss" => self.loss = parse_probability(value).ok_or_else(invalid)?,
+            "duplicate" => self.duplicate = parse_probability(value).ok_or_else(invalid)?,
+            "reorder" => self.reorder = parse_probability(value).ok_or_else(invalid)?,
+            "bandwidth" => {
+                let bandwidth: u64 = value.parse().map_err(|_| invalid())?;
+                self.bandwidth = if bandwidth == 0 {
+                    None
+                } else {
+                    Some(bandwidth)
+                };
+            }
+            _ => return Err(format!("Unknown condition '{}'.", key)),
+        }
+        Ok(())
+    }
+}
+
+fn parse_probability(value: &str) -> Option<f64> {
+    let probability: f64 = value.parse().ok()?;
+    if (0.0..=1.0).contains(&probability) {
+        return Some(probability);
+    }
+    None
+}
+
+#[derive(Clone, Debug)]
+struct Profile {
+    name: String,
+    up: Conditions,
+    down: Conditions,
+}
+
+fn get_profile(name: &str) -> Option<Profile> {
+    let conditions = match name {
+        "clean" => Conditions::clean(),
+        "lossy" => Conditions {
+            latency: Duration::from_millis(30),
+            jitter: Duration::from_millis(10),
+            loss: 0.05,
+            ..Conditions::clean()
+        },
+        "mobile" => Conditions {
+            latency: Duration::from_millis(80),
+            jitter: Duration::from_millis(40),
+            loss: 0.02,
+            reorder: 0.02,
+            bandwidth: Some(64 * 1024),
+            ..Conditions::clean()
+        },
+        "awful" => Conditions {
+            latency: Duration::from_millis(200),
+            jitter: Duration::from_millis(100),
+            loss: 0.2,
+            duplicate: 0.05,
+            reorder: 0.1,
+            bandwidth: Some(16 * 1024),
+        },
+        _ => return None,
+    };
+    Some(Profile {
+        name: name.to_string(),
+        up: conditions,
+        down: conditions,
+    })
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Direction {
+    Up,
+    Down,
+}
+
+impl Direction {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Up => "up",
+            Direction::Down => "down",
+        }
+    }
+}
+
+/*
+ * Link
+ *
+ * One direction of the proxy. Decides for every packet, if it is dropped and how
+ * much each copy of it is delayed. Every random number is drawn for every packet,
+ * so that decisions of a packet do not depend on the outcome of earlier ones.
+ */
+struct Link {
+    direction: Direction,
+    rng: Rng,
+    packet_count: u64,
+    // Bandwidth cap sends next packet, when the previous has left.
+    next_free_instant: Option<Instant>,
+}
+
+impl Link {
+    fn new(direction: Direction, seed: u64) -> Link {
+        let seed = match direction {
+            Direction::Up => seed,
+            Direction::Down => seed ^ DOWN_SEED_MASK,
+        };
+        Link {
+            direction,
+            rng: Rng::new(seed),
+            packet_count: 0,
+            next_free_instant: None,
+        }
+    }
+
+    // Returns delay of each copy of the packet, and the log line. No delays means
+    // that packet is dropped.
+    fn decide(&mut self, conditions: &Conditions, len: usize) -> (Vec<Duration>, String) {
+        self.packet_count += 1;
+        let is_lost = self.rng.next_f64() < conditions.loss;
+        let is_duplicated = self.rng.next_f64() < conditions.duplicate;
+        let is_reordered = self.rng.next_f64() < conditions.reorder;
+        let jitters = [self.rng.next_f64(), self.rng.next_f64()];
+
+        let prefix = format!(
+            "{} #{} {} bytes:",
+            self.direction.as_str(),
+            self.packet_count,
+            len
+        );
+        if is_lost {
+            return (vec![], format!("{} dropped", prefix));
+        }
+
+        let copies = if is_duplicated { 2 } else { 1 };
+        let mut delays = vec![];
+        for jitter in &jitters[..copies] {
+            // Jitter is uniform in [-jitter, +jitter], delay never goes below zero.
+            let jitter_ms = conditions.jitter.as_secs_f64() * 1000.0 * (jitter * 2.0 - 1.0);
+            let latency_ms = conditions.latency.as_secs_f64() * 1000.0;
+            let mut delay = Duration::from_secs_f64((latency_ms + jitter_ms).max(0.0) / 1000.0);
+            if is_reordered {
+                delay += REORDER_DELAY;
+            }
+            delays.push(delay);
+        }
+
+        let delays_ms: Vec<String> = delays
+            .iter()
+            .map(|delay| format!("{} ms", delay.as_millis()))
+            .collect();
+        let mut line = format!("{} delay {}", prefix, delays_ms.join(", duplicate "));
+        if is_reordered {
+            line.push_str(", reordered");
+        }
+        (delays, line)
+    }
+
+    // Queueing delay of bandwidth cap, added to seeded delays.
+    fn get_queueing_delay(
+        &mut self,
+        conditions: &Conditions,
+        len: usize,
+        now: Instant,
+    ) -> Duration {
+        let bandwidth = match conditions.bandwidth {
+            None => {
+                self.next_free_instant = None;
+                return Duration::from_millis(0);
+            }
+            Some(bandwidth) => bandwidth,
+        };
+        let start = match self.next_free_instant {
+            Some(instant) if instant > now => instant,
+            _ => now,
+        };
+        let departure = start + Duration::from_secs_f64(len as f64 / bandwidth as f64);
+        self.next_free_instant = Some(departure);
+        departure - now
+    }
+}
+
+struct ScheduledPacket {
+    due: Instant,
+    // Packets due at the same instant keep their scheduling order.
+    order: u64,
+    socket: Arc<UdpSocket>,
+    dst_addr: SocketAddr,
+    data: Vec<u8>,
+}
+
+impl PartialEq for ScheduledPacket {
+    fn eq(&self, other: &Self) -> bool {
+        (self.due, self.order) == (other.due, other.order)
+    }
+}
+
+impl Eq for ScheduledPacket {}
+
+impl PartialOrd for ScheduledPacket {
+    fn partial_cmp(&self, other: &Self) -> Option<CmpOrdering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so that binary heap gives the earliest packet first.
+impl Ord for ScheduledPacket {
+    fn cmp(&self, other: &Self) -> CmpOrdering {
+        (other.due, other.order).cmp(&(self.due, self.order))
+    }
+}
+
+// Sends packets, when they are due.
+fn run_delivery(packets_rx: Receiver<ScheduledPacket>, time_to_die: Arc<AtomicBool>) {
+    let mut packets = BinaryHeap::new();
+    loop {
+        if time_to_die.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let now = Instant::now();
+        while packets
+            .peek()
+            .is_some_and(|packet: &ScheduledPacket| packet.due <= now)
+        {
+            let packet = packets.pop().unwrap();
+            if let Err(e) = packet.socket.send_to(&packet.data, packet.dst_addr) {
+                println!("Failed to send to {}: {}", packet.dst_addr, e);
+            }
+        }
+
+        let wait_time = match packets.peek() {
+            None => READ_TIMEOUT,
+            Some(packet) => packet.due.saturating_duration_since(now).min(READ_TIMEOUT),
+        };
+        match packets_rx.recv_timeout(wait_time) {
+            Ok(packet) => packets.push(packet),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+}
+
+// Shared by receiving threads, which pass packets through links to delivery.
+struct Proxy {
+    profile: Mutex<Profile>,
+    up_link: Mutex<Link>,
+    down_link: Mutex<Link>,
+    packets_tx: Mutex<Sender<ScheduledPacket>>,
+    order: Mutex<u64>,
+}
+
+impl Proxy {
+    fn forward(
+        &self,
+        direction: Direction,
+        socket: &Arc<UdpSocket>,
+        dst_addr: SocketAddr,
+        data: &[u8],
+    ) {
+        let now = Instant::now();
+        let profile = self.profile.lock().unwrap().clone();
+        let (mut link, conditions) = match direction {
+            Direction::Up => (self.up_link.lock().unwrap(), profile.up),
+            Direction::Down => (self.down_link.lock().unwrap(), profile.down),
+        };
+        let (delays, line) = link.decide(&conditions, data.len());
+        println!("{}", line);
+
+        for delay in delays {
+            let queueing_delay = link.get_queueing_delay(&conditions, data.len(), now);
+            let mut order = self.order.lock().unwrap();
+            *order += 1;
+            let packet = ScheduledPacket {
+                due: now + delay + queueing_delay,
+                order: *order,
+                socket: Arc::clone(socket),
+                dst_addr,
+                data: data.to_vec(),
+            };
+            if self.packets_tx.lock().unwrap().send(packet).is_err() {
+                println!("Delivery has stopped.");
+            }
+        }
+    }
+}
+
+// Datagrams from server to one client.
+fn run_downstream(
+    proxy: Arc<Proxy>,
+    upstream_socket: Arc<UdpSocket>,
+    listen_socket: Arc<UdpSocket>,
+    server_addr: SocketAddr,
+    client_addr: SocketAddr,
+    time_to_die: Arc<AtomicBool>,
+) {
+    let mut buf = [0; RECEIVE_BUFFER_SIZE];
+    loop {
+        if time_to_die.load(Ordering::SeqCst) {
+            break;
+        }
+        match upstream_socket.recv_from(&mut buf) {
+            Ok((number_of_bytes, src_addr)) if src_addr == server_addr => {
+                proxy.forward(
+                    Direction::Down,
+                    &listen_socket,
+                    client_addr,
+                    &buf[..number_of_bytes],
+                );
+            }
+            Ok(_) => {}
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {}
+            Err(e) => println!("Upstream socket of {} failed: {}", client_addr, e),
+        }
+    }
+}
+
+fn run_commands(proxy: Arc<Proxy>, time_to_die: Arc<AtomicBool>) {
+    for line in io::stdin().lock().lines() {
+        let line = match line {
+            Err(_) => break,
+            Ok(line) => line,
+        };
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["quit"] => break,
+            ["show"] => println!("{:?}", proxy.profile.lock().unwrap()),
+            ["profile", name] => match get_profile(name) {
+                None => println!("Unknown profile '{}'.", name),
+                Some(profile) => {
+                    println!("Switched to profile {}.", profile.name);
+                    *proxy.profile.lock().unwrap() = profile;
+                }
+            },
+            [direction @ ("up" | "down"), settings @ ..] => {
+                let mut profile = proxy.profile.lock().unwrap();
+                let conditions = match *direction {
+                    "up" => &mut profile.up,
+                    _ => &mut profile.down,
+                };
+                for setting in settings {
+                    let result = match setting.split_once('=') {
+                        None => Err(format!("Setting '{}' is not key=value.", setting)),
+                        Some((key, value)) => conditions.set(key, value),
+                    };
+                    if let Err(e) = result {
+                        println!("{}", e);
+                    }
+                }
+                profile.name = "custom".to_string();
+                println!("{:?}", *profile);
+            }
+            _ => println!("Unknown command '{}'.", line),
+        }
+    }
+    time_to_die.store(true, Ordering::SeqCst);
+}
+
+struct Args {
+    listen: String,
+    server: String,
+    seed: u64,
+    profile: String,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut args = Args {
+        listen: "127.0.0.1:22222".to_string(),
+        server: "127.0.0.1:11111".to_string(),
+        seed: 1,
+        profile: "clean".to_string(),
+    };
+    let mut words = env::args().skip(1);
+    while let Some(word) = words.next() {
+        let value = words
+            .next()
+            .ok_or_else(|| format!("Value missing for {}.", word))?;
+        match word.as_str() {
+            "--listen" => args.listen = value,
+            "--server" => args.server = value,
+            "--seed" => args.seed = value.parse().map_err(|_| "Invalid seed.".to_string())?,
+            "--profile" => args.profile = value,
+            _ => return Err(format!("Unknown option {}.", word)),
+        }
+    }
+    Ok(args)
+}
+
+fn main() {
+    let args = match parse_args() {
+        Err(e) => {
+            println!("{}", e);
+            println!("Usage: gum-netem --listen <addr> --server <addr> [--seed <u64>] [--profile <name>]");
+            return;
+        }
+        Ok(args) => args,
+    };
+    if let Err(e) = run(args) {
+        println!("gum-netem failed: {}", e);
+    }
+}
+
+fn run(args: Args) -> Result<(), io::Error> {
+    let profile = get_profile(&args.profile)
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Unknown profile."))?;
+    let server_addr = args
+        .server
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "Server address not found."))?;
+    let listen_socket = Arc::new(UdpSocket::bind(&args.listen)?);
+    listen_socket.set_read_timeout(Some(READ_TIMEOUT))?;
+    println!(
+        "gum-netem {} -> {}, seed {}, profile {}",
+        listen_socket.local_addr()?,
+        server_addr,
+        args.seed,
+        profile.name
+    );
+
+    let time_to_die = Arc::new(AtomicBool::new(false));
+    let (packets_tx, packets_rx) = channel();
+    let proxy = Arc::new(Proxy {
+        profile: Mutex::new(profile),
+        up_link: Mutex::new(Link::new(Direction::Up, args.seed)),
+        down_link: Mutex::new(Link::new(Direction::Down, args.seed)),
+        packets_tx: Mutex::new(packets_tx),
+        order: Mutex::new(0),
+    });
+
+    let mut handles = vec![];
+    let delivery_time_to_die = Arc::clone(&time_to_die);
+    handles.push(thread::spawn(move || {
+        run_delivery(packets_rx, delivery_time_to_die)
+    }));
+    // Commands thread blocks on stdin, it is not joined.
+    let commands_proxy = Arc::clone(&proxy);
+    let commands_time_to_die = Arc::clone(&time_to_die);
+    thread::spawn(move || run_commands(commands_proxy, commands_time_to_die));
+
+    let upstream_bind_addr = match server_addr {
+        SocketAddr::V4(_) => "0.0.0.0:0",
+        SocketAddr::V6(_) => "[::]:0",
+    };
+    let mut upstream_sockets: HashMap<SocketAddr, Arc<UdpSocket>> = HashMap::new();
+    let mut buf = [0; RECEIVE_BUFFER_SIZE];
+    while !time_to_die.load(Ordering::SeqCst) {
+        let (number_of_bytes, client_addr) = match listen_socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e) if e.kind() == ErrorKind::WouldBlock || e.kind() == ErrorKind::TimedOut => {
+                continue
+            }
+            Err(e) => return Err(e),
+        };
+
+        if let Entry::Vacant(entry) = upstream_sockets.entry(client_addr) {
+            let upstream_socket = Arc::new(UdpSocket::bind(upstream_bind_addr)?);
+            upstream_socket.set_read_timeout(Some(READ_TIMEOUT))?;
+            println!("New client {}", client_addr);
+
+            let proxy = Arc::clone(&proxy);
+            let downstream_socket = Arc::clone(&upstream_socket);
+            let listen_socket = Arc::clone(&listen_socket);
+            let time_to_die = Arc::clone(&time_to_die);
+            handles.push(thread::spawn(move || {
+                run_downstream(
+                    proxy,
+                    downstream_socket,
+                    listen_socket,
+                    server_addr,
+                    client_addr,
+                    time_to_die,
+                )
+            }));
+            entry.insert(upstream_socket);
+        }
+
+        proxy.forward(
+            Direction::Up,
+            &upstream_sockets[&client_addr],
+            server_addr,
+            &buf[..number_of_bytes],
+        );
+    }
+
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
+}
+
+// Binary is not reachable from src/tests, so its tests are kept here.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decide_all(seed: u64, conditions: &Conditions) -> Vec<String> {
+        let mut link = Link::new(Direction::Up, seed);
+        (0..100).map(|len| link.decide(conditions, len).1).collect()
+    }
+
+    #[test]
+    fn test_same_seed_makes_same_decisions() {
+        let conditions = get_profile("awful").unwrap().up;
+        assert_eq!(decide_all(7, &conditions), decide_all(7, &conditions));
+        assert_ne!(decide_all(7, &conditions), decide_all(8, &conditions));
+    }
+
+    #[test]
+    fn test_conditions_are_applied() {
+        let mut conditions = Conditions::clean();
+        let mut link = Link::new(Direction::Down, 1);
+        assert_eq!(
+            link.decide(&conditions, 10).0,
+            vec![Duration::from_millis(0)]
+        );
+
+        conditions.set("duplicate", "1").unwrap();
+        conditions.set("latency", "20").unwrap();
+        assert_eq!(
+            link.decide(&conditions, 10).0,
+            vec![Duration::from_millis(20), Duration::from_millis(20)]
+        );
+
+        conditions.set("loss", "1").unwrap();
+        assert!(link.decide(&conditions, 10).0.is_empty());
+        assert!(conditions.set("loss", "2").is_err());
+    }
+
+    #[test]
+    fn test_bandwidth_cap_queues_packets() {
+        let mut conditions = Conditions::clean();
+        conditions.set("bandwidth", "1000").unwrap();
+        let mut link = Link::new(Direction::Up, 1);
+        let now = Instant::now();
+        assert_eq!(
+            link.get_queueing_delay(&conditions, 100, now),
+            Duration::from_millis(100)
+        );
+        assert_eq!(
+            link.get_queueing_delay(&conditions, 100, now),
+            Duration::from_millis(200)
+        );
+    }
+}
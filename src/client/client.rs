@@ -1,16 +1,30 @@
+use crate::client::datahandlers::structs::request::{DataError, Response, RoomError};
+use crate::client::peers::{PeerPath, Peers};
+use crate::client::pendingrequests::{PendingRequests, RequestHandle, ResponseFuture};
 use crate::client::socketlistener::SocketListener;
 use crate::client::socketsender::SocketSender;
 use crate::client::RequestEvents;
-use crate::helpers::threadkiller::client_channel_killer;
-use crate::helpers::threadkiller::thread_killer;
 use crate::protocol::Protocol;
+use crate::requests::batching::DEFAULT_FLUSH_INTERVAL;
+use crate::requests::challenge::{is_challenged, prepend_empty_cookie};
+use crate::requests::data::{create_data_request, DataOperation, DataResponse, DataStatus};
+use crate::requests::delivery::{DeliveryMode, DeliveryModes};
+use crate::requests::fragmentation::{is_message_too_large, MAX_DATAGRAM_SIZE};
+use crate::requests::header::{Header, HEADER_LENGTH};
 use crate::requests::jobs::Jobs;
 use crate::requests::jobworkers;
-use crate::requests::{ClientJob, Job, JobAction, JobType, ServerJob};
-use crate::socket::SocketCombatible;
+use crate::requests::room::{
+    create_room_request, JoinedRoom, RoomInfo, RoomOperation, RoomResponse, RoomStatus,
+};
+use crate::requests::session::{create_session_token_data, SessionToken};
+use crate::requests::targetedpush::create_targeted_push_request;
+use crate::requests::{ClientJob, Job, JobAction, JobHandle, JobType, ServerJob};
+use crate::socket::{SocketCombatible, Transport};
 use std::io::Error;
 use std::io::ErrorKind;
 use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::net::ToSocketAddrs;
 use std::net::UdpSocket;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::channel;
@@ -20,8 +34,17 @@ use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread;
 use std::thread::JoinHandle;
+use std::time::Duration;
 use std::time::Instant;
 
+// Listener thread wakes up this often, even if nothing is received, to check
+// if client is closing.
+const LISTENER_READ_TIMEOUT: Duration = Duration::from_millis(100);
+// Heartbeat is sent, if nothing has been sent within this time.
+const DEFAULT_KEEPALIVE_INTERVAL: Duration = Duration::from_secs(1);
+// Client is disconnected, if nothing is received within this time.
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
 /*
 trait SendRequest {
     fn send_request(&self, job_type: JobType, raw_data: &mut Vec<u8>);
@@ -31,15 +54,17 @@ trait SendRequest {
 pub struct Client {
     // Multi transmitets txs for threads to share.
     // Ownership of receivers is stored at init and then pushed to handler threads.
-    job_action_channel_tx: Option<Sender<(JobAction, u8, Option<Job>)>>,
-    job_action_channel_rx: Option<Receiver<(JobAction, u8, Option<Job>)>>,
+    job_action_channel_tx: Option<Sender<(JobAction, JobHandle, Option<Job>)>>,
+    job_action_channel_rx: Option<Receiver<(JobAction, JobHandle, Option<Job>)>>,
 
-    socket_send_channel_tx: Option<Sender<(Vec<u8>, Job)>>,
-    socket_send_channel_rx: Option<Receiver<(Vec<u8>, Job)>>,
+    socket_send_channel_tx: Option<Sender<(Vec<u8>, Arc<Job>)>>,
+    socket_send_channel_rx: Option<Receiver<(Vec<u8>, Arc<Job>)>>,
 
     _socket_receive_channel_tx: Option<Sender<Vec<u8>>>,
 
     jobs: Arc<Jobs>,
+    // Requests, which caller waits a response for.
+    pending_requests: Arc<PendingRequests>,
     // Time_to_die variable to terminate threads.
     time_to_die: Arc<AtomicBool>,
     // Is running implicates that socket is tied to address, and socket listener is activated.
@@ -50,11 +75,23 @@ pub struct Client {
     port: Option<u16>,
     // Default and custom protocols. If defaults are missing or mutated, client might fail.
     protocols: Arc<Protocol>,
+    // Delivery mode of each request type, used when mode is not given on send.
+    delivery_modes: DeliveryModes,
+    keepalive_interval: Duration,
+    connection_timeout: Duration,
+    // Requests are batched and sent once per flush interval.
+    flush_interval: Duration,
     error_state_previous: Arc<AtomicBool>,
     error_state_current: Arc<AtomicBool>,
     error_state_start_time: Option<Instant>,
     thread_handles: Option<Vec<JoinHandle<()>>>,
-    socket: Option<Arc<UdpSocket>>,
+    socket: Option<Arc<dyn Transport>>,
+    // Socket is not connected, so that it can receive from peers too. Everything
+    // else than peer data is sent to server address.
+    server_addr: Option<SocketAddr>,
+    // Other players reached directly, if peer-to-peer mode is enabled.
+    peers: Arc<Peers>,
+    p2p_enabled: bool,
     threads_count: u8,
     //handle_data_cb: Arc<Mutex<fn(job_type: JobType, raw_data: &mut [u8])>>,
 }
@@ -69,9 +106,6 @@ impl SocketCombatible for Client {
     fn is_running(&self) -> bool {
         return self.is_running.load(Ordering::SeqCst);
     }
-    fn get_is_running_atomic(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.is_running)
-    }
 }
 
 impl Client {
@@ -92,16 +126,24 @@ impl Client {
 
             // job data struct
             jobs: Arc::new(Jobs::new()),
+            pending_requests: Arc::new(PendingRequests::new()),
             time_to_die: Arc::new(AtomicBool::new(false)),
             is_running: Arc::new(AtomicBool::new(false)),
             ip: None,
             port: None,
             protocols: Arc::new(Protocol::new()),
+            delivery_modes: DeliveryModes::new(),
+            keepalive_interval: DEFAULT_KEEPALIVE_INTERVAL,
+            connection_timeout: DEFAULT_CONNECTION_TIMEOUT,
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
             error_state_previous: Arc::new(AtomicBool::new(false)),
             error_state_current: Arc::new(AtomicBool::new(false)),
             error_state_start_time: None,
             thread_handles: None,
             socket: None,
+            server_addr: None,
+            peers: Arc::new(Peers::new()),
+            p2p_enabled: false,
             threads_count: threads_count,
             //handle_data_cb: Arc::new(Mutex::new(handle_data_cb)),
         }
@@ -110,75 +152,119 @@ impl Client {
     pub fn connect(&mut self, local_ip: String, server_ip: String) -> Result<(), std::io::Error> {
         let socket = UdpSocket::bind(local_ip)?;
         let socket_addr = socket.local_addr()?;
+
+        // Server address of the same family as the local socket is preferred.
+        let server_addrs: Vec<SocketAddr> = server_ip.to_socket_addrs()?.collect();
+        let server_addr = server_addrs
+            .iter()
+            .find(|addr| addr.is_ipv4() == socket_addr.is_ipv4())
+            .or(server_addrs.first())
+            .copied()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidInput, "Server address not found."))?;
+        self.connect_with_transport(Arc::new(socket), server_addr)
+    }
+
+    // Client uses given transport instead of UDP socket, e.g. memory transport in tests.
+    pub fn connect_with_transport(
+        &mut self,
+        transport: Arc<dyn Transport>,
+        server_addr: SocketAddr,
+    ) -> Result<(), std::io::Error> {
+        transport.set_read_timeout(Some(LISTENER_READ_TIMEOUT))?;
+        let socket_addr = transport.local_addr()?;
         self.ip = Some(socket_addr.ip());
         self.port = Some(socket_addr.port());
-        let connect_result = socket.connect(server_ip);
-
-        match connect_result {
-            Ok(()) => {
-                self.socket = Some(Arc::new(socket));
-                Ok(())
-            }
-            Err(e) => Err(e),
-        }
+        self.server_addr = Some(server_addr);
+        self.socket = Some(transport);
+        Ok(())
     }
 
     pub fn init_channels(&mut self) {
         // Creation of Socket Send Channel
-        let (socket_send_channel_tx, socket_send_channel_rx) = channel::<(Vec<u8>, Job)>();
+        let (socket_send_channel_tx, socket_send_channel_rx) = channel::<(Vec<u8>, Arc<Job>)>();
         self.socket_send_channel_tx = Some(socket_send_channel_tx);
         self.socket_send_channel_rx = Some(socket_send_channel_rx);
 
         // Creation of Job Handler Action Channel
         let (job_handler_channel_tx, job_handler_channel_rx) =
-            channel::<(JobAction, u8, Option<Job>)>();
+            channel::<(JobAction, JobHandle, Option<Job>)>();
         self.job_action_channel_tx = Some(job_handler_channel_tx);
         self.job_action_channel_rx = Some(job_handler_channel_rx);
     }
 
     // Takes ownership of thread handles and joins threads.
-    // UDP listening and job handling ends. Uses thread killer from helpers
-    // to activate blocking channel receivers and UDP listener.
+    // UDP listening and job handling ends. Listener thread notices closing within
+    // socket read timeout, and channel receivers when every sender is dropped.
     pub fn die(&mut self) -> bool {
         println!(
             "Closing client running on {}:{}",
             self.ip.unwrap(),
             self.port.unwrap()
         );
-        if self.job_action_channel_tx.is_none() || self.socket_send_channel_tx.is_none() {
-            panic!("Closing client, but channels are not activated. This should not be possible???!?!?");
+        self.time_to_die.store(true, Ordering::SeqCst);
+
+        // Threads holding the rest of the senders end on time to die.
+        self.socket_send_channel_tx = None;
+        self.job_action_channel_tx = None;
+
+        if let Some(handles) = self.thread_handles.take() {
+            handles.into_iter().for_each(|handle| {
+                print!("Closing '{}' ...", handle.thread().name().unwrap());
+                handle.join().unwrap();
+                println!("success.")
+            });
+        }
+        if let Some(socket) = &self.socket {
+            socket.shutdown();
         }
+        self.is_running.store(false, Ordering::SeqCst);
+        true
+    }
 
-        /*
-        let job_channel_tx = self.job_action_channel_tx.as_ref().unwrap().clone();
-        let socket_send_channel_tx = self.socket_send_channel_tx.as_ref().unwrap().clone();
-        let is_running = self.get_is_running_atomic().clone();
-
-        let channel_killer_handle =
-            client_channel_killer(job_channel_tx, socket_send_channel_tx, is_running);
-        */
-        match thread_killer(self) {
-            None => return false,
-            Some(thread_killer_handle) => match self.thread_handles.take() {
-                None => return true,
-                Some(handles) => {
-                    self.time_to_die.store(true, Ordering::SeqCst);
-
-                    handles.into_iter().for_each(|handle| {
-                        if handle.thread().name().unwrap() == "Listener thread" {
-                            print!("Closing '{}' ...", handle.thread().name().unwrap());
-                            handle.join().unwrap();
-                            println!("success.")
-                        }
-                    });
-                    self.is_running.store(false, Ordering::SeqCst);
-                    print!("Closing helpers: Thread killer, ");
-                    thread_killer_handle.join().unwrap();
-                    //println!("channel killer.");
-                    //channel_killer_handle.unwrap().join().unwrap();
-                    return true;
-                }
-            },
+    // Heartbeat settings must be set before client is run.
+    pub fn set_keepalive_interval(&mut self, keepalive_interval: Duration) {
+        self.keepalive_interval = keepalive_interval;
+    }
+
+    pub fn set_connection_timeout(&mut self, connection_timeout: Duration) {
+        self.connection_timeout = connection_timeout;
+    }
+
+    // Peer-to-peer mode must be set before client is run. Client punches a direct
+    // path to every other player, and peer data pushes use it when it is open.
+    pub fn set_p2p_enabled(&mut self, p2p_enabled: bool) {
+        self.p2p_enabled = p2p_enabled;
+    }
+
+    // Path used for data pushes to the player, None if player is not a known peer.
+    pub fn get_peer_path(&self, player_number: u8) -> Option<PeerPath> {
+        self.peers.get_path(player_number)
+    }
+
+    // Flush interval must be set before client is run.
+    pub fn set_flush_interval(&mut self, flush_interval: Duration) {
+        self.flush_interval = flush_interval;
+    }
+
+    // Sends batched requests at once, without waiting for flush interval.
+    pub fn flush(&self) -> Result<(), std::io::Error> {
+        match &self.socket_send_channel_tx {
+            None => Err(Error::new(
+                ErrorKind::Other,
+                "Socket send channel has not been initialized.",
+            )),
+            Some(tx) => {
+                // Empty data asks sender thread to flush.
+                let header = Header::new(
+                    0,
+                    (ServerJob::NoServerAction, ClientJob::NoClientAction),
+                    DeliveryMode::Unreliable,
+                    0,
+                );
+                let job = Arc::new(Job::new(header, &mut vec![]));
+                tx.send((vec![], job))
+                    .map_err(|_| Error::new(ErrorKind::Other, "Send channel hang up."))
+            }
         }
     }
 
@@ -186,6 +272,10 @@ impl Client {
         self.jobs.get_ping()
     }
 
+    pub fn get_send_rate(&self) -> f64 {
+        self.jobs.get_send_rate()
+    }
+
     pub fn is_in_error_state(&self) -> bool {
         self.error_state_current.load(Ordering::SeqCst)
     }
@@ -208,12 +298,19 @@ impl Client {
         let mut handles: Vec<JoinHandle<()>> = Vec::new();
         // clone stuff for next thread
         let socket = Arc::clone(&socket);
+        let server_addr = self.server_addr.unwrap();
+        let peers = match self.p2p_enabled {
+            true => Some(Arc::clone(&self.peers)),
+            false => None,
+        };
         let protocols = Arc::clone(&self.protocols);
         let time_to_die = Arc::clone(&self.time_to_die);
         let events = Arc::clone(&events);
         let error_state_current = Arc::clone(&self.error_state_current);
         let error_state_previous = Arc::clone(&self.error_state_previous);
         let job_channel_tx = self.job_action_channel_tx.as_ref().unwrap().clone();
+        let jobs = Arc::clone(&self.jobs);
+        let pending_requests = Arc::clone(&self.pending_requests);
 
         //let handle_data_cb = Arc::clone(&self.handle_data_cb);
 
@@ -221,8 +318,12 @@ impl Client {
         let listener_thread = thread::Builder::new().name(format!("Listener thread"));
         match listener_thread.spawn(move || {
             (SocketListener::new(
+                jobs,
+                pending_requests,
                 job_channel_tx,
                 socket,
+                server_addr,
+                peers,
                 protocols,
                 time_to_die,
                 events,
@@ -243,7 +344,10 @@ impl Client {
         self.is_running.store(true, Ordering::SeqCst);
     }
 
-    pub fn init_sender(&mut self) {
+    pub fn init_sender<S: 'static>(&mut self, events: Arc<Mutex<S>>)
+    where
+        S: RequestEvents + Send + Sync,
+    {
         if self.socket.is_none() {
             return;
         }
@@ -258,19 +362,28 @@ impl Client {
 
         // other stuff to thread.
         let socket = Arc::clone(&socket);
+        let server_addr = self.server_addr.unwrap();
         let time_to_die = Arc::clone(&self.time_to_die);
         let error_state_current = Arc::clone(&self.error_state_current);
         let error_state_previous = Arc::clone(&self.error_state_previous);
         let job_channel_tx = self.job_action_channel_tx.as_ref().unwrap().clone();
+        let jobs = Arc::clone(&self.jobs);
+        let pending_requests = Arc::clone(&self.pending_requests);
+        let flush_interval = self.flush_interval;
 
         // Thread creation with name.
         let sender_thread = thread::Builder::new().name(format!("Client sender thread"));
         match sender_thread.spawn(move || {
             (SocketSender::new(
                 socket,
+                server_addr,
                 socket_send_channel_rx,
                 job_channel_tx,
+                jobs,
+                pending_requests,
                 time_to_die,
+                events,
+                flush_interval,
                 error_state_current,
                 error_state_previous,
             ))
@@ -309,7 +422,10 @@ impl Client {
     }
 
     // Job handler checks if there is problematic jobs
-    pub fn init_job_handler(&mut self) {
+    pub fn init_job_handler<S: 'static>(&mut self, events: Arc<Mutex<S>>)
+    where
+        S: RequestEvents + Send + Sync,
+    {
         if self.socket_send_channel_tx.is_none() {
             println!("Socket channel not found");
             self.error_state_current.store(true, Ordering::SeqCst);
@@ -318,14 +434,54 @@ impl Client {
 
         // let's take clone only of jobs hadhmap inside jobs struct.
         let jobs = Arc::clone(&self.jobs);
+        let pending_requests = Arc::clone(&self.pending_requests);
         let time_to_die = Arc::clone(&self.time_to_die);
         let job_channel_tx = self.socket_send_channel_tx.as_ref().unwrap().clone();
 
         // uses socket send channel to send re request, when job is not received in given time.
         // worker to handle packages
         let job_thread = thread::Builder::new().name(format!("Job worker thread"));
-        let handle_maybe = job_thread
-            .spawn(move || jobworkers::run_job_handler(jobs, job_channel_tx, time_to_die));
+        let handle_maybe = job_thread.spawn(move || {
+            jobworkers::run_job_handler(jobs, pending_requests, job_channel_tx, time_to_die, events)
+        });
+        match handle_maybe {
+            Ok(handle) => {
+                self.push_handles(vec![handle]);
+            }
+            Err(e) => {
+                panic!("Thread is not running ... {}", e);
+            }
+        }
+    }
+
+    pub fn init_heartbeat<S: 'static>(&mut self, events: Arc<Mutex<S>>)
+    where
+        S: RequestEvents + Send + Sync,
+    {
+        if self.socket_send_channel_tx.is_none() {
+            println!("Socket channel not found");
+            self.error_state_current.store(true, Ordering::SeqCst);
+            return;
+        }
+
+        let jobs = Arc::clone(&self.jobs);
+        let time_to_die = Arc::clone(&self.time_to_die);
+        let socket_send_channel_tx = self.socket_send_channel_tx.as_ref().unwrap().clone();
+        let keepalive_interval = self.keepalive_interval;
+        let connection_timeout = self.connection_timeout;
+
+        // sends heartbeats on idle connection and detects server timeout.
+        let heartbeat_thread = thread::Builder::new().name(format!("Heartbeat thread"));
+        let handle_maybe = heartbeat_thread.spawn(move || {
+            jobworkers::run_heartbeat(
+                jobs,
+                socket_send_channel_tx,
+                time_to_die,
+                events,
+                keepalive_interval,
+                connection_timeout,
+            )
+        });
         match handle_maybe {
             Ok(handle) => {
                 self.push_handles(vec![handle]);
@@ -346,11 +502,292 @@ impl Client {
         }
     }
 
+    // Sets the delivery mode used for given request type.
+    pub fn set_delivery_mode(&mut self, client_job_type: ClientJob, mode: DeliveryMode) {
+        self.delivery_modes
+            .set_client_job_mode(client_job_type, mode);
+    }
+
+    // Sends request with the delivery mode configured for the request type. Returned
+    // handle can be used to wait for the response.
     pub fn send_request(
         &self,
         client_job_type: ClientJob,
         raw_data: &mut Vec<u8>,
-    ) -> Result<(), std::io::Error> {
+    ) -> Result<RequestHandle, std::io::Error> {
+        let mode = self.delivery_modes.get_client_job_mode(&client_job_type);
+        self.send_request_with_mode(client_job_type, raw_data, mode)
+    }
+
+    pub fn send_request_with_mode(
+        &self,
+        client_job_type: ClientJob,
+        raw_data: &mut Vec<u8>,
+        mode: DeliveryMode,
+    ) -> Result<RequestHandle, std::io::Error> {
+        let handle = self.send(client_job_type, raw_data, mode, None)?;
+        Ok(RequestHandle::new(
+            handle,
+            Arc::clone(&self.pending_requests),
+        ))
+    }
+
+    // Sends request with the delivery mode configured for the request type. Returned
+    // future resolves to the response, or to error if request is abandoned or no
+    // response is received within timeout. Future can be awaited on any executor.
+    pub fn send_request_async(
+        &self,
+        client_job_type: ClientJob,
+        raw_data: &mut Vec<u8>,
+        timeout: Duration,
+    ) -> Result<ResponseFuture, std::io::Error> {
+        let mode = self.delivery_modes.get_client_job_mode(&client_job_type);
+        let handle = self.send(client_job_type, raw_data, mode, Some(timeout))?;
+        Ok(ResponseFuture::new(
+            handle,
+            Arc::clone(&self.pending_requests),
+        ))
+    }
+
+    // Player leaves the game. Server frees the player number, answers with player
+    // leave response and tells other players with player leave push.
+    pub fn leave(&self) -> Result<RequestHandle, std::io::Error> {
+        self.send_request(ClientJob::PlayerLeaveRequest, &mut vec![])
+    }
+
+    // Sends data store operation, handle resolves to Response::Data.
+    pub fn send_data_request(
+        &self,
+        operation: &DataOperation,
+    ) -> Result<RequestHandle, std::io::Error> {
+        self.send_request(ClientJob::DataRequest, &mut create_data_request(operation))
+    }
+
+    // Value of the key in server's data store, None if key does not exist.
+    pub fn get_data(&self, key: &str, timeout: Duration) -> Result<Option<Vec<u8>>, DataError> {
+        let operation = DataOperation::Get {
+            key: key.to_string(),
+        };
+        match self.wait_data_response(&operation, timeout)? {
+            DataResponse {
+                status: DataStatus::NotFound,
+                ..
+            } => Ok(None),
+            response => Ok(response.value),
+        }
+    }
+
+    pub fn set_data(&self, key: &str, value: &[u8], timeout: Duration) -> Result<(), DataError> {
+        let operation = DataOperation::Set {
+            key: key.to_string(),
+            value: value.to_vec(),
+        };
+        self.wait_data_response(&operation, timeout)?;
+        Ok(())
+    }
+
+    // Value is set only if current value equals expected value, expected None
+    // means that key must not exist. Otherwise error holds the current value.
+    pub fn compare_and_set(
+        &self,
+        key: &str,
+        expected: Option<&[u8]>,
+        value: &[u8],
+        timeout: Duration,
+    ) -> Result<(), DataError> {
+        let operation = DataOperation::CompareAndSet {
+            key: key.to_string(),
+            expected: expected.map(|expected| expected.to_vec()),
+            value: value.to_vec(),
+        };
+        self.wait_data_response(&operation, timeout)?;
+        Ok(())
+    }
+
+    // Returns false, if key did not exist.
+    pub fn delete_data(&self, key: &str, timeout: Duration) -> Result<bool, DataError> {
+        let operation = DataOperation::Delete {
+            key: key.to_string(),
+        };
+        match self.wait_data_response(&operation, timeout)?.status {
+            DataStatus::NotFound => Ok(false),
+            _ => Ok(true),
+        }
+    }
+
+    // Returns response with status Ok or NotFound, other statuses are errors.
+    fn wait_data_response(
+        &self,
+        operation: &DataOperation,
+        timeout: Duration,
+    ) -> Result<DataResponse, DataError> {
+        let request = self.send_data_request(operation).map_err(DataError::Send)?;
+        let response = match request.wait(timeout).map_err(DataError::Request)? {
+            Response::Data(response) => response,
+            _ => return Err(DataError::InvalidRequest),
+        };
+        match response.status {
+            DataStatus::Ok | DataStatus::NotFound => Ok(response),
+            DataStatus::CompareFailed => Err(DataError::CompareFailed(response.value)),
+            DataStatus::AccessDenied => Err(DataError::AccessDenied),
+            DataStatus::TooLarge => Err(DataError::TooLarge),
+            DataStatus::StoreFull => Err(DataError::StoreFull),
+            DataStatus::InvalidRequest => Err(DataError::InvalidRequest),
+        }
+    }
+
+    // Sends room operation, handle resolves to Response::Room.
+    pub fn send_room_request(
+        &self,
+        operation: &RoomOperation,
+    ) -> Result<RequestHandle, std::io::Error> {
+        self.send_request(ClientJob::RoomRequest, &mut create_room_request(operation))
+    }
+
+    pub fn list_rooms(&self, timeout: Duration) -> Result<Vec<RoomInfo>, RoomError> {
+        match self.wait_room_response(&RoomOperation::List, timeout)? {
+            RoomResponse::Rooms(rooms) => Ok(rooms),
+            _ => Err(RoomError::InvalidRequest),
+        }
+    }
+
+    // Player creates the room and joins it. Room closes, when its last player leaves.
+    pub fn create_room(
+        &self,
+        name: &str,
+        capacity: u8,
+        timeout: Duration,
+    ) -> Result<JoinedRoom, RoomError> {
+        let operation = RoomOperation::Create {
+            name: name.to_string(),
+            capacity,
+        };
+        self.wait_joined_room(&operation, timeout)
+    }
+
+    // Player leaves the lobby for the room. Data pushes reach only other players
+    // in the room, and players are numbered inside the room.
+    pub fn join_room(&self, room_id: u8, timeout: Duration) -> Result<JoinedRoom, RoomError> {
+        self.wait_joined_room(&RoomOperation::Join { room_id }, timeout)
+    }
+
+    // Player is back in the lobby. Returns id of the left room.
+    pub fn leave_room(&self, timeout: Duration) -> Result<u8, RoomError> {
+        match self.wait_room_response(&RoomOperation::Leave, timeout)? {
+            RoomResponse::Left(room_id) => Ok(room_id),
+            _ => Err(RoomError::InvalidRequest),
+        }
+    }
+
+    fn wait_joined_room(
+        &self,
+        operation: &RoomOperation,
+        timeout: Duration,
+    ) -> Result<JoinedRoom, RoomError> {
+        match self.wait_room_response(operation, timeout)? {
+            RoomResponse::Joined(joined_room) => Ok(joined_room),
+            _ => Err(RoomError::InvalidRequest),
+        }
+    }
+
+    // Error statuses of the response are returned as errors.
+    fn wait_room_response(
+        &self,
+        operation: &RoomOperation,
+        timeout: Duration,
+    ) -> Result<RoomResponse, RoomError> {
+        let request = self.send_room_request(operation).map_err(RoomError::Send)?;
+        let response = match request.wait(timeout).map_err(RoomError::Request)? {
+            Response::Room(response) => response,
+            _ => return Err(RoomError::InvalidRequest),
+        };
+        match response {
+            RoomResponse::Error(status) => Err(match status {
+                RoomStatus::NotFound => RoomError::NotFound,
+                RoomStatus::Full => RoomError::Full,
+                RoomStatus::AlreadyInRoom => RoomError::AlreadyInRoom,
+                RoomStatus::NotInRoom => RoomError::NotInRoom,
+                RoomStatus::NoFreeRoom => RoomError::NoFreeRoom,
+                RoomStatus::Ok | RoomStatus::InvalidRequest => RoomError::InvalidRequest,
+            }),
+            response => Ok(response),
+        }
+    }
+
+    // Resumes session of the player from address of this client, e.g. after address
+    // of the player has changed. Token is given in player created response. Server
+    // tracks sequences of the resumed session from 0.
+    pub fn resume_session(
+        &self,
+        session_token: SessionToken,
+    ) -> Result<RequestHandle, std::io::Error> {
+        self.jobs.reset_sequences();
+        self.send_request(
+            ClientJob::ResumeRequest,
+            &mut create_session_token_data(session_token),
+        )
+    }
+
+    // Server forwards data only to the given players, as data push with player number
+    // of this player. Handle resolves to Response::TargetedPushDone, unknown targets
+    // are answered even if push is unreliable.
+    pub fn send_targeted_push(
+        &self,
+        player_numbers: &[u8],
+        raw_data: &[u8],
+    ) -> Result<RequestHandle, std::io::Error> {
+        if player_numbers.is_empty() || player_numbers.len() > u8::MAX as usize {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Targeted push needs 1 to 255 target players.",
+            ));
+        }
+        self.send_request(
+            ClientJob::TargetedPushRequest,
+            &mut create_targeted_push_request(player_numbers, raw_data),
+        )
+    }
+
+    // Sends data straight to the player, if direct path is open. Direct datagram is
+    // queued to sender thread, so that it shares send budget and batching with requests.
+    // Otherwise data is relayed by server as a reliable targeted push to the player, so
+    // is data too large for one datagram. Returns the path used, and handle of the
+    // targeted push, which resolves to Response::TargetedPushDone.
+    pub fn send_peer_data_push(
+        &self,
+        player_number: u8,
+        raw_data: &[u8],
+    ) -> Result<(PeerPath, Option<RequestHandle>), std::io::Error> {
+        let peer_addr_maybe = self.peers.get_direct_addr(player_number);
+        if let (Some(tx), Some(peer_addr)) = (&self.socket_send_channel_tx, peer_addr_maybe) {
+            if HEADER_LENGTH + raw_data.len() <= MAX_DATAGRAM_SIZE {
+                let header = Header::new(
+                    0,
+                    (ServerJob::NoServerAction, ClientJob::PeerDataPush),
+                    DeliveryMode::Unreliable,
+                    0,
+                );
+                let job = Job::new(header, &mut raw_data.to_vec()).with_destination(peer_addr);
+                let datagram = job.get_raw_data();
+                tx.send((datagram, Arc::new(job)))
+                    .map_err(|_| Error::new(ErrorKind::Other, "Send channel hang up."))?;
+                return Ok((PeerPath::Direct, None));
+            }
+        }
+
+        let request = self.send_targeted_push(&[player_number], raw_data)?;
+        Ok((PeerPath::Relay, Some(request)))
+    }
+
+    // Returns handle of the sent request. Request is added to pending requests
+    // before it is sent, timeout is the deadline for the response.
+    fn send(
+        &self,
+        client_job_type: ClientJob,
+        raw_data: &mut Vec<u8>,
+        mode: DeliveryMode,
+        timeout: Option<Duration>,
+    ) -> Result<JobHandle, std::io::Error> {
         match &self.socket_send_channel_tx {
             None => {
                 return Err(Error::new(
@@ -359,36 +796,58 @@ impl Client {
                 ));
             }
             Some(tx) => {
+                // Player enter and resume requests hold the cookie of server's challenge.
+                if is_challenged(&client_job_type) {
+                    prepend_empty_cookie(raw_data);
+                }
+                if is_message_too_large(HEADER_LENGTH + raw_data.len()) {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        "Request is too large to be fragmented.",
+                    ));
+                }
+
                 // Create a job to follow up server response.
                 // Job thread will ask to send new request if job fails.
                 let next_job_handle = self.jobs.get_next_job_handle();
-                let job_should_complite = (self.jobs.get_job_finish_time_average() * 5.0) as u128;
+                let sequence = self.jobs.get_next_sequence(mode);
                 let job_type: JobType = (ServerJob::NoServerAction, client_job_type);
+                let header = Header::new(next_job_handle, job_type, mode, sequence);
+
+                // let's add header to a job.
+                let job = Job::new(header, raw_data);
+                // data has now header as first bytes, rest is actual raw_data.
+                // Large data is split to fragments, each fragment is sent as own datagram.
+                let datagrams = job.get_datagrams();
 
-                // let's add index and job type to a job.
-                let job = Job::new(next_job_handle, job_type, raw_data, job_should_complite);
-                // data has now index and job_type as 2 first bytes, rest is actual raw_data.
-                let data = job.get_raw_data();
+                self.pending_requests.add(next_job_handle, timeout);
 
-                // Job is inserted to Jobs via job_action_channel
+                // Only reliable jobs are followed up. Job is inserted to Jobs via job_action_channel
                 // (*jobs_changer).jobs.insert(next_job_handle, job);
-                let job_action_channel_tx = self.job_action_channel_tx.as_ref().unwrap();
-                match job_action_channel_tx.send((
-                    JobAction::ADD,
-                    next_job_handle,
-                    Some(job.clone()),
-                )) {
-                    Err(e) => {
-                        println!("Job action channel hang up: {}", e);
+                if mode.is_reliable() {
+                    let job_action_channel_tx = self.job_action_channel_tx.as_ref().unwrap();
+                    match job_action_channel_tx.send((
+                        JobAction::ADD,
+                        next_job_handle,
+                        Some(job.clone()),
+                    )) {
+                        Err(e) => {
+                            println!("Job action channel hang up: {}", e);
+                        }
+                        Ok(()) => {}
                     }
-                    Ok(()) => {}
                 }
 
-                let result = tx.send((data, job));
-                if result.is_ok() {
-                    return Ok(());
+                // Fragments share one copy of the job.
+                let job = Arc::new(job);
+                for data in datagrams {
+                    let result = tx.send((data, Arc::clone(&job)));
+                    if result.is_err() {
+                        self.pending_requests.remove(next_job_handle);
+                        return Err(Error::new(ErrorKind::Other, "Send channel hang up."));
+                    }
                 }
-                return Err(Error::new(ErrorKind::Other, "Send channel hang up."));
+                return Ok(next_job_handle);
             }
         }
     }
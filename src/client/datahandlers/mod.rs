@@ -4,6 +4,7 @@ use crate::protocol::ProtocolError;
 use std::collections::HashMap;
 pub mod playercreatedresponse;
 pub mod playerenterpush;
+pub mod playerleavepush;
 pub mod structs;
 
 fn check_raw_data_length_integrity(
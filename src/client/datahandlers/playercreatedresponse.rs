@@ -3,6 +3,7 @@ use crate::client::datahandlers::structs::player::{PlayerCreatedResponseData, Pl
 use crate::protocol::datahelpers::create_addr_from_ip_and_port;
 use crate::protocol::datastructure::StructuredData;
 use crate::protocol::{Protocol, ProtocolError};
+use crate::requests::session::get_session_token;
 use std::fmt::Display;
 use std::fmt::Formatter;
 
@@ -50,11 +51,17 @@ pub fn structurize_raw_data(
     if status == 1 {
         let player_number = structured_data.get_u8_data("PlayerNumber")?;
         let player_name = structured_data.get_string_data("PlayerName")?;
-        let mut player_created_response_data = PlayerCreatedResponseData::new(PlayerData {
-            player_name: player_name,
-            player_number: player_number,
-            addr: None,
-        });
+        let session_token_data = structured_data.get_vec_data("SessionToken")?;
+        let session_token =
+            get_session_token(&session_token_data).ok_or(ProtocolError::InvalidRawData)?;
+        let mut player_created_response_data = PlayerCreatedResponseData::new(
+            PlayerData {
+                player_name: player_name,
+                player_number: player_number,
+                addr: None,
+            },
+            session_token,
+        );
 
         // let's loop other player array data.
         for other_player_structured_data in structured_data.get_iterable_array("OtherPlayers")? {
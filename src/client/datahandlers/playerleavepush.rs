@@ -0,0 +1,26 @@
+use crate::client::datahandlers::check_raw_data_length_integrity;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
+use crate::protocol::datastructure::StructuredData;
+use crate::protocol::Protocol;
+use crate::protocol::ProtocolError;
+use crate::requests::leave::LeaveReason;
+
+pub fn structurize_raw_data(
+    protocols: &Protocol,
+    raw_data: &[u8],
+) -> Result<PlayerLeaveData, ProtocolError> {
+    let player_leave_push_protocol = protocols.get_protocol("PlayerLeavePush")?;
+
+    check_raw_data_length_integrity(player_leave_push_protocol, raw_data)?;
+
+    let structured_data = StructuredData::new(player_leave_push_protocol, raw_data.to_vec());
+
+    let player_number = structured_data.get_u8_data("PlayerNumber")?;
+    let reason = LeaveReason::from_byte(structured_data.get_u8_data("LeaveReason")?)
+        .ok_or(ProtocolError::InvalidRawData)?;
+
+    Ok(PlayerLeaveData {
+        player_number,
+        reason,
+    })
+}
@@ -1,21 +1,35 @@
+use crate::requests::leave::LeaveReason;
+use crate::requests::session::SessionToken;
 use std::net::SocketAddr;
 
+#[derive(Debug)]
 pub struct PlayerData {
     pub player_name: String,
     pub player_number: u8,
     pub addr: Option<SocketAddr>,
 }
 
+// Player, who left the game, and why. Player number is free for new players.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlayerLeaveData {
+    pub player_number: u8,
+    pub reason: LeaveReason,
+}
+
+#[derive(Debug)]
 pub struct PlayerCreatedResponseData {
     pub player: PlayerData,
     pub others_players: Vec<PlayerData>,
+    // Player can resume the session with the token, if its address changes.
+    pub session_token: SessionToken,
 }
 
 impl PlayerCreatedResponseData {
-    pub fn new(player: PlayerData) -> PlayerCreatedResponseData {
+    pub fn new(player: PlayerData, session_token: SessionToken) -> PlayerCreatedResponseData {
         PlayerCreatedResponseData {
             player: player,
             others_players: vec![],
+            session_token: session_token,
         }
     }
     pub fn add_other_player(&mut self, player: PlayerData) {
@@ -0,0 +1,89 @@
+use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
+use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
+use crate::requests::data::DataResponse;
+use crate::requests::room::RoomResponse;
+use crate::requests::{ClientJob, JobHandle};
+use std::time::Duration;
+
+// Request, which client gave up on. Server may or may not have received it.
+#[derive(Clone, Debug)]
+pub struct AbandonedRequest {
+    pub handle: JobHandle,
+    pub client_job: ClientJob,
+    // How many times request was sent.
+    pub attempts: u32,
+    // Time from first send to giving up.
+    pub elapsed: Duration,
+}
+
+// Server response to one request.
+#[derive(Debug)]
+pub enum Response {
+    PlayerCreated(Result<PlayerCreatedResponseData, PlayerCreatedServerError>),
+    // Time from send to response.
+    Pong(Duration),
+    DataPushDone,
+    // Status of the data store operation, with value of get or failed compare-and-set.
+    Data(DataResponse),
+    // Rooms listed, room joined or left, or error status.
+    Room(RoomResponse),
+    // Targeted push is forwarded, or rejected as a whole.
+    TargetedPushDone(Result<(), TargetedPushError>),
+    // Player number of the player who left, None if client was not a player.
+    PlayerLeave(Option<u8>),
+    Heartbeat,
+    // Player number of the resumed session, None if session can not be resumed.
+    SessionResumed(Option<u8>),
+}
+
+#[derive(Debug)]
+pub enum RequestError {
+    // No response within the timeout given on send.
+    Timeout,
+    // Request was given up, see RequestEvents::on_request_abandoned.
+    Abandoned(AbandonedRequest),
+    // Response was received, but it could not be read.
+    InvalidResponse,
+}
+
+// Targeted push, which server did not forward.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TargetedPushError {
+    // Player numbers, which are not other players of the server.
+    UnknownTargets(Vec<u8>),
+    // No targets given, or request could not be read by server.
+    InvalidRequest,
+}
+
+// Data store operation, which did not succeed.
+#[derive(Debug)]
+pub enum DataError {
+    AccessDenied,
+    // Value is longer than server allows.
+    TooLarge,
+    // Server does not take more keys.
+    StoreFull,
+    // Compare-and-set found another value, holds the current value.
+    CompareFailed(Option<Vec<u8>>),
+    // Key is empty or too long, or request could not be read by server.
+    InvalidRequest,
+    Send(std::io::Error),
+    Request(RequestError),
+}
+
+// Room operation, which did not succeed.
+#[derive(Debug)]
+pub enum RoomError {
+    NotFound,
+    Full,
+    // Player leaves the current room, before joining or creating another.
+    AlreadyInRoom,
+    NotInRoom,
+    // Room name is empty or too long, capacity is zero, or request could not be
+    // read by server.
+    InvalidRequest,
+    // Every room id is in use.
+    NoFreeRoom,
+    Send(std::io::Error),
+    Request(RequestError),
+}
@@ -1,12 +1,18 @@
 pub mod client;
 pub mod datahandlers;
+pub mod peers;
+pub mod pendingrequests;
 mod socketlistener;
 mod socketsender;
 
 use crate::client::client::Client;
 use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
-use crate::client::datahandlers::structs::player::{PlayerCreatedResponseData, PlayerData};
-use std::net::UdpSocket;
+use crate::client::datahandlers::structs::player::{
+    PlayerCreatedResponseData, PlayerData, PlayerLeaveData,
+};
+use crate::client::datahandlers::structs::request::AbandonedRequest;
+use crate::client::peers::PeerPath;
+use crate::requests::room::RoomEvent;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
@@ -21,9 +27,22 @@ pub trait RequestEvents {
         response: Result<PlayerCreatedResponseData, PlayerCreatedServerError>,
     );
     fn on_player_enter_push(&mut self, player: PlayerData);
-    fn on_player_leave(&mut self, raw_data: Vec<u8>);
+    // Other player left, or own leave request was confirmed.
+    fn on_player_leave(&mut self, player: PlayerLeaveData);
     fn on_error(&mut self);
     fn on_connection_state_change(&mut self, new_state: bool);
+    // Request was not answered after all resends, or it could not be sent at all.
+    fn on_request_abandoned(&mut self, _request: AbandonedRequest) {}
+    // Nothing has been received from server within connection timeout.
+    fn on_disconnect(&mut self) {}
+    // Player number of the resumed session, None if session can not be resumed.
+    fn on_session_resumed(&mut self, _player_number: Option<u8>) {}
+    // Other player resumed its session from a new address.
+    fn on_player_reconnect_push(&mut self, _player_number: u8) {}
+    // Path of peer data pushes to the player changed, in peer-to-peer mode.
+    fn on_peer_path_change(&mut self, _player_number: u8, _path: PeerPath) {}
+    // Player joined or left the room of this client, or the room was closed.
+    fn on_room_event(&mut self, _event: RoomEvent) {}
 }
 
 pub fn run<S: 'static>(client: &mut Client, events: Arc<Mutex<S>>)
@@ -33,9 +52,10 @@ where
     // please note, that channels must be initialized first, since other threads need
     // the channel rx and tx attributes.
     client.init_channels();
-    client.init_sender();
-    client.init_job_handler();
+    client.init_sender(Arc::clone(&events));
+    client.init_job_handler(Arc::clone(&events));
     client.init_job_channel_consumer();
+    client.init_heartbeat(Arc::clone(&events));
     client.init_listeners(events);
     // started -->
     client.mark_client_start();
@@ -0,0 +1,149 @@
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Punches are sent this often, while direct path is being opened.
+const PUNCH_INTERVAL: Duration = Duration::from_millis(100);
+// Punches keep direct path open, and retry failed path, at this interval.
+const PEER_KEEPALIVE_INTERVAL: Duration = Duration::from_millis(500);
+// Data goes through server, if direct path is not opened within this time.
+const PUNCH_TIMEOUT: Duration = Duration::from_secs(1);
+// Direct path has failed, if nothing is received from peer within this time.
+const DIRECT_PATH_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PeerPath {
+    // Hole punching is in progress, data goes through server meanwhile.
+    Punching,
+    // Data is sent straight to the peer.
+    Direct,
+    // Direct path failed, data goes through server.
+    Relay,
+}
+
+struct Peer {
+    addr: SocketAddr,
+    path: PeerPath,
+    path_instant: Instant,
+    last_receive_instant: Option<Instant>,
+    last_punch_instant: Option<Instant>,
+}
+
+/*
+ * Peers
+ *
+ * Other players, which client tries to reach directly in peer-to-peer mode.
+ * Server is the rendezvous, it tells the address of each player. Both ends punch
+ * a hole to their NAT by sending to the address of the other. Path turns direct,
+ * when anything is received from the peer, and falls back to server relay, if
+ * nothing is received in time.
+ */
+pub struct Peers {
+    peers: Mutex<HashMap<u8, Peer>>,
+}
+
+impl Peers {
+    pub fn new() -> Peers {
+        Peers {
+            peers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn add(&self, player_number: u8, addr: SocketAddr, now: Instant) {
+        let mut peers_changer = self.peers.lock().unwrap();
+        peers_changer.insert(
+            player_number,
+            Peer {
+                addr,
+                path: PeerPath::Punching,
+                path_instant: now,
+                last_receive_instant: None,
+                last_punch_instant: None,
+            },
+        );
+    }
+
+    pub fn remove(&self, player_number: u8) {
+        let mut peers_changer = self.peers.lock().unwrap();
+        peers_changer.remove(&player_number);
+    }
+
+    pub fn clear(&self) {
+        let mut peers_changer = self.peers.lock().unwrap();
+        peers_changer.clear();
+    }
+
+    pub fn get_path(&self, player_number: u8) -> Option<PeerPath> {
+        let peers_changer = self.peers.lock().unwrap();
+        peers_changer.get(&player_number).map(|peer| peer.path)
+    }
+
+    // Returns address of the peer, if data can be sent to it directly.
+    pub fn get_direct_addr(&self, player_number: u8) -> Option<SocketAddr> {
+        let peers_changer = self.peers.lock().unwrap();
+        match peers_changer.get(&player_number) {
+            Some(peer) if peer.path == PeerPath::Direct => Some(peer.addr),
+            _ => None,
+        }
+    }
+
+    // Returns player number of the peer, and true if its path turned direct.
+    pub fn mark_received(&self, addr: SocketAddr, now: Instant) -> Option<(u8, bool)> {
+        let mut peers_changer = self.peers.lock().unwrap();
+        let (player_number, peer) = peers_changer
+            .iter_mut()
+            .find(|(_player_number, peer)| peer.addr == addr)?;
+        peer.last_receive_instant = Some(now);
+        if peer.path == PeerPath::Direct {
+            return Some((*player_number, false));
+        }
+        peer.path = PeerPath::Direct;
+        peer.path_instant = now;
+        Some((*player_number, true))
+    }
+
+    // Returns addresses, which are due for a punch.
+    pub fn take_punch_addrs(&self, now: Instant) -> Vec<SocketAddr> {
+        let mut peers_changer = self.peers.lock().unwrap();
+        let mut addrs = vec![];
+        for peer in peers_changer.values_mut() {
+            let interval = match peer.path {
+                PeerPath::Punching => PUNCH_INTERVAL,
+                PeerPath::Direct | PeerPath::Relay => PEER_KEEPALIVE_INTERVAL,
+            };
+            let is_due = match peer.last_punch_instant {
+                None => true,
+                Some(instant) => now.duration_since(instant) >= interval,
+            };
+            if is_due {
+                peer.last_punch_instant = Some(now);
+                addrs.push(peer.addr);
+            }
+        }
+        addrs
+    }
+
+    // Paths, which have not received anything in time, fall back to relay.
+    // Returns player numbers of the peers, whose path changed.
+    pub fn fall_back_failed_paths(&self, now: Instant) -> Vec<u8> {
+        let mut peers_changer = self.peers.lock().unwrap();
+        let mut player_numbers = vec![];
+        for (player_number, peer) in peers_changer.iter_mut() {
+            let is_failed = match peer.path {
+                PeerPath::Punching => now.duration_since(peer.path_instant) >= PUNCH_TIMEOUT,
+                PeerPath::Direct => match peer.last_receive_instant {
+                    None => true,
+                    Some(instant) => now.duration_since(instant) >= DIRECT_PATH_TIMEOUT,
+                },
+                PeerPath::Relay => false,
+            };
+            if is_failed {
+                peer.path = PeerPath::Relay;
+                peer.path_instant = now;
+                player_numbers.push(*player_number);
+            }
+        }
+        player_numbers
+    }
+}
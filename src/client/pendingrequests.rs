@@ -0,0 +1,253 @@
+use crate::client::datahandlers::structs::request::{RequestError, Response};
+use crate::requests::JobHandle;
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::{Condvar, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
+
+pub type RequestResult = Result<Response, RequestError>;
+
+struct PendingRequest {
+    send_instant: Instant,
+    // Request without deadline is waited for, until its handle is dropped.
+    deadline: Option<Instant>,
+    result: Option<RequestResult>,
+    waker: Option<Waker>,
+}
+
+/*
+ * Pending requests
+ *
+ * Requests, which caller waits a response for. Socket listener resolves them
+ * with responses, job handler with timeouts and abandoned requests. Waiting
+ * future or thread is woken up, when its request is resolved.
+ */
+pub struct PendingRequests {
+    requests: Mutex<HashMap<JobHandle, PendingRequest>>,
+    // Threads waiting for results are notified, when any request is resolved.
+    resolved: Condvar,
+    // Cancelled requests, which job handler stops resending.
+    cancelled: Mutex<Vec<JobHandle>>,
+}
+
+impl PendingRequests {
+    pub fn new() -> PendingRequests {
+        PendingRequests {
+            requests: Mutex::new(HashMap::new()),
+            resolved: Condvar::new(),
+            cancelled: Mutex::new(vec![]),
+        }
+    }
+
+    // Request must be added before it is sent, so that response can not be missed.
+    pub fn add(&self, handle: JobHandle, timeout: Option<Duration>) {
+        let now = Instant::now();
+        let mut requests_changer = self.requests.lock().unwrap();
+        requests_changer.insert(
+            handle,
+            PendingRequest {
+                send_instant: now,
+                deadline: timeout.map(|timeout| now + timeout),
+                result: None,
+                waker: None,
+            },
+        );
+    }
+
+    pub fn remove(&self, handle: JobHandle) {
+        let mut requests_changer = self.requests.lock().unwrap();
+        requests_changer.remove(&handle);
+    }
+
+    // Request is no longer waited for, and it is not resent.
+    pub fn cancel(&self, handle: JobHandle) {
+        self.remove(handle);
+        self.cancelled.lock().unwrap().push(handle);
+    }
+
+    pub fn take_cancelled(&self) -> Vec<JobHandle> {
+        std::mem::take(&mut *self.cancelled.lock().unwrap())
+    }
+
+    pub fn is_pending(&self, handle: JobHandle) -> bool {
+        let requests_changer = self.requests.lock().unwrap();
+        requests_changer.contains_key(&handle)
+    }
+
+    // Time since request was sent.
+    pub fn get_elapsed(&self, handle: JobHandle) -> Option<Duration> {
+        let requests_changer = self.requests.lock().unwrap();
+        requests_changer
+            .get(&handle)
+            .map(|request| request.send_instant.elapsed())
+    }
+
+    // First result of the request is kept, later ones are ignored.
+    pub fn resolve(&self, handle: JobHandle, result: RequestResult) {
+        let mut requests_changer = self.requests.lock().unwrap();
+        if let Some(request) = requests_changer.get_mut(&handle) {
+            if request.result.is_none() {
+                request.result = Some(result);
+                if let Some(waker) = request.waker.take() {
+                    waker.wake();
+                }
+                self.resolved.notify_all();
+            }
+        }
+    }
+
+    // Requests, which have not been answered within their timeout, are resolved with timeout.
+    pub fn expire(&self, now: Instant) {
+        let mut requests_changer = self.requests.lock().unwrap();
+        for request in requests_changer.values_mut() {
+            let is_late = matches!(request.deadline, Some(deadline) if now >= deadline);
+            if request.result.is_none() && is_late {
+                request.result = Some(Err(RequestError::Timeout));
+                if let Some(waker) = request.waker.take() {
+                    waker.wake();
+                }
+                self.resolved.notify_all();
+            }
+        }
+    }
+
+    // Takes result of the request, if it is resolved.
+    pub fn take_result(&self, handle: JobHandle) -> Option<RequestResult> {
+        let mut requests_changer = self.requests.lock().unwrap();
+        take_resolved(&mut requests_changer, handle)
+    }
+
+    // Blocks until request is resolved or timeout passes.
+    pub fn wait_result(&self, handle: JobHandle, timeout: Duration) -> Option<RequestResult> {
+        let deadline = Instant::now() + timeout;
+        let mut requests_changer = self.requests.lock().unwrap();
+        loop {
+            if let Some(result) = take_resolved(&mut requests_changer, handle) {
+                return Some(result);
+            }
+            let now = Instant::now();
+            if now >= deadline || !requests_changer.contains_key(&handle) {
+                return None;
+            }
+            requests_changer = self
+                .resolved
+                .wait_timeout(requests_changer, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    // Takes result of the request, or stores waker to wake up when result is ready.
+    fn poll_result(&self, handle: JobHandle, waker: &Waker) -> Option<RequestResult> {
+        let mut requests_changer = self.requests.lock().unwrap();
+        let result = take_resolved(&mut requests_changer, handle);
+        if result.is_none() {
+            if let Some(request) = requests_changer.get_mut(&handle) {
+                request.waker = Some(waker.clone());
+            }
+        }
+        result
+    }
+}
+
+// Resolved request is removed, its result is given only once.
+fn take_resolved(
+    requests: &mut HashMap<JobHandle, PendingRequest>,
+    handle: JobHandle,
+) -> Option<RequestResult> {
+    let result = requests.get_mut(&handle)?.result.take()?;
+    requests.remove(&handle);
+    Some(result)
+}
+
+/*
+ * Response future
+ *
+ * Resolves to the response of one request, or to error if request is abandoned
+ * or times out. Future does not depend on any executor, it is woken up from
+ * client's network threads. Dropping the future stops waiting for the response.
+ */
+pub struct ResponseFuture {
+    handle: JobHandle,
+    pending_requests: Arc<PendingRequests>,
+}
+
+impl ResponseFuture {
+    pub fn new(handle: JobHandle, pending_requests: Arc<PendingRequests>) -> ResponseFuture {
+        ResponseFuture {
+            handle,
+            pending_requests,
+        }
+    }
+
+    pub fn get_handle(&self) -> JobHandle {
+        self.handle
+    }
+}
+
+impl Future for ResponseFuture {
+    type Output = RequestResult;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<RequestResult> {
+        match self.pending_requests.poll_result(self.handle, cx.waker()) {
+            Some(result) => Poll::Ready(result),
+            None => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for ResponseFuture {
+    fn drop(&mut self) {
+        self.pending_requests.remove(self.handle);
+    }
+}
+
+/*
+ * Request handle
+ *
+ * Handle of one sent request. Response can be waited for with a timeout, or
+ * checked without blocking. Result is given only once. Dropping the handle stops
+ * waiting for the response, cancelling it stops resending the request too.
+ */
+pub struct RequestHandle {
+    handle: JobHandle,
+    pending_requests: Arc<PendingRequests>,
+}
+
+impl RequestHandle {
+    pub fn new(handle: JobHandle, pending_requests: Arc<PendingRequests>) -> RequestHandle {
+        RequestHandle {
+            handle,
+            pending_requests,
+        }
+    }
+
+    pub fn get_handle(&self) -> JobHandle {
+        self.handle
+    }
+
+    // Blocks until response is received, request is abandoned or timeout passes.
+    pub fn wait(&self, timeout: Duration) -> RequestResult {
+        self.pending_requests
+            .wait_result(self.handle, timeout)
+            .unwrap_or(Err(RequestError::Timeout))
+    }
+
+    // Result of the request, if it is already resolved.
+    pub fn try_result(&self) -> Option<RequestResult> {
+        self.pending_requests.take_result(self.handle)
+    }
+
+    pub fn cancel(self) {
+        self.pending_requests.cancel(self.handle);
+    }
+}
+
+impl Drop for RequestHandle {
+    fn drop(&mut self) {
+        self.pending_requests.remove(self.handle);
+    }
+}
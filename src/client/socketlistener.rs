@@ -1,35 +1,75 @@
-use crate::client::datahandlers::{playercreatedresponse, playerenterpush};
+use crate::client::datahandlers::structs::player::{PlayerData, PlayerLeaveData};
+use crate::client::datahandlers::structs::request::{RequestError, Response, TargetedPushError};
+use crate::client::datahandlers::{playercreatedresponse, playerenterpush, playerleavepush};
+use crate::client::peers::{PeerPath, Peers};
+use crate::client::pendingrequests::{PendingRequests, RequestResult};
 use crate::client::Arc;
 use crate::client::Mutex;
 use crate::client::RequestEvents;
-use crate::client::UdpSocket;
-use crate::protocol::bithelpers::get_u8_from_bit_slice;
 use crate::protocol::Protocol;
-use crate::requests::jobtype::get_job_type;
+use crate::requests::batching::split_batch;
+use crate::requests::challenge::is_challenged;
+use crate::requests::data::parse_data_response;
+use crate::requests::delivery::{DeliveryMode, ReceiveChannels, Sequence};
+use crate::requests::fragmentation::{
+    create_fragment_ack, get_fragment_index_and_count, Reassembler, RECEIVE_BUFFER_SIZE,
+};
+use crate::requests::header::{Header, HEADER_LENGTH};
+use crate::requests::heartbeat::get_heartbeat_probe;
+use crate::requests::jobs::Jobs;
+use crate::requests::leave::LeaveReason;
+use crate::requests::room::{parse_room_push, parse_room_response};
+use crate::requests::targetedpush::{parse_targeted_push_response, TargetedPushStatus};
+use crate::requests::ClientJob;
 use crate::requests::Job;
 use crate::requests::JobAction;
-use crate::requests::{JobType, ServerJob};
+use crate::requests::JobHandle;
+use crate::requests::ServerJob;
+use crate::socket::Transport;
+use std::collections::VecDeque;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::Sender;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
+
+// Resent pushes are recognized among this many latest reliable pushes.
+const MAX_RECEIVED_PUSHES: usize = 256;
 
 pub struct SocketListener<S: 'static> {
-    //jobs: Arc<Mutex<Jobs>>,
-    job_channel_tx: Sender<(JobAction, u8, Option<Job>)>,
+    // Jobs keeps track of latest received data, for connection timeout.
+    jobs: Arc<Jobs>,
+    // Responses resolve requests, which caller waits for.
+    pending_requests: Arc<PendingRequests>,
+    job_channel_tx: Sender<(JobAction, JobHandle, Option<Job>)>,
     protocols: Arc<Protocol>,
     time_to_die: Arc<AtomicBool>,
-    socket: Arc<UdpSocket>,
+    socket: Arc<dyn Transport>,
+    // Datagrams from other addresses than server and peers are ignored.
+    server_addr: SocketAddr,
+    // Other players reached directly, None if peer-to-peer mode is not enabled.
+    peers: Option<Arc<Peers>>,
     events: Arc<Mutex<S>>,
     error_state_current: Arc<AtomicBool>,
     error_state_previous: Arc<AtomicBool>,
+    // Ordering of server originated pushes, one channel per delivery mode.
+    receive_channels: ReceiveChannels<(Header, Vec<u8>)>,
+    reassembler: Reassembler<SocketAddr>,
+    // Latest reliable pushes, server resends push if acknowledgement is lost.
+    received_pushes: VecDeque<(DeliveryMode, Sequence)>,
 }
 
 impl<S: RequestEvents + Send + Sync> SocketListener<S> {
     pub fn new(
-        job_channel_tx: Sender<(JobAction, u8, Option<Job>)>,
-        socket: Arc<UdpSocket>,
+        jobs: Arc<Jobs>,
+        pending_requests: Arc<PendingRequests>,
+        job_channel_tx: Sender<(JobAction, JobHandle, Option<Job>)>,
+        socket: Arc<dyn Transport>,
+        server_addr: SocketAddr,
+        peers: Option<Arc<Peers>>,
         protocols: Arc<Protocol>,
         time_to_die: Arc<AtomicBool>,
         events: Arc<Mutex<S>>,
@@ -40,14 +80,20 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
         S: RequestEvents + Send + Sync,
     {
         SocketListener {
-            //jobs: jobs,
+            jobs: jobs,
+            pending_requests: pending_requests,
             job_channel_tx: job_channel_tx,
             socket: socket,
+            server_addr: server_addr,
+            peers: peers,
             protocols: protocols,
             time_to_die: time_to_die,
             events: events,
             error_state_current: error_state_current,
             error_state_previous: error_state_previous,
+            receive_channels: ReceiveChannels::new(),
+            reassembler: Reassembler::new(),
+            received_pushes: VecDeque::new(),
         }
     }
 
@@ -59,6 +105,8 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
             }
 
             let raw_data_maybe = self.read_socket();
+            self.punch_peers();
+            self.release_late_pushes();
 
             // if conenction state has changed, raise event.
             if self.error_state_current.load(Ordering::SeqCst)
@@ -74,32 +122,130 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
             }
             // Thread sleeps 1 second, if connection is lost...
             if raw_data_maybe.is_none() {
-                thread::sleep(Duration::new(1, 0));
+                if self.error_state_current.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::new(1, 0));
+                }
                 continue;
             }
             // Data is received, lets work on it.
-            let raw_data = raw_data_maybe.unwrap();
+            let (raw_data, src_addr) = raw_data_maybe.unwrap();
 
-            // 5 zeroes comes from thread killer thread, no need for further analyse.
-            if raw_data.len() == 5 && (&raw_data).iter().all(|&item| item == 0) {
+            if !self.is_server_addr(src_addr) {
+                self.handle_peer_datagram(src_addr, raw_data);
                 continue;
             }
 
-            let index_and_type_maybe = self.get_index_and_type(&raw_data);
+            // Batch datagram holds several datagrams, they are handled one by one.
+            for raw_data in split_batch(raw_data) {
+                self.handle_datagram(src_addr, raw_data);
+            }
+        }
+    }
 
-            // Received misformed data
-            if index_and_type_maybe.is_none() {
-                continue;
+    fn handle_datagram(&mut self, src_addr: SocketAddr, raw_data: Vec<u8>) {
+        let header_maybe = self.get_header(&raw_data);
+
+        // Received misformed data
+        if header_maybe.is_none() {
+            return;
+        }
+
+        let header = header_maybe.unwrap();
+
+        // Server is alive.
+        self.jobs.mark_received();
+
+        // Fragment acknowledgements and fragments are handled first.
+        let reassembled = self.reassemble(src_addr, header, raw_data);
+        if reassembled.is_none() {
+            return;
+        }
+        let (header, raw_data) = reassembled.unwrap();
+
+        // Server originated pushes are released in the order their delivery mode
+        // requires. Responses are matched to jobs, so they are handled at once.
+        let received = if is_push(&header.get_server_job()) {
+            // Push, which has already been received, is only acknowledged again.
+            if !self.acknowledge_push(&header) {
+                return;
             }
+            let (mode, sequence) = (header.delivery_mode, header.sequence);
+            self.receive_channels
+                .receive(mode, sequence, (header, raw_data))
+        } else {
+            vec![(header, raw_data)]
+        };
+        self.handle_received(received);
+    }
 
-            let (job_index, job_type) = index_and_type_maybe.unwrap();
-            // [2..] means that we will remove job_index and job_type from raw data.
-            // Protocol datahandler funtions use only data without job index and type.
-            self.create_request_event(job_index, job_type.0, raw_data[2..].to_vec());
+    // Ordered pushes, which waited a push the server did not deliver, are handled.
+    fn release_late_pushes(&mut self) {
+        let released = self.receive_channels.release_late(Instant::now());
+        self.handle_received(released);
+    }
+
+    fn handle_received(&mut self, received: Vec<(Header, Vec<u8>)>) {
+        for (header, raw_data) in received {
+            // [HEADER_LENGTH..] means that we will remove header from raw data.
+            // Protocol datahandler funtions use only data without header.
+            self.resolve_pending_request(&header, &raw_data[HEADER_LENGTH..]);
+            self.create_request_event(&header, raw_data[HEADER_LENGTH..].to_vec());
         }
     }
 
-    fn send_to_job_channel(&self, job_action: JobAction, job_handle: u8, job: Option<Job>) -> bool {
+    // Response is given to the caller, who waits for it.
+    fn resolve_pending_request(&self, header: &Header, raw_data: &[u8]) {
+        if is_push(&header.get_server_job()) || !self.pending_requests.is_pending(header.handle) {
+            return;
+        }
+
+        let result: RequestResult = match header.get_server_job() {
+            ServerJob::PlayerCreatedResponse => {
+                match playercreatedresponse::structurize_raw_data(&self.protocols, raw_data) {
+                    Err(_) => Err(RequestError::InvalidResponse),
+                    Ok(response_data) => Ok(Response::PlayerCreated(response_data)),
+                }
+            }
+            ServerJob::PongResponse => {
+                let elapsed = self
+                    .pending_requests
+                    .get_elapsed(header.handle)
+                    .unwrap_or_default();
+                Ok(Response::Pong(elapsed))
+            }
+            ServerJob::DataPushDoneResponse => Ok(Response::DataPushDone),
+            ServerJob::DataResponse => match parse_data_response(raw_data) {
+                None => Err(RequestError::InvalidResponse),
+                Some(response) => Ok(Response::Data(response)),
+            },
+            ServerJob::TargetedPushResponse => match parse_targeted_push_response(raw_data) {
+                None => Err(RequestError::InvalidResponse),
+                Some((status, rejected_player_numbers)) => Ok(Response::TargetedPushDone(
+                    get_targeted_push_result(status, rejected_player_numbers),
+                )),
+            },
+            ServerJob::RoomResponse => match parse_room_response(raw_data) {
+                None => Err(RequestError::InvalidResponse),
+                Some(response) => Ok(Response::Room(response)),
+            },
+            ServerJob::PlayerLeaveResponse => {
+                Ok(Response::PlayerLeave(get_left_player_number(raw_data)))
+            }
+            ServerJob::HeartbeatResponse => Ok(Response::Heartbeat),
+            ServerJob::ResumeResponse => Ok(Response::SessionResumed(get_resumed_player_number(
+                raw_data,
+            ))),
+            _ => return,
+        };
+        self.pending_requests.resolve(header.handle, result);
+    }
+
+    fn send_to_job_channel(
+        &self,
+        job_action: JobAction,
+        job_handle: JobHandle,
+        job: Option<Job>,
+    ) -> bool {
         let result = self.job_channel_tx.send((job_action, job_handle, job));
         if result.is_err() {
             println!("Job channel reveicer hang up.");
@@ -108,12 +254,182 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
         true
     }
 
-    // Read socket, blocking function, return None is socket read fails for connection error.
-    fn read_socket(&self) -> Option<Vec<u8>> {
-        let mut buf = [0; 200];
+    // Reliable push is acknowledged, so that server stops resending it. Returns false,
+    // if push has already been received, acknowledgement was lost then.
+    fn acknowledge_push(&mut self, header: &Header) -> bool {
+        if !header.delivery_mode.is_reliable() {
+            return true;
+        }
+
+        let ack_header = Header::new(
+            header.handle,
+            (header.get_server_job(), ClientJob::PushAck),
+            DeliveryMode::Unreliable,
+            header.sequence,
+        );
+        if self.send_to_server(&ack_header.get_raw_data()).is_err() {
+            println!("Failed to acknowledge push {}.", header.handle);
+        }
+
+        let push = (header.delivery_mode, header.sequence);
+        if self.received_pushes.contains(&push) {
+            return false;
+        }
+        if self.received_pushes.len() >= MAX_RECEIVED_PUSHES {
+            self.received_pushes.pop_front();
+        }
+        self.received_pushes.push_back(push);
+        true
+    }
+
+    fn send_to_server(&self, datagram: &[u8]) -> std::io::Result<usize> {
+        self.socket.send_to(datagram, self.server_addr)
+    }
+
+    // IPv4 address may be seen as IPv4-mapped IPv6 address on dual-stack socket.
+    fn is_server_addr(&self, addr: SocketAddr) -> bool {
+        addr.port() == self.server_addr.port()
+            && addr.ip().to_canonical() == self.server_addr.ip().to_canonical()
+    }
+
+    // Datagram from a peer opens direct path to it. Datagrams from unknown
+    // addresses are ignored.
+    fn handle_peer_datagram(&self, src_addr: SocketAddr, raw_data: Vec<u8>) {
+        let peers = match &self.peers {
+            None => return,
+            Some(peers) => peers,
+        };
+        let (player_number, is_path_changed) = match peers.mark_received(src_addr, Instant::now()) {
+            None => return,
+            Some(received) => received,
+        };
+        if is_path_changed {
+            let mut events_changer = self.events.lock().unwrap();
+            (*events_changer).on_peer_path_change(player_number, PeerPath::Direct);
+        }
+
+        let header = match Header::from_raw_data(&raw_data) {
+            None => return,
+            Some(header) => header,
+        };
+        if header.get_client_job() == ClientJob::PeerDataPush {
+            let mut events_changer = self.events.lock().unwrap();
+            (*events_changer)
+                .on_data_push_received(player_number, raw_data[HEADER_LENGTH..].to_vec());
+        }
+    }
+
+    // Punches keep NAT mappings towards peers open. Paths, which have failed, fall
+    // back to server relay.
+    fn punch_peers(&self) {
+        let peers = match &self.peers {
+            None => return,
+            Some(peers) => peers,
+        };
+        let now = Instant::now();
+        let punch = Header::new(
+            0,
+            (ServerJob::NoServerAction, ClientJob::PeerPunch),
+            DeliveryMode::Unreliable,
+            0,
+        )
+        .get_raw_data();
+        for addr in peers.take_punch_addrs(now) {
+            if self.socket.send_to(&punch, addr).is_err() {
+                println!("Failed to punch peer {}.", addr);
+            }
+        }
+        for player_number in peers.fall_back_failed_paths(now) {
+            let mut events_changer = self.events.lock().unwrap();
+            (*events_changer).on_peer_path_change(player_number, PeerPath::Relay);
+        }
+    }
+
+    // Addresses of other players are given by server, they are punched in
+    // peer-to-peer mode.
+    fn add_peer(&self, player: &PlayerData) {
+        if let (Some(peers), Some(addr)) = (&self.peers, player.addr) {
+            peers.add(player.player_number, addr, Instant::now());
+        }
+    }
+
+    fn remove_peers(&self, player_numbers: &[u8]) {
+        if let Some(peers) = &self.peers {
+            for player_number in player_numbers {
+                peers.remove(*player_number);
+            }
+        }
+    }
+
+    // Server verifies address of a new player, player enter or resume request is sent
+    // again with the cookie of the challenge. Only reliable requests are kept as jobs,
+    // so unreliable request can not answer the challenge.
+    fn answer_challenge(&self, header: &Header, cookie: &[u8]) {
+        let mut jobs_changer = self.jobs.jobs.lock().unwrap();
+        let job = match jobs_changer.get_mut(&header.handle) {
+            None => return,
+            Some(job) => job,
+        };
+        if !is_challenged(&job.get_client_job()) || !job.set_challenge_cookie(cookie) {
+            return;
+        }
+        for datagram in job.get_datagrams() {
+            if self.send_to_server(&datagram).is_err() {
+                println!("Failed to answer challenge {}.", header.handle);
+            }
+        }
+    }
+
+    // Returns whole message, or None if datagram is fragment acknowledgement or fragment
+    // of message, which is not yet fully received.
+    fn reassemble(
+        &mut self,
+        src_addr: SocketAddr,
+        header: Header,
+        raw_data: Vec<u8>,
+    ) -> Option<(Header, Vec<u8>)> {
+        if header.is_fragment_ack() {
+            // Job is kept, until every fragment is acknowledged or response is received.
+            if raw_data.len() > HEADER_LENGTH {
+                let fragment_index = raw_data[HEADER_LENGTH];
+                self.send_to_job_channel(
+                    JobAction::ACKFRAGMENT(fragment_index),
+                    header.handle,
+                    None,
+                );
+            }
+            return None;
+        }
+
+        if header.is_fragment() {
+            // Every fragment of reliable push is acknowledged, so that server
+            // resends only fragments that are lost.
+            if header.delivery_mode.is_reliable() && is_push(&header.get_server_job()) {
+                if let Some((fragment_index, _fragment_count)) =
+                    get_fragment_index_and_count(&raw_data)
+                {
+                    let ack = create_fragment_ack(&header, fragment_index);
+                    if self.send_to_server(&ack).is_err() {
+                        println!("Failed to acknowledge fragment {}.", fragment_index);
+                    }
+                }
+            }
+            let raw_data = self.reassembler.receive(src_addr, &header, &raw_data)?;
+            return Some((header.with_flags(0), raw_data));
+        }
+
+        Some((header, raw_data))
+    }
+
+    // Read socket, blocking function, return None is socket read fails for connection error,
+    // or if nothing is received within read timeout.
+    fn read_socket(&self) -> Option<(Vec<u8>, SocketAddr)> {
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
         let result = self.socket.recv_from(&mut buf);
-        if result.is_err() {
-            self.error_state_current.store(true, Ordering::SeqCst);
+        if let Err(e) = result {
+            if e.kind() != ErrorKind::WouldBlock && e.kind() != ErrorKind::TimedOut {
+                self.error_state_current.store(true, Ordering::SeqCst);
+            }
             return None;
         }
 
@@ -121,57 +437,48 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
             self.error_state_current.store(false, Ordering::SeqCst);
         }
 
-        let (number_of_bytes, _src_addr) = result.unwrap();
+        let (number_of_bytes, src_addr) = result.unwrap();
         let raw_data = &mut buf[..number_of_bytes];
-        Some(raw_data.to_vec())
+        Some((raw_data.to_vec(), src_addr))
     }
 
-    fn get_index_and_type(&self, raw_data: &Vec<u8>) -> Option<(u8, JobType)> {
-        // package is too short.
-        if raw_data.len() < 2 {
+    fn get_header(&self, raw_data: &[u8]) -> Option<Header> {
+        let header = Header::from_raw_data(raw_data);
+        // package is too short or garbage, does not hold job information.
+        if header.is_none() {
             // Fail package that has no job handle (using 0), and now raw_data to analyse (using None)
             self.send_to_job_channel(JobAction::INCFAILED, 0, None);
             //let mut jobs_changer = self.jobs.lock().unwrap();
             //(*jobs_changer).packages_failed += 1;
             return None;
         }
-        let index = raw_data[0];
-
-        let server_client = (
-            get_u8_from_bit_slice(raw_data[1], 0, 4),
-            get_u8_from_bit_slice(raw_data[1], 4, 4),
-        );
-
-        let job_type = get_job_type(&server_client);
-        // package is garbage, does not hold job information.
-        if job_type.is_none() {
-            //let mut jobs_changer = self.jobs.lock().unwrap();
-            //(*jobs_changer).packages_failed += 1;
-            self.send_to_job_channel(JobAction::INCFAILED, 0, None);
-            return None;
-        }
-        Some((index, job_type.unwrap()))
+        header
     }
 
     // Call implemented trait (RequestEvent) methods according
     // what kind of (JobType) data is reveiced.
-    fn create_request_event(
-        &self,
-        job_handle: u8,
-        server_response_type: ServerJob,
-        mut raw_data: Vec<u8>,
-    ) where
+    fn create_request_event(&mut self, header: &Header, mut raw_data: Vec<u8>)
+    where
         S: RequestEvents + Send + Sync,
     {
         let job_duration = Duration::new(0, 0);
+        let job_handle = header.handle;
+        let server_response_type = header.get_server_job();
 
         // Job handling for operations fired from client.
         match &server_response_type {
             ServerJob::DataPush => { /* no job handling for server originated push operations */ }
             ServerJob::PlayerEnterPush => { /* no job handling */ }
             ServerJob::PlayerLeavePush => {}
+            ServerJob::PlayerReconnectPush => {}
+            ServerJob::RoomPush => {}
+            ServerJob::HeartbeatResponse => { /* heartbeats are not followed up */ }
+            ServerJob::ChallengeResponse => { /* job is sent again with the cookie */ }
             __ => {
-                self.send_to_job_channel(JobAction::REMOVE, job_handle, None);
+                // Unreliable requests are not followed up as jobs.
+                if header.delivery_mode.is_reliable() {
+                    self.send_to_job_channel(JobAction::REMOVE, job_handle, None);
+                }
                 self.send_to_job_channel(JobAction::INCHANDLED, job_handle, None);
 
                 /*
@@ -185,7 +492,6 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
                 }
                 let job = job_maybe.unwrap();
                 job_duration = job.finish();
-                (*jobs_changer).add_finish_time(job_duration);
                 (*jobs_changer).packages_handled += 1;
                 */
             }
@@ -214,20 +520,8 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
                 let mut events_changer = self.events.lock().unwrap();
                 (*events_changer).on_data_push_received(player, raw_data);
             }
-            ServerJob::DataResponse => {
-                /*
-                println!("request");
-                if raw_data.len() < 4 {
-                    let mut jobs_changer = self.jobs.lock().unwrap();
-                    (*jobs_changer).packages_failed += 1;
-                    let mut events_changer = self.events.lock().unwrap();
-                    (*events_changer).on_error();
-                    return;
-                }
-                */
-                let player = raw_data[0];
-                let mut events_changer = self.events.lock().unwrap();
-                (*events_changer).on_data_push_received(player, raw_data);
+            ServerJob::DataResponse | ServerJob::TargetedPushResponse | ServerJob::RoomResponse => {
+                // Response is given to the waiting request, not to events.
             }
             ServerJob::PlayerCreatedResponse => {
                 /*
@@ -256,6 +550,14 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
                             (*events_changer).on_player_created(Err(e));
                         }
                         Ok(player_created_response_data) => {
+                            // Player list holds this client too.
+                            let own_player_number =
+                                player_created_response_data.player.player_number;
+                            for other_player in &player_created_response_data.others_players {
+                                if other_player.player_number != own_player_number {
+                                    self.add_peer(other_player);
+                                }
+                            }
                             (*events_changer).on_player_created(Ok(player_created_response_data));
                         }
                     },
@@ -275,14 +577,74 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
                 if player_data.is_err() {
                     (*events_changer).on_error();
                 } else {
-                    (*events_changer).on_player_enter_push(player_data.unwrap());
+                    let player_data = player_data.unwrap();
+                    self.add_peer(&player_data);
+                    (*events_changer).on_player_enter_push(player_data);
                 }
             }
-            ServerJob::PlayerLeavePush => {}
-            ServerJob::PlayerLeaveResponse => {
-                println!("leave");
+            ServerJob::PlayerLeavePush => {
+                let player_leave_data =
+                    match playerleavepush::structurize_raw_data(&self.protocols, &raw_data) {
+                        Err(_) => {
+                            self.send_to_job_channel(JobAction::INCFAILED, 0, None);
+                            return;
+                        }
+                        Ok(player_leave_data) => player_leave_data,
+                    };
+                self.remove_peers(&[player_leave_data.player_number]);
                 let mut events_changer = self.events.lock().unwrap();
-                (*events_changer).on_player_leave(raw_data);
+                (*events_changer).on_player_leave(player_leave_data);
+            }
+            ServerJob::HeartbeatResponse => {
+                // Probe is sent back with the next heartbeat.
+                if let Some(probe_id) = get_heartbeat_probe(&raw_data) {
+                    self.jobs.set_heartbeat_probe(probe_id, Instant::now());
+                }
+            }
+            ServerJob::ChallengeResponse => {
+                self.answer_challenge(header, &raw_data);
+            }
+            ServerJob::ResumeResponse => {
+                // Server starts sequences of its pushes from 0 in the resumed session.
+                let player_number = get_resumed_player_number(&raw_data);
+                if player_number.is_some() {
+                    self.receive_channels = ReceiveChannels::new();
+                }
+                let mut events_changer = self.events.lock().unwrap();
+                (*events_changer).on_session_resumed(player_number);
+            }
+            ServerJob::PlayerReconnectPush => {
+                // Player number of the player who reconnected.
+                if raw_data.is_empty() {
+                    self.send_to_job_channel(JobAction::INCFAILED, 0, None);
+                    return;
+                }
+                // New address of the player is not known, data is relayed by server.
+                self.remove_peers(&raw_data[..1]);
+                let mut events_changer = self.events.lock().unwrap();
+                (*events_changer).on_player_reconnect_push(raw_data[0]);
+            }
+            ServerJob::RoomPush => match parse_room_push(&raw_data) {
+                None => {
+                    self.send_to_job_channel(JobAction::INCFAILED, 0, None);
+                }
+                Some(event) => {
+                    let mut events_changer = self.events.lock().unwrap();
+                    (*events_changer).on_room_event(event);
+                }
+            },
+            ServerJob::PlayerLeaveResponse => {
+                // Own player left, so there are no peers anymore.
+                if let Some(peers) = &self.peers {
+                    peers.clear();
+                }
+                if let Some(player_number) = get_left_player_number(&raw_data) {
+                    let mut events_changer = self.events.lock().unwrap();
+                    (*events_changer).on_player_leave(PlayerLeaveData {
+                        player_number,
+                        reason: LeaveReason::Quit,
+                    });
+                }
             }
             ServerJob::PongResponse => {
                 println!("ping");
@@ -293,3 +655,44 @@ impl<S: RequestEvents + Send + Sync> SocketListener<S> {
         }
     }
 }
+
+fn is_push(server_job: &ServerJob) -> bool {
+    matches!(
+        server_job,
+        ServerJob::DataPush
+            | ServerJob::PlayerEnterPush
+            | ServerJob::PlayerLeavePush
+            | ServerJob::PlayerReconnectPush
+            | ServerJob::RoomPush
+    )
+}
+
+fn get_targeted_push_result(
+    status: TargetedPushStatus,
+    rejected_player_numbers: Vec<u8>,
+) -> Result<(), TargetedPushError> {
+    match status {
+        TargetedPushStatus::Done => Ok(()),
+        TargetedPushStatus::UnknownTargets => {
+            Err(TargetedPushError::UnknownTargets(rejected_player_numbers))
+        }
+        TargetedPushStatus::InvalidRequest => Err(TargetedPushError::InvalidRequest),
+    }
+}
+
+// Player leave response holds player number of the left player, nothing if
+// client was not a player.
+fn get_left_player_number(raw_data: &[u8]) -> Option<u8> {
+    match raw_data {
+        [player_number, ..] if *player_number != 0 => Some(*player_number),
+        _ => None,
+    }
+}
+
+// Resume response holds status 1 and player number, if session is resumed.
+fn get_resumed_player_number(raw_data: &[u8]) -> Option<u8> {
+    match raw_data {
+        [1, player_number, ..] => Some(*player_number),
+        _ => None,
+    }
+}
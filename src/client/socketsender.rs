@@ -1,49 +1,213 @@
+use crate::client::datahandlers::structs::request::{AbandonedRequest, RequestError};
+use crate::client::pendingrequests::PendingRequests;
 use crate::client::Arc;
-use crate::client::UdpSocket;
+use crate::client::Mutex;
+use crate::client::RequestEvents;
+use crate::requests::batching::Batch;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::jobs::Jobs;
+use crate::requests::ClientJob;
 use crate::requests::Job;
 use crate::requests::JobAction;
+use crate::requests::JobHandle;
+use crate::socket::Transport;
+use std::collections::HashMap;
+use std::net::SocketAddr;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
 use std::sync::mpsc::Sender;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
-pub struct SocketSender {
-    socket: Arc<UdpSocket>,
-    send_channel_rx: Receiver<(Vec<u8>, Job)>,
-    job_channel_tx: Sender<(JobAction, u8, Option<Job>)>,
+pub struct SocketSender<S>
+where
+    S: RequestEvents,
+{
+    socket: Arc<dyn Transport>,
+    server_addr: SocketAddr,
+    send_channel_rx: Receiver<(Vec<u8>, Arc<Job>)>,
+    job_channel_tx: Sender<(JobAction, JobHandle, Option<Job>)>,
+    // Jobs holds congestion control, which limits send rate.
+    jobs: Arc<Jobs>,
+    // Caller waiting for abandoned request is informed too.
+    pending_requests: Arc<PendingRequests>,
+    events: Arc<Mutex<S>>,
+    // Handle of the latest request reported as abandoned, so that failing
+    // fragments of one request are reported only once.
+    abandoned_handle: Option<JobHandle>,
+    // Datagrams waiting for flush per destination, and jobs they belong to.
+    batches: HashMap<SocketAddr, (Batch, Vec<Arc<Job>>)>,
+    flush_interval: Duration,
     error_state_current: Arc<AtomicBool>,
     error_state_previous: Arc<AtomicBool>,
     time_to_die: Arc<AtomicBool>,
 }
 
-impl SocketSender {
+impl<S: RequestEvents> SocketSender<S> {
     pub fn new(
-        socket: Arc<UdpSocket>,
-        send_channel_rx: Receiver<(Vec<u8>, Job)>,
-        job_channel_tx: Sender<(JobAction, u8, Option<Job>)>,
+        socket: Arc<dyn Transport>,
+        server_addr: SocketAddr,
+        send_channel_rx: Receiver<(Vec<u8>, Arc<Job>)>,
+        job_channel_tx: Sender<(JobAction, JobHandle, Option<Job>)>,
+        jobs: Arc<Jobs>,
+        pending_requests: Arc<PendingRequests>,
         time_to_die: Arc<AtomicBool>,
+        events: Arc<Mutex<S>>,
+        flush_interval: Duration,
         error_state_current: Arc<AtomicBool>,
         error_state_previous: Arc<AtomicBool>,
-    ) -> SocketSender {
+    ) -> SocketSender<S> {
         SocketSender {
             time_to_die: time_to_die,
             socket: socket,
+            server_addr: server_addr,
             send_channel_rx: send_channel_rx,
             job_channel_tx: job_channel_tx,
+            jobs: jobs,
+            pending_requests: pending_requests,
+            events: events,
+            abandoned_handle: None,
+            batches: HashMap::new(),
+            flush_interval: flush_interval,
             error_state_current: error_state_current,
             error_state_previous: error_state_previous,
         }
     }
 
-    fn read_raw_data_from_send_channel(&self) -> Option<(Vec<u8>, Job)> {
-        let result = self.send_channel_rx.recv();
-        if result.is_err() {
-            println!("Send channel reveicer hang up.");
-            return None;
+    // Waits for next datagram. If batch holds datagrams, waits only until next flush.
+    fn read_raw_data_from_send_channel(
+        &self,
+        flush_instant: Instant,
+    ) -> Result<(Vec<u8>, Arc<Job>), RecvTimeoutError> {
+        if self.batches.values().all(|(batch, _jobs)| batch.is_empty()) {
+            return self
+                .send_channel_rx
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected);
         }
-        Some(result.unwrap())
+        let wait_time = self
+            .flush_interval
+            .checked_sub(flush_instant.elapsed())
+            .unwrap_or_default();
+        self.send_channel_rx.recv_timeout(wait_time)
+    }
+
+    // Datagram is added to batch of its destination, which is server unless job is
+    // sent to a peer. Empty data asks to flush the batches at once.
+    fn queue(&mut self, raw_data: Vec<u8>, job: Arc<Job>) {
+        if raw_data.is_empty() {
+            self.flush();
+            return;
+        }
+
+        if !self.wait_for_send_budget(&raw_data) {
+            return;
+        }
+
+        let destination = job.get_destination().unwrap_or(self.server_addr);
+        let (batch, batched_jobs) = self
+            .batches
+            .entry(destination)
+            .or_insert_with(|| (Batch::new(), vec![]));
+        let full_batch = batch
+            .push(raw_data)
+            .map(|datagram| (datagram, std::mem::take(batched_jobs)));
+        batched_jobs.push(job);
+        if let Some((datagram, jobs)) = full_batch {
+            self.send_datagram(&datagram, destination, jobs);
+        }
+    }
+
+    fn flush(&mut self) {
+        let mut full_batches = vec![];
+        for (destination, (batch, batched_jobs)) in self.batches.iter_mut() {
+            if let Some(datagram) = batch.take() {
+                full_batches.push((*destination, datagram, std::mem::take(batched_jobs)));
+            }
+        }
+        for (destination, datagram, jobs) in full_batches {
+            self.send_datagram(&datagram, destination, jobs);
+        }
+        // Batches of peers are created again when needed, old peer addresses are not kept.
+        let server_addr = self.server_addr;
+        self.batches
+            .retain(|destination, _batch| *destination == server_addr);
+    }
+
+    fn send_datagram(&mut self, datagram: &[u8], destination: SocketAddr, jobs: Vec<Arc<Job>>) {
+        let result = self.socket.send_to(datagram, destination);
+        match result {
+            Err(e) => {
+                // send fails ... JOB REMOVAL from jobs list
+                for job in jobs {
+                    // Peer datagrams are not followed up, nobody waits for them.
+                    if job.get_destination().is_some() {
+                        continue;
+                    }
+                    // Send fails only during shutdown, when job handler is already gone.
+                    let _ = self
+                        .job_channel_tx
+                        .send((JobAction::ABANDON, job.get_handle(), None));
+                    self.raise_request_abandoned(&job);
+                }
+
+                self.error_state_previous.store(true, Ordering::SeqCst);
+                self.error_state_event_raiser(Some(e.to_string()));
+            }
+            Ok(_send_buffer_size) => {
+                self.jobs.mark_sent();
+                if self.error_state_current.load(Ordering::SeqCst) == true {
+                    self.error_state_previous.store(false, Ordering::SeqCst);
+                }
+                self.error_state_event_raiser(None);
+            }
+        }
+    }
+
+    // Request that can not be sent is given up, let application know about it.
+    fn raise_request_abandoned(&mut self, job: &Job) {
+        let client_job = job.get_client_job();
+        if client_job == ClientJob::NoClientAction
+            || self.abandoned_handle == Some(job.get_handle())
+        {
+            return;
+        }
+        self.abandoned_handle = Some(job.get_handle());
+
+        let abandoned_request = AbandonedRequest {
+            handle: job.get_handle(),
+            client_job,
+            attempts: job.requested_count as u32 + 1,
+            elapsed: job.get_elapsed(Instant::now()),
+        };
+        self.pending_requests.resolve(
+            job.get_handle(),
+            Err(RequestError::Abandoned(abandoned_request.clone())),
+        );
+        let mut events_changer = self.events.lock().unwrap();
+        (*events_changer).on_request_abandoned(abandoned_request);
+    }
+
+    // Returns false, if datagram is dropped for congestion. Unreliable datagrams are
+    // dropped when send budget is exhausted, reliable datagrams wait for budget.
+    fn wait_for_send_budget(&self, raw_data: &[u8]) -> bool {
+        let mode = match Header::from_raw_data(raw_data) {
+            Some(header) => header.delivery_mode,
+            None => DeliveryMode::Unreliable,
+        };
+
+        while !self.jobs.try_send(raw_data.len(), mode) {
+            if !mode.is_reliable() || self.time_to_die.load(Ordering::Relaxed) {
+                println!("Send budget exhausted, datagram dropped.");
+                return false;
+            }
+            thread::sleep(self.jobs.get_send_wait_time(raw_data.len()));
+        }
+        true
     }
 
     fn error_state_event_raiser(&self, _error_message: Option<String>) {
@@ -69,40 +233,33 @@ impl SocketSender {
     }
 
     pub fn init(&mut self) {
+        let mut flush_instant = Instant::now();
         loop {
             // break loop if server is closing...
             if self.time_to_die.load(Ordering::Relaxed) {
                 break;
             }
 
-            let raw_data_maybe = self.read_raw_data_from_send_channel();
-
-            // Thread sleeps 100 millis, if sender channel producers hang up...
-            // Something must be fucked, if this happens.
-            if raw_data_maybe.is_none() {
-                thread::sleep(Duration::from_millis(100));
-                continue;
-            }
-
-            let (raw_data, job) = raw_data_maybe.unwrap();
-
-            let result = self.socket.send(&raw_data);
-            match result {
-                Err(e) => {
-                    // send fails ... JOB REMOVAL from jobs list
-                    self.job_channel_tx
-                        .send((JobAction::REMOVE, job.get_handle(), None));
-
-                    self.error_state_previous.store(true, Ordering::SeqCst);
-                    self.error_state_event_raiser(Some(e.to_string()));
+            match self.read_raw_data_from_send_channel(flush_instant) {
+                Err(RecvTimeoutError::Timeout) => {}
+                // Every sender has been dropped, client is closing.
+                Err(RecvTimeoutError::Disconnected) => {
+                    self.flush();
+                    break;
                 }
-                Ok(send_buffer_size) => {
-                    if self.error_state_current.load(Ordering::SeqCst) == true {
-                        self.error_state_previous.store(false, Ordering::SeqCst);
+                Ok((raw_data, job)) => {
+                    self.queue(raw_data, job);
+                    // Datagrams already waiting in channel are batched together.
+                    while let Ok((raw_data, job)) = self.send_channel_rx.try_recv() {
+                        self.queue(raw_data, job);
                     }
-                    self.error_state_event_raiser(None);
                 }
             }
+
+            if flush_instant.elapsed() >= self.flush_interval {
+                self.flush();
+                flush_instant = Instant::now();
+            }
         }
     }
 }
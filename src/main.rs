@@ -2,7 +2,6 @@
 mod tests;
 
 mod client;
-mod helpers;
 mod params;
 mod protocol;
 mod requests;
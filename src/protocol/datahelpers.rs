@@ -3,6 +3,8 @@ use crate::protocol::DataStructure;
 use crate::protocol::HashMap;
 use crate::protocol::Protocol;
 use crate::protocol::ProtocolError;
+use crate::requests::leave::LeaveReason;
+use crate::requests::session::{create_session_token_data, SessionToken};
 use crate::server::connection::Connection;
 use std::io::ErrorKind;
 
@@ -14,28 +16,32 @@ pub fn get_protocol_total_length(structures: &HashMap<String, DataStructure>) ->
     protocol_total_length
 }
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::net::{IpAddr, Ipv6Addr, SocketAddr};
 
-pub fn get_ip_and_port_from_socket_addr(addr: SocketAddr) -> Result<([u8; 4], u16), ErrorKind> {
-    let port = addr.port();
-    let ip = addr.ip();
-    if let IpAddr::V4(ipv4) = ip {
-        return Ok((ipv4.octets(), port));
-    }
+// Length of address field, IPv4 addresses are carried as IPv4-mapped IPv6 addresses.
+pub const PLAYER_IP_LENGTH: usize = 16;
 
-    Err(ErrorKind::InvalidData)
+pub fn get_ip_and_port_from_socket_addr(addr: SocketAddr) -> ([u8; PLAYER_IP_LENGTH], u16) {
+    let ip = match addr.ip() {
+        IpAddr::V4(ipv4) => ipv4.to_ipv6_mapped(),
+        IpAddr::V6(ipv6) => ipv6,
+    };
+    (ip.octets(), addr.port())
 }
 
 pub fn create_addr_from_ip_and_port(ip_vec: Vec<u8>, port: u16) -> Result<SocketAddr, ErrorKind> {
-    if ip_vec.len() != 4 {
+    if ip_vec.len() != PLAYER_IP_LENGTH {
         return Err(ErrorKind::InvalidData);
     }
 
-    let ip = [ip_vec[0], ip_vec[1], ip_vec[2], ip_vec[3]];
-    Ok(SocketAddr::new(
-        IpAddr::V4(Ipv4Addr::new(ip[0], ip[1], ip[2], ip[3])),
-        port,
-    ))
+    let mut octets = [0; PLAYER_IP_LENGTH];
+    octets.copy_from_slice(&ip_vec);
+    let ipv6 = Ipv6Addr::from(octets);
+    let ip = match ipv6.to_ipv4_mapped() {
+        Some(ipv4) => IpAddr::V4(ipv4),
+        None => IpAddr::V6(ipv6),
+    };
+    Ok(SocketAddr::new(ip, port))
 }
 
 pub fn create_player_request(
@@ -59,17 +65,7 @@ pub fn create_player_enter_push(
 ) -> Result<Vec<u8>, ProtocolError> {
     let player_enter_push_protocol = protocols.get_protocol("PlayerEnterPush")?;
 
-    let player_ip_and_port_maybe = get_ip_and_port_from_socket_addr(player_addr);
-
-    let player_ip = match player_ip_and_port_maybe {
-        Ok(_) => player_ip_and_port_maybe.unwrap().0,
-        Err(_) => [0, 0, 0, 0],
-    };
-
-    let player_port = match player_ip_and_port_maybe {
-        Ok(_) => player_ip_and_port_maybe.unwrap().1,
-        Err(_) => 0,
-    };
+    let (player_ip, player_port) = get_ip_and_port_from_socket_addr(player_addr);
 
     let raw_data = RawDataBuilder::new(false)
         .add_vec_data(
@@ -86,6 +82,30 @@ pub fn create_player_enter_push(
     Ok(raw_data)
 }
 
+pub fn create_player_leave_push(
+    protocols: &Protocol,
+    player_number: u8,
+    reason: LeaveReason,
+) -> Result<Vec<u8>, ProtocolError> {
+    let player_leave_push_protocol = protocols.get_protocol("PlayerLeavePush")?;
+
+    let raw_data = RawDataBuilder::new(false)
+        .add_vec_data(
+            "PlayerNumber",
+            player_leave_push_protocol,
+            vec![player_number],
+        )?
+        .add_vec_data(
+            "LeaveReason",
+            player_leave_push_protocol,
+            vec![reason.get_byte()],
+        )?
+        .test_byte_length(player_leave_push_protocol)?
+        .get_raw_data();
+
+    Ok(raw_data)
+}
+
 /*
 "PlayerCreatedResponse" => {
     let structures = DataStructuresFactory::new()
@@ -100,7 +120,7 @@ pub fn create_player_enter_push(
                 DataStructuresFactory::new()
                     .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
                     .structure("PlayerName", 15, DataType::STRINGDATAFIXEDLENGTH, None)?
-                    .structure("PlayerIP", 4, DataType::NUMBERDATA, None)?
+                    .structure("PlayerIP", 16, DataType::NUMBERDATA, None)?
                     .structure("PlayerPort", 2, DataType::NUMBERDATA, None)?
                     .get_structures(),
             ),
@@ -118,6 +138,7 @@ pub fn create_player_created_response(
     status: u8,
     player_name: String,
     player_number: u8,
+    session_token: SessionToken,
     connections: &HashMap<SocketAddr, Connection>,
 ) -> Result<Vec<u8>, ProtocolError> {
     let player_created_protocol = protocols.get_protocol("PlayerCreatedResponse")?;
@@ -125,7 +146,12 @@ pub fn create_player_created_response(
     let mut response_builder = RawDataBuilder::new(false)
         .add_u8_data("Status", player_created_protocol, status)?
         .add_u8_data("PlayerNumber", player_created_protocol, player_number)?
-        .add_string_data("PlayerName", player_created_protocol, player_name)?;
+        .add_string_data("PlayerName", player_created_protocol, player_name)?
+        .add_vec_data(
+            "SessionToken",
+            player_created_protocol,
+            create_session_token_data(session_token),
+        )?;
 
     let array_structure =
         protocols.get_array_structure_as_ref("PlayerCreatedResponse", "OtherPlayers")?;
@@ -134,17 +160,7 @@ pub fn create_player_created_response(
         let player_name = connection.player_name.clone();
         let player_number = connection.player_number.clone();
 
-        let player_ip_and_port_maybe = get_ip_and_port_from_socket_addr(*connection_addr);
-
-        let player_ip = match player_ip_and_port_maybe {
-            Ok(_) => player_ip_and_port_maybe.unwrap().0,
-            Err(_) => [0, 0, 0, 0],
-        };
-
-        let player_port = match player_ip_and_port_maybe {
-            Ok(_) => player_ip_and_port_maybe.unwrap().1,
-            Err(_) => 0,
-        };
+        let (player_ip, player_port) = get_ip_and_port_from_socket_addr(*connection_addr);
 
         let raw_data = RawDataBuilder::new(true)
             .add_u8_data("PlayerNumber", array_structure, player_number)
@@ -160,6 +160,13 @@ impl Protocol {
 "PlayerEnterRequest",
 "PlayerLeaveRequest",
 "PingRequest",
+"HeartbeatRequest",
+"PushAck",
+"ResumeRequest",
+"PeerPunch",
+"PeerDataPush",
+"TargetedPushRequest",
+"RoomRequest",
 
 "NoServerAction",
 "DataPush",
@@ -170,6 +177,13 @@ impl Protocol {
 "PlayerLeaveResponse",
 "PlayerLeavePush",
 "PongResponse",
+"HeartbeatResponse",
+"ChallengeResponse",
+"ResumeResponse",
+"PlayerReconnectPush",
+"TargetedPushResponse",
+"RoomResponse",
+"RoomPush",
 */
 
 fn get_default_data_protocols() -> Result<HashMap<String, DataStructureT>, ProtocolError> {
@@ -182,14 +196,6 @@ fn get_default_data_protocols() -> Result<HashMap<String, DataStructureT>, Proto
                     .get_structures();
                 Some(structures)
             }
-            "PlayerLeaveRequest" => {
-                let structures = DataStructuresFactory::new()
-                    .structure("Index", 1, DataType::NUMBERDATA, None)?
-                    .structure("ServerClientJobByte", 1, DataType::NUMBERDATA, None)?
-                    .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
-                    .get_structures();
-                Some(structures)
-            }
             "DataPushRequest" => {
                 let structures = DataStructuresFactory::new()
                     .structure("DataPushType", 1, DataType::NUMBERDATA, None)?
@@ -211,6 +217,7 @@ fn get_default_data_protocols() -> Result<HashMap<String, DataStructureT>, Proto
                     .structure("Status", 1, DataType::NUMBERDATA, None)?
                     .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
                     .structure("PlayerName", 15, DataType::STRINGDATAFIXEDLENGTH, None)?
+                    .structure("SessionToken", 8, DataType::NUMBERDATA, None)?
                     .structure(
                         "OtherPlayers",
                         0,
@@ -219,7 +226,7 @@ fn get_default_data_protocols() -> Result<HashMap<String, DataStructureT>, Proto
                             DataStructuresFactory::new()
                                 .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
                                 .structure("PlayerName", 15, DataType::STRINGDATAFIXEDLENGTH, None)?
-                                .structure("PlayerIP", 4, DataType::NUMBERDATA, None)?
+                                .structure("PlayerIP", 16, DataType::NUMBERDATA, None)?
                                 .structure("PlayerPort", 2, DataType::NUMBERDATA, None)?
                                 .get_structures(),
                         ),
@@ -231,11 +238,18 @@ fn get_default_data_protocols() -> Result<HashMap<String, DataStructureT>, Proto
                 let structures = DataStructuresFactory::new()
                     .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
                     .structure("PlayerName", 15, DataType::STRINGDATAFIXEDLENGTH, None)?
-                    .structure("PlayerIP", 4, DataType::NUMBERDATA, None)?
+                    .structure("PlayerIP", 16, DataType::NUMBERDATA, None)?
                     .structure("PlayerPort", 2, DataType::NUMBERDATA, None)?
                     .get_structures();
                 Some(structures)
             }
+            "PlayerLeavePush" => {
+                let structures = DataStructuresFactory::new()
+                    .structure("PlayerNumber", 1, DataType::NUMBERDATA, None)?
+                    .structure("LeaveReason", 1, DataType::NUMBERDATA, None)?
+                    .get_structures();
+                Some(structures)
+            }
             _ => None,
         };
         if structures.is_some() {
@@ -0,0 +1,99 @@
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::fragmentation::MAX_DATAGRAM_SIZE;
+use crate::requests::header::{Header, BATCH_FLAG, HEADER_LENGTH};
+use crate::requests::{ClientJob, ServerJob};
+use std::time::Duration;
+
+// Queued messages are sent at once by default, only messages queued at the same
+// time are batched.
+pub const DEFAULT_FLUSH_INTERVAL: Duration = Duration::from_millis(0);
+// Every message in batch is preceded by its length, two bytes big endian.
+const MESSAGE_LENGTH_SIZE: usize = 2;
+
+/*
+ * Batch of datagrams for one destination.
+ *
+ * Batch datagram has a header with batch flag, followed by length prefixed datagrams.
+ * Batch is kept under MTU, datagram that does not fit flushes the batch. Batch of one
+ * datagram is sent as is, so it has no overhead.
+ */
+pub struct Batch {
+    datagrams: Vec<Vec<u8>>,
+    batch_length: usize,
+}
+
+impl Batch {
+    pub fn new() -> Batch {
+        Batch {
+            datagrams: vec![],
+            batch_length: HEADER_LENGTH,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.datagrams.is_empty()
+    }
+
+    // Adds datagram to batch. Returns earlier datagrams as one datagram, if
+    // they must be sent first to make room.
+    pub fn push(&mut self, datagram: Vec<u8>) -> Option<Vec<u8>> {
+        let datagram_length = MESSAGE_LENGTH_SIZE + datagram.len();
+        let flushed = if self.batch_length + datagram_length > MAX_DATAGRAM_SIZE {
+            self.take()
+        } else {
+            None
+        };
+
+        self.batch_length += datagram_length;
+        self.datagrams.push(datagram);
+        flushed
+    }
+
+    // Empties batch, returns datagram to send.
+    pub fn take(&mut self) -> Option<Vec<u8>> {
+        self.batch_length = HEADER_LENGTH;
+        let mut datagrams = std::mem::take(&mut self.datagrams);
+        match datagrams.len() {
+            0 => None,
+            1 => datagrams.pop(),
+            _ => Some(create_batch_datagram(&datagrams)),
+        }
+    }
+}
+
+fn create_batch_datagram(datagrams: &[Vec<u8>]) -> Vec<u8> {
+    let header = Header::new(
+        0,
+        (ServerJob::NoServerAction, ClientJob::NoClientAction),
+        DeliveryMode::Unreliable,
+        0,
+    );
+    let mut raw_data = header.with_flags(BATCH_FLAG).get_raw_data();
+    for datagram in datagrams {
+        raw_data.extend_from_slice(&(datagram.len() as u16).to_be_bytes());
+        raw_data.extend_from_slice(datagram);
+    }
+    raw_data
+}
+
+// Splits batch datagram to datagrams it holds. Other datagrams are returned as is.
+// Reading stops at first malformed length, datagrams before it are returned.
+pub fn split_batch(raw_data: Vec<u8>) -> Vec<Vec<u8>> {
+    match Header::from_raw_data(&raw_data) {
+        Some(header) if header.is_batch() => {}
+        _ => return vec![raw_data],
+    }
+
+    let mut datagrams = vec![];
+    let mut index = HEADER_LENGTH;
+    while index + MESSAGE_LENGTH_SIZE <= raw_data.len() {
+        let datagram_length = u16::from_be_bytes([raw_data[index], raw_data[index + 1]]) as usize;
+        index += MESSAGE_LENGTH_SIZE;
+        if datagram_length == 0 || index + datagram_length > raw_data.len() {
+            break;
+        }
+        datagrams.push(raw_data[index..index + datagram_length].to_vec());
+        index += datagram_length;
+    }
+    datagrams
+}
@@ -0,0 +1,32 @@
+use crate::requests::header::HEADER_LENGTH;
+use crate::requests::ClientJob;
+
+// Challenge cookie is echoed by the client at the start of player enter request.
+// Cookie holds creation time in seconds (4 bytes) and signature (8 bytes), big endian.
+pub const COOKIE_LENGTH: usize = 12;
+
+// Requests, which create or resume a connection, are challenged.
+pub fn is_challenged(client_job: &ClientJob) -> bool {
+    matches!(
+        client_job,
+        ClientJob::PlayerEnterRequest | ClientJob::ResumeRequest
+    )
+}
+
+// First player enter request has an empty cookie, so that request is at least as
+// large as the challenge it is answered with.
+pub fn prepend_empty_cookie(raw_data: &mut Vec<u8>) {
+    raw_data.splice(0..0, [0; COOKIE_LENGTH].iter().copied());
+}
+
+// Splits request with header to cookie and request without cookie.
+// Returns None, if request is too short to hold a cookie.
+pub fn remove_cookie(raw_data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    if raw_data.len() < HEADER_LENGTH + COOKIE_LENGTH {
+        return None;
+    }
+    let cookie = raw_data[HEADER_LENGTH..HEADER_LENGTH + COOKIE_LENGTH].to_vec();
+    let mut request = raw_data[..HEADER_LENGTH].to_vec();
+    request.extend_from_slice(&raw_data[HEADER_LENGTH + COOKIE_LENGTH..]);
+    Some((cookie, request))
+}
@@ -0,0 +1,151 @@
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::fragmentation::MAX_DATAGRAM_SIZE;
+use std::time::{Duration, Instant};
+
+// Send rate limits in bytes per second.
+const INITIAL_BYTES_PER_SECOND: f64 = 64.0 * 1024.0;
+const MIN_BYTES_PER_SECOND: f64 = 8.0 * 1024.0;
+const MAX_BYTES_PER_SECOND: f64 = 4.0 * 1024.0 * 1024.0;
+// Rate grows by one datagram per second for every acknowledged message.
+const ADDITIVE_INCREASE: f64 = MAX_DATAGRAM_SIZE as f64;
+// Loss halves the rate, growing round trip time shrinks it less.
+const LOSS_DECREASE_FACTOR: f64 = 0.5;
+const DELAY_DECREASE_FACTOR: f64 = 0.85;
+// Round trip time this many times the minimum means queues are building up.
+const DELAY_THRESHOLD_FACTOR: u32 = 2;
+// Rate is decreased at most once per interval, so that one burst of
+// losses is handled as one congestion event.
+const MIN_DECREASE_INTERVAL: Duration = Duration::from_millis(100);
+// Budget can be saved for this long, which allows short bursts.
+const BURST_DURATION: Duration = Duration::from_millis(100);
+const MIN_BURST_BYTES: f64 = 4.0 * MAX_DATAGRAM_SIZE as f64;
+// Share of the budget unreliable traffic can not use, so that reliable
+// traffic still gets through when unreliable traffic fills the link.
+const RELIABLE_RESERVE_SHARE: f64 = 0.25;
+
+/*
+ * Congestion control
+ *
+ * AIMD rate control for one connection. Sent bytes are taken from a budget, which
+ * refills with the allowed rate. Acknowledged messages increase the rate additively,
+ * lost messages and growing round trip time decrease it multiplicatively.
+ *
+ * When the budget is exhausted, unreliable datagrams are dropped. Reliable
+ * datagrams can use the reserve, which unreliable datagrams can not touch, and
+ * they wait for the budget instead of being dropped.
+ */
+pub struct CongestionControl {
+    bytes_per_second: f64,
+    available_bytes: f64,
+    refill_instant: Instant,
+    min_rtt: Option<Duration>,
+    decrease_instant: Option<Instant>,
+}
+
+impl CongestionControl {
+    pub fn new() -> CongestionControl {
+        let mut congestion_control = CongestionControl {
+            bytes_per_second: INITIAL_BYTES_PER_SECOND,
+            available_bytes: 0.0,
+            refill_instant: Instant::now(),
+            min_rtt: None,
+            decrease_instant: None,
+        };
+        congestion_control.available_bytes = congestion_control.get_burst_bytes();
+        congestion_control
+    }
+
+    pub fn get_bytes_per_second(&self) -> f64 {
+        self.bytes_per_second
+    }
+
+    // Takes bytes from the budget, returns false if datagram should not be sent now.
+    pub fn try_send(&mut self, number_of_bytes: usize, mode: DeliveryMode) -> bool {
+        self.refill(Instant::now());
+
+        let reserve = if mode.is_reliable() {
+            0.0
+        } else {
+            self.get_burst_bytes() * RELIABLE_RESERVE_SHARE
+        };
+        if self.available_bytes - (number_of_bytes as f64) < reserve {
+            return false;
+        }
+
+        self.available_bytes -= number_of_bytes as f64;
+        true
+    }
+
+    // Time until the budget has room for the bytes, reliable traffic waits
+    // for this instead of being dropped.
+    pub fn get_wait_time(&mut self, number_of_bytes: usize) -> Duration {
+        self.refill(Instant::now());
+
+        let missing_bytes = number_of_bytes as f64 - self.available_bytes;
+        if missing_bytes <= 0.0 {
+            return Duration::from_millis(0);
+        }
+        Duration::from_secs_f64(missing_bytes / self.bytes_per_second)
+    }
+
+    // Message was acknowledged. Round trip time is None, if it is not known,
+    // or if message was resent and the time would be ambiguous.
+    pub fn on_ack(&mut self, rtt: Option<Duration>) {
+        if let Some(rtt) = rtt {
+            if self.on_rtt_sample(rtt) {
+                return;
+            }
+        }
+
+        self.bytes_per_second =
+            (self.bytes_per_second + ADDITIVE_INCREASE).min(MAX_BYTES_PER_SECOND);
+    }
+
+    // Round trip time measured without acknowledging a message, like from a
+    // heartbeat. Returns true, if growing round trip time decreased the rate.
+    pub fn on_rtt_sample(&mut self, rtt: Duration) -> bool {
+        let min_rtt = match self.min_rtt {
+            Some(min_rtt) if min_rtt <= rtt => min_rtt,
+            _ => rtt,
+        };
+        self.min_rtt = Some(min_rtt);
+
+        if rtt > min_rtt * DELAY_THRESHOLD_FACTOR && rtt > MIN_DECREASE_INTERVAL {
+            self.decrease(DELAY_DECREASE_FACTOR);
+            return true;
+        }
+        false
+    }
+
+    pub fn on_loss(&mut self) {
+        self.decrease(LOSS_DECREASE_FACTOR);
+    }
+
+    fn decrease(&mut self, factor: f64) {
+        let now = Instant::now();
+        let decrease_interval = match self.min_rtt {
+            Some(min_rtt) if min_rtt > MIN_DECREASE_INTERVAL => min_rtt,
+            _ => MIN_DECREASE_INTERVAL,
+        };
+        if let Some(decrease_instant) = self.decrease_instant {
+            if now.duration_since(decrease_instant) < decrease_interval {
+                return;
+            }
+        }
+
+        self.decrease_instant = Some(now);
+        self.bytes_per_second = (self.bytes_per_second * factor).max(MIN_BYTES_PER_SECOND);
+        self.available_bytes = self.available_bytes.min(self.get_burst_bytes());
+    }
+
+    fn get_burst_bytes(&self) -> f64 {
+        (self.bytes_per_second * BURST_DURATION.as_secs_f64()).max(MIN_BURST_BYTES)
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.refill_instant).as_secs_f64();
+        self.refill_instant = now;
+        self.available_bytes =
+            (self.available_bytes + elapsed * self.bytes_per_second).min(self.get_burst_bytes());
+    }
+}
@@ -0,0 +1,187 @@
+use std::convert::TryInto;
+
+// Keys are UTF-8, at most this many bytes.
+pub const MAX_KEY_LENGTH: usize = 64;
+
+const GET_OPERATION: u8 = 1;
+const SET_OPERATION: u8 = 2;
+const COMPARE_AND_SET_OPERATION: u8 = 3;
+const DELETE_OPERATION: u8 = 4;
+
+/*
+ * Data operation
+ *
+ * Operation on the shared data store of the server, carried in data request:
+ * [operation byte][key length byte][key][operation data]
+ *
+ * Set carries the value. Compare-and-set carries a byte telling if the key is
+ * expected to exist, expected value length (2 bytes, big endian), expected value
+ * and the new value. Expected value None means that key must not exist yet.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DataOperation {
+    Get {
+        key: String,
+    },
+    Set {
+        key: String,
+        value: Vec<u8>,
+    },
+    CompareAndSet {
+        key: String,
+        expected: Option<Vec<u8>>,
+        value: Vec<u8>,
+    },
+    Delete {
+        key: String,
+    },
+}
+
+impl DataOperation {
+    pub fn get_key(&self) -> &str {
+        match self {
+            DataOperation::Get { key }
+            | DataOperation::Set { key, .. }
+            | DataOperation::CompareAndSet { key, .. }
+            | DataOperation::Delete { key } => key,
+        }
+    }
+}
+
+// Status of data operation, first byte of data response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataStatus {
+    Ok = 0,
+    NotFound = 1,
+    // Compare-and-set found another value, response holds the current value.
+    CompareFailed = 2,
+    AccessDenied = 3,
+    TooLarge = 4,
+    StoreFull = 5,
+    InvalidRequest = 6,
+}
+
+impl DataStatus {
+    pub fn get_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<DataStatus> {
+        match byte {
+            0 => Some(DataStatus::Ok),
+            1 => Some(DataStatus::NotFound),
+            2 => Some(DataStatus::CompareFailed),
+            3 => Some(DataStatus::AccessDenied),
+            4 => Some(DataStatus::TooLarge),
+            5 => Some(DataStatus::StoreFull),
+            6 => Some(DataStatus::InvalidRequest),
+            _ => None,
+        }
+    }
+}
+
+/*
+ * Data response
+ *
+ * [status byte][value exists byte][value]
+ *
+ * Value is the stored value of get, and the current value of failed compare-and-set.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DataResponse {
+    pub status: DataStatus,
+    pub value: Option<Vec<u8>>,
+}
+
+impl DataResponse {
+    pub fn new(status: DataStatus, value: Option<Vec<u8>>) -> DataResponse {
+        DataResponse { status, value }
+    }
+
+    pub fn from_status(status: DataStatus) -> DataResponse {
+        DataResponse::new(status, None)
+    }
+}
+
+pub fn create_data_request(operation: &DataOperation) -> Vec<u8> {
+    let (operation_byte, key) = match operation {
+        DataOperation::Get { key } => (GET_OPERATION, key),
+        DataOperation::Set { key, .. } => (SET_OPERATION, key),
+        DataOperation::CompareAndSet { key, .. } => (COMPARE_AND_SET_OPERATION, key),
+        DataOperation::Delete { key } => (DELETE_OPERATION, key),
+    };
+    // Too long key is not truncated, length byte makes server reject it.
+    let mut raw_data = vec![operation_byte, key.len().min(u8::MAX as usize) as u8];
+    raw_data.extend(key.as_bytes());
+    match operation {
+        DataOperation::Get { .. } | DataOperation::Delete { .. } => {}
+        DataOperation::Set { value, .. } => raw_data.extend(value),
+        DataOperation::CompareAndSet {
+            expected, value, ..
+        } => {
+            let expected_value = expected.as_deref().unwrap_or(&[]);
+            raw_data.push(expected.is_some() as u8);
+            raw_data.extend((expected_value.len() as u16).to_be_bytes());
+            raw_data.extend(expected_value);
+            raw_data.extend(value);
+        }
+    }
+    raw_data
+}
+
+// Returns None, if request is malformed.
+pub fn parse_data_request(raw_data: &[u8]) -> Option<DataOperation> {
+    let (&operation_byte, rest) = raw_data.split_first()?;
+    let (&key_length, rest) = rest.split_first()?;
+    let key_data = rest.get(..key_length as usize)?;
+    let rest = &rest[key_length as usize..];
+    let key = String::from_utf8(key_data.to_vec()).ok()?;
+
+    match operation_byte {
+        GET_OPERATION if rest.is_empty() => Some(DataOperation::Get { key }),
+        SET_OPERATION => Some(DataOperation::Set {
+            key,
+            value: rest.to_vec(),
+        }),
+        COMPARE_AND_SET_OPERATION => {
+            let (&expected_exists, rest) = rest.split_first()?;
+            let expected_length = u16::from_be_bytes(rest.get(..2)?.try_into().ok()?) as usize;
+            let expected_value = rest.get(2..2 + expected_length)?.to_vec();
+            let value = rest[2 + expected_length..].to_vec();
+            let expected = match expected_exists {
+                0 if expected_value.is_empty() => None,
+                1 => Some(expected_value),
+                _ => return None,
+            };
+            Some(DataOperation::CompareAndSet {
+                key,
+                expected,
+                value,
+            })
+        }
+        DELETE_OPERATION if rest.is_empty() => Some(DataOperation::Delete { key }),
+        _ => None,
+    }
+}
+
+pub fn create_data_response(response: &DataResponse) -> Vec<u8> {
+    let mut raw_data = vec![response.status.get_byte(), response.value.is_some() as u8];
+    if let Some(value) = &response.value {
+        raw_data.extend(value);
+    }
+    raw_data
+}
+
+// Returns None, if response is malformed.
+pub fn parse_data_response(raw_data: &[u8]) -> Option<DataResponse> {
+    match raw_data {
+        [status_byte, 0] => Some(DataResponse::from_status(DataStatus::from_byte(
+            *status_byte,
+        )?)),
+        [status_byte, 1, value @ ..] => Some(DataResponse::new(
+            DataStatus::from_byte(*status_byte)?,
+            Some(value.to_vec()),
+        )),
+        _ => None,
+    }
+}
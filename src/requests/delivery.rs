@@ -0,0 +1,300 @@
+use crate::requests::{ClientJob, ServerJob};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+// Ordering buffer will not grow over this. If it is full, missing sequences
+// are given up and buffered data is released.
+const MAX_ORDERING_BUFFER: usize = 64;
+// Missing sequence is given up after this, e.g. when sender has abandoned the
+// message, so that buffered data is not held until the buffer is full.
+const ORDERING_GAP_TIMEOUT: Duration = Duration::from_secs(3);
+
+/*
+ * Delivery mode
+ *
+ * Every message on the wire carries its delivery mode in the header. Each mode
+ * has its own sequence space, so that e.g. unreliable position updates do not
+ * block reliable ordered messages.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DeliveryMode {
+    // Fire and forget, every received message is handled.
+    Unreliable = 0,
+    // Fire and forget, messages older than the latest received are dropped.
+    UnreliableSequenced = 1,
+    // Tracked and resent as a job, handled in arrival order.
+    ReliableUnordered = 2,
+    // Tracked and resent as a job, handled in sending order.
+    ReliableOrdered = 3,
+}
+
+impl DeliveryMode {
+    pub fn all() -> [DeliveryMode; 4] {
+        [
+            DeliveryMode::Unreliable,
+            DeliveryMode::UnreliableSequenced,
+            DeliveryMode::ReliableUnordered,
+            DeliveryMode::ReliableOrdered,
+        ]
+    }
+
+    pub fn is_reliable(&self) -> bool {
+        matches!(
+            self,
+            DeliveryMode::ReliableUnordered | DeliveryMode::ReliableOrdered
+        )
+    }
+
+    pub fn get_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<DeliveryMode> {
+        match byte {
+            0 => Some(DeliveryMode::Unreliable),
+            1 => Some(DeliveryMode::UnreliableSequenced),
+            2 => Some(DeliveryMode::ReliableUnordered),
+            3 => Some(DeliveryMode::ReliableOrdered),
+            _ => None,
+        }
+    }
+}
+
+// Sequence numbers of delivery modes and job handles share the same 16 bit space.
+pub type Sequence = u16;
+
+// Wrap around safe sequence comparison. Sequence a is newer than b, if it is
+// less than half of the sequence space ahead of b.
+pub fn is_sequence_newer(a: Sequence, b: Sequence) -> bool {
+    a != b && a.wrapping_sub(b) < 0x8000
+}
+
+/*
+ * Delivery modes per message type
+ *
+ * Client requests are reliable unordered by default, which is how every request
+ * was handled before delivery modes. Player enter, leave, reconnect and room pushes
+ * are reliable ordered by default, since a lost one leaves the roster of the client
+ * wrong. Other server originated pushes are unreliable by default. Responses always
+ * use the mode of the request they answer, and data pushes forwarded by the server
+ * use the mode of the original push request.
+ */
+pub struct DeliveryModes {
+    client_jobs: HashMap<ClientJob, DeliveryMode>,
+    server_jobs: HashMap<ServerJob, DeliveryMode>,
+}
+
+impl DeliveryModes {
+    pub fn new() -> DeliveryModes {
+        DeliveryModes {
+            client_jobs: HashMap::new(),
+            server_jobs: HashMap::new(),
+        }
+    }
+
+    pub fn set_client_job_mode(&mut self, client_job: ClientJob, mode: DeliveryMode) {
+        self.client_jobs.insert(client_job, mode);
+    }
+
+    pub fn set_server_job_mode(&mut self, server_job: ServerJob, mode: DeliveryMode) {
+        self.server_jobs.insert(server_job, mode);
+    }
+
+    pub fn get_client_job_mode(&self, client_job: &ClientJob) -> DeliveryMode {
+        match self.client_jobs.get(client_job) {
+            Some(mode) => *mode,
+            None => DeliveryMode::ReliableUnordered,
+        }
+    }
+
+    pub fn get_server_job_mode(&self, server_job: &ServerJob) -> DeliveryMode {
+        match (self.server_jobs.get(server_job), server_job) {
+            (Some(mode), _) => *mode,
+            (None, ServerJob::PlayerEnterPush)
+            | (None, ServerJob::PlayerLeavePush)
+            | (None, ServerJob::PlayerReconnectPush)
+            | (None, ServerJob::RoomPush) => DeliveryMode::ReliableOrdered,
+            (None, _) => DeliveryMode::Unreliable,
+        }
+    }
+}
+
+/*
+ * Sequence counters, one sequence space per delivery mode.
+ */
+pub struct SequenceCounters {
+    next_sequences: HashMap<DeliveryMode, Sequence>,
+}
+
+impl SequenceCounters {
+    pub fn new() -> SequenceCounters {
+        SequenceCounters {
+            next_sequences: HashMap::new(),
+        }
+    }
+
+    pub fn get_next_sequence(&mut self, mode: DeliveryMode) -> Sequence {
+        let next_sequence = self.next_sequences.entry(mode).or_insert(0);
+        let sequence = *next_sequence;
+        *next_sequence = next_sequence.wrapping_add(1);
+        sequence
+    }
+}
+
+/*
+ * Receive channel
+ *
+ * Receive side of one delivery mode. Sender counters start at 0 per session, so
+ * ordered mode waits for sequence 0 first, also when a later sequence arrives
+ * before it. Message handled before the receiver started tracking the sender
+ * (e.g. request which created the player on server) is marked handled.
+ *
+ * Ordered mode skips a missing sequence, when the ordering buffer is full or the
+ * sequence has been missing longer than gap timeout.
+ */
+pub struct ReceiveChannel<T> {
+    mode: DeliveryMode,
+    // latest released sequence, used by sequenced mode.
+    last_sequence: Option<Sequence>,
+    // next sequence to release, used by ordered mode.
+    next_expected: Sequence,
+    ordering_buffer: HashMap<Sequence, T>,
+    // when next expected sequence was found missing, while later ones were buffered.
+    gap_instant: Option<Instant>,
+}
+
+impl<T> ReceiveChannel<T> {
+    pub fn new(mode: DeliveryMode) -> ReceiveChannel<T> {
+        ReceiveChannel {
+            mode,
+            last_sequence: None,
+            next_expected: 0,
+            ordering_buffer: HashMap::new(),
+            gap_instant: None,
+        }
+    }
+
+    // Returns data that can be handled now, in the order it should be handled.
+    pub fn receive(&mut self, sequence: Sequence, data: T) -> Vec<T> {
+        match self.mode {
+            DeliveryMode::Unreliable | DeliveryMode::ReliableUnordered => vec![data],
+            DeliveryMode::UnreliableSequenced => self.receive_sequenced(sequence, data),
+            DeliveryMode::ReliableOrdered => self.receive_ordered(sequence, data),
+        }
+    }
+
+    fn receive_sequenced(&mut self, sequence: Sequence, data: T) -> Vec<T> {
+        match self.last_sequence {
+            Some(last_sequence) if !is_sequence_newer(sequence, last_sequence) => {
+                // stale or duplicate data, drop it.
+                vec![]
+            }
+            _ => {
+                self.last_sequence = Some(sequence);
+                vec![data]
+            }
+        }
+    }
+
+    fn receive_ordered(&mut self, sequence: Sequence, data: T) -> Vec<T> {
+        // Already released sequence. This is a resent reliable message, which
+        // sender still waits an answer for, so it is passed through.
+        if sequence != self.next_expected && !is_sequence_newer(sequence, self.next_expected) {
+            return vec![data];
+        }
+
+        self.ordering_buffer.insert(sequence, data);
+
+        // Too many missing sequences, let's skip to the oldest buffered one.
+        if self.ordering_buffer.len() > MAX_ORDERING_BUFFER {
+            self.skip_gap();
+        }
+        self.release_buffered(Instant::now())
+    }
+
+    // Message was handled outside the channel, ordered mode waits for the next one.
+    pub fn mark_handled(&mut self, sequence: Sequence) {
+        if self.mode == DeliveryMode::ReliableOrdered
+            && (sequence == self.next_expected || is_sequence_newer(sequence, self.next_expected))
+        {
+            self.next_expected = sequence.wrapping_add(1);
+        }
+    }
+
+    // Returns buffered data, which has waited a missing sequence longer than gap timeout.
+    pub fn release_late(&mut self, now: Instant) -> Vec<T> {
+        match self.gap_instant {
+            Some(gap_instant)
+                if now.saturating_duration_since(gap_instant) >= ORDERING_GAP_TIMEOUT =>
+            {
+                self.skip_gap();
+                self.release_buffered(now)
+            }
+            _ => vec![],
+        }
+    }
+
+    // Next expected sequence is moved to the oldest buffered one.
+    fn skip_gap(&mut self) {
+        let next_expected = self.next_expected;
+        if let Some(oldest_buffered) = self
+            .ordering_buffer
+            .keys()
+            .min_by_key(|buffered| buffered.wrapping_sub(next_expected))
+        {
+            self.next_expected = *oldest_buffered;
+        }
+    }
+
+    fn release_buffered(&mut self, now: Instant) -> Vec<T> {
+        let mut released = vec![];
+        while let Some(data) = self.ordering_buffer.remove(&self.next_expected) {
+            released.push(data);
+            self.next_expected = self.next_expected.wrapping_add(1);
+        }
+
+        // Gap starts again, when buffered data still waits for a missing sequence.
+        if self.ordering_buffer.is_empty() {
+            self.gap_instant = None;
+        } else if !released.is_empty() || self.gap_instant.is_none() {
+            self.gap_instant = Some(now);
+        }
+        released
+    }
+}
+
+/*
+ * Receive channels for every delivery mode of one sender.
+ */
+pub struct ReceiveChannels<T> {
+    channels: HashMap<DeliveryMode, ReceiveChannel<T>>,
+}
+
+impl<T> ReceiveChannels<T> {
+    pub fn new() -> ReceiveChannels<T> {
+        let mut channels = HashMap::new();
+        for mode in DeliveryMode::all() {
+            channels.insert(mode, ReceiveChannel::new(mode));
+        }
+        ReceiveChannels { channels }
+    }
+
+    pub fn receive(&mut self, mode: DeliveryMode, sequence: Sequence, data: T) -> Vec<T> {
+        self.channels
+            .get_mut(&mode)
+            .unwrap()
+            .receive(sequence, data)
+    }
+
+    pub fn mark_handled(&mut self, mode: DeliveryMode, sequence: Sequence) {
+        self.channels.get_mut(&mode).unwrap().mark_handled(sequence);
+    }
+
+    // Returns data, which is released, since missing sequences were given up.
+    pub fn release_late(&mut self, now: Instant) -> Vec<T> {
+        self.channels
+            .get_mut(&DeliveryMode::ReliableOrdered)
+            .unwrap()
+            .release_late(now)
+    }
+}
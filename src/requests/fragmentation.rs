@@ -0,0 +1,189 @@
+use crate::requests::header::{
+    Header, FRAGMENT_ACK_FLAG, FRAGMENT_FLAG, HEADER_LENGTH, MODE_AND_FLAGS_INDEX,
+};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+// Datagrams are kept under common internet MTU, larger messages are fragmented.
+pub const MAX_DATAGRAM_SIZE: usize = 1200;
+// Receive buffer holds largest possible UDP datagram, so nothing is truncated.
+pub const RECEIVE_BUFFER_SIZE: usize = 65536;
+// Fragment datagram has fragment index and fragment count after the header.
+pub const FRAGMENT_HEADER_LENGTH: usize = 2;
+const MAX_FRAGMENT_PAYLOAD: usize = MAX_DATAGRAM_SIZE - HEADER_LENGTH - FRAGMENT_HEADER_LENGTH;
+const MAX_FRAGMENT_COUNT: usize = 255;
+// Largest message, header included, that can be sent.
+pub const MAX_MESSAGE_SIZE: usize = HEADER_LENGTH + MAX_FRAGMENT_COUNT * MAX_FRAGMENT_PAYLOAD;
+
+// Reassembly limits. Partial messages are dropped after timeout, and fragments
+// are dropped when source or reassembler has too much data buffered.
+const REASSEMBLY_TIMEOUT: Duration = Duration::from_secs(5);
+const MAX_REASSEMBLY_BYTES_PER_SOURCE: usize = 512 * 1024;
+const MAX_REASSEMBLY_BYTES: usize = 8 * 1024 * 1024;
+
+pub fn is_message_too_large(message_length: usize) -> bool {
+    message_length > MAX_MESSAGE_SIZE
+}
+
+/*
+ * Split message (header and user data) to datagrams.
+ *
+ * Message that fits in one datagram is returned as is. Otherwise every fragment
+ * has the message header with fragment flag, fragment index and fragment count,
+ * and a piece of user data. Message size must be checked with is_message_too_large.
+ */
+pub fn create_datagrams(raw_data: &[u8]) -> Vec<Vec<u8>> {
+    if raw_data.len() <= MAX_DATAGRAM_SIZE {
+        return vec![raw_data.to_vec()];
+    }
+
+    let mut fragment_header = raw_data[..HEADER_LENGTH].to_vec();
+    fragment_header[MODE_AND_FLAGS_INDEX] |= FRAGMENT_FLAG;
+
+    let chunks: Vec<&[u8]> = raw_data[HEADER_LENGTH..]
+        .chunks(MAX_FRAGMENT_PAYLOAD)
+        .take(MAX_FRAGMENT_COUNT)
+        .collect();
+    let fragment_count = chunks.len() as u8;
+
+    let mut datagrams = vec![];
+    for (fragment_index, chunk) in chunks.into_iter().enumerate() {
+        let mut datagram = fragment_header.clone();
+        datagram.push(fragment_index as u8);
+        datagram.push(fragment_count);
+        datagram.extend_from_slice(chunk);
+        datagrams.push(datagram);
+    }
+    datagrams
+}
+
+// Acknowledgement of one received fragment, header is echoed with ack flag.
+pub fn create_fragment_ack(header: &Header, fragment_index: u8) -> Vec<u8> {
+    let mut raw_data = header.with_flags(FRAGMENT_ACK_FLAG).get_raw_data();
+    raw_data.push(fragment_index);
+    raw_data
+}
+
+// Returns fragment index and fragment count of fragment datagram.
+pub fn get_fragment_index_and_count(raw_data: &[u8]) -> Option<(u8, u8)> {
+    if raw_data.len() < HEADER_LENGTH + FRAGMENT_HEADER_LENGTH {
+        return None;
+    }
+    let fragment_index = raw_data[HEADER_LENGTH];
+    let fragment_count = raw_data[HEADER_LENGTH + 1];
+    if fragment_count == 0 || fragment_index >= fragment_count {
+        return None;
+    }
+    Some((fragment_index, fragment_count))
+}
+
+struct PartialMessage {
+    header: Header,
+    fragments: Vec<Option<Vec<u8>>>,
+    received_count: usize,
+    bytes: usize,
+    start_instant: Instant,
+}
+
+/*
+ * Reassembler
+ *
+ * Collects fragments per source. Message is identified by its header, since header
+ * (handle, job type, delivery mode and sequence) is unique among messages in flight.
+ */
+pub struct Reassembler<K> {
+    messages: HashMap<(K, Vec<u8>), PartialMessage>,
+    bytes_per_source: HashMap<K, usize>,
+    total_bytes: usize,
+}
+
+impl<K: Eq + Hash + Clone> Reassembler<K> {
+    pub fn new() -> Reassembler<K> {
+        Reassembler {
+            messages: HashMap::new(),
+            bytes_per_source: HashMap::new(),
+            total_bytes: 0,
+        }
+    }
+
+    // Returns complete message (header and user data), when last missing fragment arrives.
+    pub fn receive(&mut self, source: K, header: &Header, raw_data: &[u8]) -> Option<Vec<u8>> {
+        let now = Instant::now();
+        self.remove_expired(now);
+
+        let (fragment_index, fragment_count) = get_fragment_index_and_count(raw_data)?;
+        let fragment = &raw_data[HEADER_LENGTH + FRAGMENT_HEADER_LENGTH..];
+
+        let source_bytes = *self.bytes_per_source.get(&source).unwrap_or(&0);
+        if source_bytes + fragment.len() > MAX_REASSEMBLY_BYTES_PER_SOURCE
+            || self.total_bytes + fragment.len() > MAX_REASSEMBLY_BYTES
+        {
+            println!("Reassembly buffer is full, fragment dropped.");
+            return None;
+        }
+
+        let message_header = header.with_flags(0);
+        let key = (source.clone(), message_header.get_raw_data());
+        let message = self
+            .messages
+            .entry(key.clone())
+            .or_insert_with(|| PartialMessage {
+                header: message_header,
+                fragments: vec![None; fragment_count as usize],
+                received_count: 0,
+                bytes: 0,
+                start_instant: now,
+            });
+
+        // Misformed fragment or a fragment already received.
+        if message.fragments.len() != fragment_count as usize
+            || message.fragments[fragment_index as usize].is_some()
+        {
+            return None;
+        }
+
+        message.fragments[fragment_index as usize] = Some(fragment.to_vec());
+        message.received_count += 1;
+        message.bytes += fragment.len();
+        self.total_bytes += fragment.len();
+        *self.bytes_per_source.entry(source).or_insert(0) += fragment.len();
+
+        if message.received_count < message.fragments.len() {
+            return None;
+        }
+
+        let message = self.remove_message(&key).unwrap();
+        let mut raw_data = message.header.get_raw_data();
+        for fragment in message.fragments.into_iter().flatten() {
+            raw_data.extend(fragment);
+        }
+        Some(raw_data)
+    }
+
+    fn remove_message(&mut self, key: &(K, Vec<u8>)) -> Option<PartialMessage> {
+        let message = self.messages.remove(key)?;
+        self.total_bytes -= message.bytes;
+        let source_bytes = self.bytes_per_source.get_mut(&key.0).unwrap();
+        *source_bytes -= message.bytes;
+        if *source_bytes == 0 {
+            self.bytes_per_source.remove(&key.0);
+        }
+        Some(message)
+    }
+
+    fn remove_expired(&mut self, now: Instant) {
+        let expired_keys: Vec<(K, Vec<u8>)> = self
+            .messages
+            .iter()
+            .filter(|(_key, message)| {
+                now.duration_since(message.start_instant) > REASSEMBLY_TIMEOUT
+            })
+            .map(|(key, _message)| key.clone())
+            .collect();
+        for key in expired_keys {
+            println!("Reassembly timed out, partial message dropped.");
+            self.remove_message(&key);
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use crate::protocol::bithelpers::get_u8_from_bit_slice;
+use crate::requests::delivery::{DeliveryMode, Sequence};
+use crate::requests::jobtype::{get_job_single_byte, get_job_type};
+use crate::requests::{ClientJob, JobHandle, JobType, ServerJob};
+
+// first two bytes are job handle, third byte is jobtype, fourth byte is delivery mode
+// and flags, fifth and sixth bytes are sequence number of the delivery mode.
+// Handle and sequence are big endian. Rest is user data.
+pub const HEADER_LENGTH: usize = 6;
+// Index of the byte holding delivery mode and flags.
+pub const MODE_AND_FLAGS_INDEX: usize = 3;
+
+// Two lowest bits of the fourth byte are delivery mode, highest bits are flags.
+const DELIVERY_MODE_MASK: u8 = 0b0000_0011;
+const FLAGS_MASK: u8 = 0b1110_0000;
+// Datagram is one fragment of a larger message.
+pub const FRAGMENT_FLAG: u8 = 0b1000_0000;
+// Datagram acknowledges one fragment of a reliable message.
+pub const FRAGMENT_ACK_FLAG: u8 = 0b0100_0000;
+// Datagram holds several messages for the same destination.
+pub const BATCH_FLAG: u8 = 0b0010_0000;
+
+#[derive(Clone)]
+pub struct Header {
+    pub handle: JobHandle,
+    pub job_type: JobType,
+    pub delivery_mode: DeliveryMode,
+    pub sequence: Sequence,
+    pub flags: u8,
+}
+
+impl Header {
+    pub fn new(
+        handle: JobHandle,
+        job_type: JobType,
+        delivery_mode: DeliveryMode,
+        sequence: Sequence,
+    ) -> Header {
+        Header {
+            handle,
+            job_type,
+            delivery_mode,
+            sequence,
+            flags: 0,
+        }
+    }
+
+    // Returns None, if raw data is too short or does not hold valid job information.
+    pub fn from_raw_data(raw_data: &[u8]) -> Option<Header> {
+        if raw_data.len() < HEADER_LENGTH {
+            return None;
+        }
+
+        let handle = JobHandle::from_be_bytes([raw_data[0], raw_data[1]]);
+        let server_client = (
+            get_u8_from_bit_slice(raw_data[2], 0, 4),
+            get_u8_from_bit_slice(raw_data[2], 4, 4),
+        );
+        let job_type = get_job_type(&server_client)?;
+        let mode_and_flags = raw_data[MODE_AND_FLAGS_INDEX];
+        let delivery_mode = DeliveryMode::from_byte(mode_and_flags & DELIVERY_MODE_MASK)?;
+        let sequence = Sequence::from_be_bytes([raw_data[4], raw_data[5]]);
+
+        let mut header = Header::new(handle, job_type, delivery_mode, sequence);
+        header.flags = mode_and_flags & FLAGS_MASK;
+        Some(header)
+    }
+
+    pub fn with_flags(&self, flags: u8) -> Header {
+        let mut header = self.clone();
+        header.flags = flags;
+        header
+    }
+
+    pub fn is_fragment(&self) -> bool {
+        self.flags & FRAGMENT_FLAG != 0
+    }
+
+    pub fn is_fragment_ack(&self) -> bool {
+        self.flags & FRAGMENT_ACK_FLAG != 0
+    }
+
+    pub fn is_batch(&self) -> bool {
+        self.flags & BATCH_FLAG != 0
+    }
+
+    // Response header echoes handle, delivery mode and sequence of the request,
+    // so that the request sender can match it to the job.
+    pub fn create_response(&self, server_job: ServerJob) -> Header {
+        Header::new(
+            self.handle,
+            (server_job, self.job_type.1.clone()),
+            self.delivery_mode,
+            self.sequence,
+        )
+    }
+
+    pub fn get_client_job(&self) -> ClientJob {
+        self.job_type.1.clone()
+    }
+
+    pub fn get_server_job(&self) -> ServerJob {
+        self.job_type.0.clone()
+    }
+
+    pub fn get_raw_data(&self) -> Vec<u8> {
+        let mut raw_data = self.handle.to_be_bytes().to_vec();
+        raw_data.push(get_job_single_byte(&self.job_type));
+        raw_data.push(self.delivery_mode.get_byte() | self.flags);
+        raw_data.extend_from_slice(&self.sequence.to_be_bytes());
+        raw_data
+    }
+}
@@ -0,0 +1,40 @@
+use std::convert::TryInto;
+use std::time::Duration;
+
+/*
+ * Heartbeat probe
+ *
+ * Heartbeat response to a player carries a probe id: [probe id, 2 bytes]
+ *
+ * Client sends the probe back with its next heartbeat, together with the time
+ * it held the probe: [probe id, 2 bytes][hold time in milliseconds, 2 bytes]
+ *
+ * Server samples round trip time of the connection from the probe, so round
+ * trip time is known also when there are no reliable pushes to acknowledge.
+ */
+
+pub fn create_heartbeat_probe(probe_id: u16) -> Vec<u8> {
+    probe_id.to_be_bytes().to_vec()
+}
+
+// Returns None, if response carries no probe.
+pub fn get_heartbeat_probe(raw_data: &[u8]) -> Option<u16> {
+    let probe_data = raw_data.get(..2)?;
+    Some(u16::from_be_bytes(probe_data.try_into().unwrap()))
+}
+
+// Hold time is cut to 65535 milliseconds.
+pub fn create_heartbeat_echo(probe_id: u16, hold_time: Duration) -> Vec<u8> {
+    let hold_millis = hold_time.as_millis().min(u16::MAX as u128) as u16;
+    let mut raw_data = probe_id.to_be_bytes().to_vec();
+    raw_data.extend(hold_millis.to_be_bytes());
+    raw_data
+}
+
+// Returns probe id and hold time, None if heartbeat carries no probe.
+pub fn get_heartbeat_echo(raw_data: &[u8]) -> Option<(u16, Duration)> {
+    let echo_data = raw_data.get(..4)?;
+    let probe_id = u16::from_be_bytes(echo_data[..2].try_into().unwrap());
+    let hold_millis = u16::from_be_bytes(echo_data[2..].try_into().unwrap());
+    Some((probe_id, Duration::from_millis(hold_millis as u64)))
+}
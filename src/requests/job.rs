@@ -1,35 +1,55 @@
-use crate::requests::jobtype::get_job_single_byte;
-use crate::requests::{Job, JobType};
+use crate::requests::challenge::COOKIE_LENGTH;
+use crate::requests::fragmentation::create_datagrams;
+use crate::requests::header::{Header, HEADER_LENGTH};
+use crate::requests::{ClientJob, Job, JobHandle};
+use std::net::SocketAddr;
 use std::time::Duration;
 use std::time::Instant;
 
 impl Job {
-    pub fn new(
-        handle: u8,
-        job_type: JobType,
-        raw_data: &mut Vec<u8>,
-        job_should_complite: u128,
-    ) -> Job {
+    pub fn new(header: Header, raw_data: &mut Vec<u8>) -> Job {
         // raw_data array will be created
-        // first byte is job handle, second byte is jobtype, rest is user data
-        let mut byte_array = vec![handle, get_job_single_byte(&job_type)];
+        // header bytes first (see header.rs), rest is user data
+        let mut byte_array = header.get_raw_data();
         byte_array.append(raw_data);
+        let datagrams = create_datagrams(&byte_array);
 
         Job {
-            handle: handle,
+            handle: header.handle,
             start_instant: Instant::now(),
-            job_type: job_type,
+            created_instant: Instant::now(),
+            job_type: header.job_type,
             raw_data: byte_array,
+            acked_datagrams: vec![false; datagrams.len()],
+            datagrams,
             pending: true,
             requested_count: 0,
-            job_should_complite: job_should_complite,
+            destination: None,
         }
     }
 
-    pub fn get_handle(&self) -> u8 {
+    pub fn with_destination(mut self, destination: SocketAddr) -> Job {
+        self.destination = Some(destination);
+        self
+    }
+
+    pub fn get_destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    pub fn get_handle(&self) -> JobHandle {
         self.handle
     }
 
+    pub fn get_client_job(&self) -> ClientJob {
+        self.job_type.1.clone()
+    }
+
+    // Time since the first send of the job.
+    pub fn get_elapsed(&self, now: Instant) -> Duration {
+        now.duration_since(self.created_instant)
+    }
+
     pub fn reset_start_instant(&mut self) {
         self.start_instant = Instant::now();
     }
@@ -39,18 +59,52 @@ impl Job {
         self.pending = false;
         now.duration_since(self.start_instant)
     }
+    // Cookie of server's challenge replaces the cookie at the start of the request.
+    // Datagrams are created again, and job is sent again at once.
+    pub fn set_challenge_cookie(&mut self, cookie: &[u8]) -> bool {
+        if cookie.len() != COOKIE_LENGTH || self.raw_data.len() < HEADER_LENGTH + COOKIE_LENGTH {
+            return false;
+        }
+        self.raw_data[HEADER_LENGTH..HEADER_LENGTH + COOKIE_LENGTH].copy_from_slice(cookie);
+        self.datagrams = create_datagrams(&self.raw_data);
+        self.acked_datagrams = vec![false; self.datagrams.len()];
+        self.reset_start_instant();
+        true
+    }
+
     pub fn get_raw_data(&self) -> Vec<u8> {
         self.raw_data.to_vec()
     }
 
-    pub fn is_pending_request_late(&self, now: Instant) -> bool {
+    pub fn get_datagrams(&self) -> Vec<Vec<u8>> {
+        self.datagrams.to_vec()
+    }
+
+    // Only datagrams not acknowledged by the other end are resent.
+    pub fn get_unacked_datagrams(&self) -> Vec<Vec<u8>> {
+        self.datagrams
+            .iter()
+            .zip(self.acked_datagrams.iter())
+            .filter(|(_datagram, acked)| !**acked)
+            .map(|(datagram, _acked)| datagram.to_vec())
+            .collect()
+    }
+
+    pub fn ack_fragment(&mut self, fragment_index: u8) {
+        if let Some(acked) = self.acked_datagrams.get_mut(fragment_index as usize) {
+            *acked = true;
+        }
+    }
+
+    // Timeout is the retransmission timeout, backed off for resend count of this job.
+    pub fn is_pending_request_late(&self, now: Instant, timeout: Duration) -> bool {
         // only check for pending requests
         if !self.pending {
             return false;
         }
 
         let job_duration = now.duration_since(self.start_instant);
-        if job_duration.as_millis() > self.job_should_complite {
+        if job_duration > timeout {
             return true;
         } else {
             return false;
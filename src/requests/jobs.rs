@@ -1,37 +1,52 @@
-use crate::requests::Job;
+use crate::requests::congestion::CongestionControl;
+use crate::requests::delivery::{DeliveryMode, Sequence, SequenceCounters};
+use crate::requests::rtt::RttEstimator;
+use crate::requests::{Job, JobHandle};
 use std::collections::HashMap;
-use std::collections::VecDeque;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::time::Duration;
 use std::time::Instant;
 
 pub struct Jobs {
-    pub jobs: Arc<Mutex<HashMap<u8, Job>>>,
-    next_job_handle: Mutex<u8>,
-    job_finish_times: Mutex<VecDeque<f64>>,
-    job_finish_time_average: Mutex<f64>,
+    pub jobs: Arc<Mutex<HashMap<JobHandle, Job>>>,
+    next_job_handle: Mutex<JobHandle>,
+    sequence_counters: Mutex<SequenceCounters>,
+    congestion_control: Mutex<CongestionControl>,
+    rtt_estimator: Mutex<RttEstimator>,
+    // Latest socket activity, heartbeats are sent and timeouts detected with these.
+    last_send_instant: Mutex<Instant>,
+    last_receive_instant: Mutex<Instant>,
+    // Latest heartbeat probe of server, and when it was received.
+    heartbeat_probe: Mutex<Option<(u16, Instant)>>,
+    last_heartbeat_instant: Mutex<Instant>,
     packages_handled: Mutex<u128>,
     packages_failed: Mutex<u128>,
-    average_set_instant: Mutex<Instant>,
 }
 
 impl Jobs {
     pub fn new() -> Jobs {
         Jobs {
-            jobs: Arc::new(Mutex::new(HashMap::<u8, Job>::new())),
+            jobs: Arc::new(Mutex::new(HashMap::<JobHandle, Job>::new())),
             next_job_handle: Mutex::new(0),
-            job_finish_times: Mutex::new(VecDeque::new()),
-            job_finish_time_average: Mutex::new(500.0),
+            sequence_counters: Mutex::new(SequenceCounters::new()),
+            congestion_control: Mutex::new(CongestionControl::new()),
+            rtt_estimator: Mutex::new(RttEstimator::new()),
+            last_send_instant: Mutex::new(Instant::now()),
+            last_receive_instant: Mutex::new(Instant::now()),
+            heartbeat_probe: Mutex::new(None),
+            last_heartbeat_instant: Mutex::new(Instant::now()),
             packages_handled: Mutex::new(0),
             packages_failed: Mutex::new(0),
-            average_set_instant: Mutex::new(Instant::now()),
         }
     }
 
+    // Smoothed round trip time in milliseconds, 500 before the first sample.
     pub fn get_ping(&self) -> f64 {
-        let job_finish_time_average_changer = self.job_finish_time_average.lock().unwrap();
-        *job_finish_time_average_changer
+        let rtt_estimator_changer = self.rtt_estimator.lock().unwrap();
+        (*rtt_estimator_changer)
+            .get_smoothed_rtt()
+            .map_or(500.0, |rtt| rtt.as_secs_f64() * 1000.0)
     }
 
     pub fn add_packages_failed(&self) {
@@ -39,39 +54,51 @@ impl Jobs {
         (*packages_failed_changer) += 1;
     }
 
-    pub fn get_job_finish_time_average(&self) -> f64 {
-        let job_finish_time_average_changer = self.job_finish_time_average.lock().unwrap();
-        *job_finish_time_average_changer
-    }
-
     pub fn add_packages_handled(&self) {
         let mut packages_handled_changer = self.packages_handled.lock().unwrap();
         (*packages_handled_changer) += 1;
     }
 
-    pub fn insert_job(&self, handle: u8, job: Job) {
+    pub fn insert_job(&self, handle: JobHandle, job: Job) {
         let mut jobs_changer = self.jobs.lock().unwrap();
         (*jobs_changer).insert(handle, job);
         println!("{}", (*jobs_changer).len());
     }
 
-    pub fn remove_job(&self, handle: u8) {
+    pub fn ack_fragment(&self, handle: JobHandle, fragment_index: u8) {
+        let mut jobs_changer = self.jobs.lock().unwrap();
+        if let Some(job) = (*jobs_changer).get_mut(&handle) {
+            job.ack_fragment(fragment_index);
+        }
+    }
+
+    pub fn remove_job(&self, handle: JobHandle) {
         let job_duration_maybe = self.get_job_duration(handle);
         match job_duration_maybe {
             None => {}
-            Some(job_duration) => {
-                self.add_finish_time(job_duration);
+            Some((job_duration, requested_count)) => {
+                // Duration of resent job is ambiguous, it does not tell round trip time.
+                let rtt = if requested_count == 0 {
+                    self.rtt_estimator.lock().unwrap().add_sample(job_duration);
+                    Some(job_duration)
+                } else {
+                    None
+                };
+                self.congestion_control.lock().unwrap().on_ack(rtt);
             }
         }
         let mut jobs_changer = self.jobs.lock().unwrap();
         (*jobs_changer).remove(&handle);
     }
 
-    /// fns related to ping counting,
-    /// --> job durations are collected to queue,
-    /// --> average is calculated in every 10 secs
+    // Job, which could not be sent, tells nothing about round trip time or send rate.
+    pub fn abandon_job(&self, handle: JobHandle) {
+        let mut jobs_changer = self.jobs.lock().unwrap();
+        (*jobs_changer).remove(&handle);
+    }
 
-    fn get_job_duration(&self, handle: u8) -> Option<Duration> {
+    // Returns job duration and how many times job has been resent.
+    fn get_job_duration(&self, handle: JobHandle) -> Option<(Duration, i8)> {
         let mut jobs_changer = self.jobs.lock().unwrap();
         let job_maybe = jobs_changer.get_mut(&handle);
         // Job is not found, something went wrong.
@@ -83,83 +110,99 @@ impl Jobs {
             return None;
         }
         let job = job_maybe.unwrap();
-        Some(job.finish())
+        Some((job.finish(), job.requested_count))
     }
 
-    fn push_job_duration_to_queue(&self, duration: Duration) {
-        let mut job_finish_times_changer = self.job_finish_times.lock().unwrap();
-
-        (*job_finish_times_changer).push_back((duration.as_micros() as f64) / 1000.0);
-        if (*job_finish_times_changer).len() > 200 {
-            (*job_finish_times_changer).pop_front();
+    // Handle wraps around, handles of jobs still in flight are skipped.
+    pub fn get_next_job_handle(&self) -> JobHandle {
+        let jobs_changer = self.jobs.lock().unwrap();
+        let mut next_job_handle_changer = self.next_job_handle.lock().unwrap();
+        let mut next_handle_before_addition = *next_job_handle_changer;
+        while (*jobs_changer).contains_key(&next_handle_before_addition)
+            && (*jobs_changer).len() < JobHandle::MAX as usize
+        {
+            next_handle_before_addition = next_handle_before_addition.wrapping_add(1);
         }
+        *next_job_handle_changer = next_handle_before_addition.wrapping_add(1);
+        next_handle_before_addition
     }
 
-    fn has_10_secs_passed_since_last_average_calucation(&self) -> bool {
-        let average_set_instant_changer = self.average_set_instant.lock().unwrap();
-        let now = Instant::now();
-        let last_average_set_secs_ago =
-            (now.duration_since(*average_set_instant_changer)).as_secs();
+    // Every delivery mode has its own sequence space.
+    pub fn get_next_sequence(&self, mode: DeliveryMode) -> Sequence {
+        let mut sequence_counters_changer = self.sequence_counters.lock().unwrap();
+        (*sequence_counters_changer).get_next_sequence(mode)
+    }
 
-        last_average_set_secs_ago > 9
+    // Sequences start from 0 again, when session is resumed.
+    pub fn reset_sequences(&self) {
+        *(self.sequence_counters.lock().unwrap()) = SequenceCounters::new();
     }
 
-    fn set_instant_as_now(&self) {
-        let mut average_set_instant_changer = self.average_set_instant.lock().unwrap();
-        *average_set_instant_changer = Instant::now();
+    // Resend timeout of a job, which has been resent resend_count times.
+    pub fn get_retransmission_timeout(&self, resend_count: i8) -> Duration {
+        let rtt_estimator_changer = self.rtt_estimator.lock().unwrap();
+        (*rtt_estimator_changer).get_backed_off_timeout(resend_count.max(0) as u32)
     }
 
-    pub fn add_finish_time(&self, duration: Duration) {
-        // Jobs collects job durations to calculate
-        self.push_job_duration_to_queue(duration);
+    pub fn mark_sent(&self) {
+        *(self.last_send_instant.lock().unwrap()) = Instant::now();
+    }
 
-        // set average every ten seconds
-        if self.has_10_secs_passed_since_last_average_calucation() {
-            self.set_instant_as_now();
-            self.set_average_finish_time();
-        }
+    pub fn mark_received(&self) {
+        *(self.last_receive_instant.lock().unwrap()) = Instant::now();
     }
 
-    pub fn get_next_job_handle(&self) -> u8 {
-        let mut next_job_handle_changer = self.next_job_handle.lock().unwrap();
-        let next_handle_before_addition = *next_job_handle_changer;
-        if *next_job_handle_changer < 255 {
-            *next_job_handle_changer += 1;
-        } else {
-            *next_job_handle_changer = 0;
-        }
-        next_handle_before_addition
+    pub fn get_send_idle_time(&self, now: Instant) -> Duration {
+        now.duration_since(*(self.last_send_instant.lock().unwrap()))
     }
 
-    fn set_average_finish_time(&self) {
-        // if there is less than 5 instances of jobs times, return 500 millis just to be sure.
-        let job_finnish_times_changer = self.job_finish_times.lock().unwrap();
+    pub fn get_receive_idle_time(&self, now: Instant) -> Duration {
+        now.duration_since(*(self.last_receive_instant.lock().unwrap()))
+    }
 
-        if (*job_finnish_times_changer).len() < 5 {
-            *(self.job_finish_time_average.lock().unwrap()) = 500.0;
-            return;
-        }
-        let mut calculated: f64 = 0.0;
-        for job_finish_time in (*job_finnish_times_changer).iter() {
-            calculated += job_finish_time;
-        }
-        let average = calculated / ((*job_finnish_times_changer).len() as f64);
-        println!(
-            "calulated average ping {}, packages handled {}, failed {}",
-            average,
-            *(self.packages_handled.lock().unwrap()),
-            *(self.packages_failed.lock().unwrap())
-        );
-
-        // if under 1, use 10.0, and do not use over 500.0 ms average.
-        *(self.job_finish_time_average.lock().unwrap()) = if average <= 1.0 {
-            10.0
-        } else {
-            if average < 500.0 {
-                average
-            } else {
-                500.0
-            }
-        };
+    pub fn set_heartbeat_probe(&self, probe_id: u16, now: Instant) {
+        *(self.heartbeat_probe.lock().unwrap()) = Some((probe_id, now));
+    }
+
+    // Returns probe id and hold time of the latest probe, which is echoed once.
+    pub fn take_heartbeat_probe(&self, now: Instant) -> Option<(u16, Duration)> {
+        self.heartbeat_probe
+            .lock()
+            .unwrap()
+            .take()
+            .map(|(probe_id, received_instant)| (probe_id, now.duration_since(received_instant)))
+    }
+
+    pub fn mark_heartbeat_sent(&self, now: Instant) {
+        *(self.last_heartbeat_instant.lock().unwrap()) = now;
+    }
+
+    pub fn get_heartbeat_idle_time(&self, now: Instant) -> Duration {
+        now.duration_since(*(self.last_heartbeat_instant.lock().unwrap()))
+    }
+
+    // fns related to congestion control,
+    // --> datagrams are sent, if send budget allows,
+    // --> acknowledged jobs increase and resent jobs decrease the send rate.
+
+    pub fn try_send(&self, number_of_bytes: usize, mode: DeliveryMode) -> bool {
+        let mut congestion_control_changer = self.congestion_control.lock().unwrap();
+        (*congestion_control_changer).try_send(number_of_bytes, mode)
+    }
+
+    // Allowed send rate in bytes per second.
+    pub fn get_send_rate(&self) -> f64 {
+        let congestion_control_changer = self.congestion_control.lock().unwrap();
+        (*congestion_control_changer).get_bytes_per_second()
+    }
+
+    pub fn get_send_wait_time(&self, number_of_bytes: usize) -> Duration {
+        let mut congestion_control_changer = self.congestion_control.lock().unwrap();
+        (*congestion_control_changer).get_wait_time(number_of_bytes)
+    }
+
+    pub fn add_lost_job(&self) {
+        let mut congestion_control_changer = self.congestion_control.lock().unwrap();
+        (*congestion_control_changer).on_loss();
     }
 }
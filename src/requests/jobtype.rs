@@ -11,6 +11,13 @@ impl ClientJob {
             "PlayerEnterRequest",
             "PlayerLeaveRequest",
             "PingRequest",
+            "HeartbeatRequest",
+            "PushAck",
+            "ResumeRequest",
+            "PeerPunch",
+            "PeerDataPush",
+            "TargetedPushRequest",
+            "RoomRequest",
         ]
     }
 }
@@ -27,6 +34,13 @@ impl ServerJob {
             "PlayerLeaveResponse",
             "PlayerLeavePush",
             "PongResponse",
+            "HeartbeatResponse",
+            "ChallengeResponse",
+            "ResumeResponse",
+            "PlayerReconnectPush",
+            "TargetedPushResponse",
+            "RoomResponse",
+            "RoomPush",
         ]
     }
 }
@@ -43,6 +57,13 @@ pub fn get_job_bytes(job_type: &JobType) -> (u8, u8) {
             ServerJob::PlayerLeaveResponse => 6,
             ServerJob::PlayerLeavePush => 7,
             ServerJob::PongResponse => 8,
+            ServerJob::HeartbeatResponse => 9,
+            ServerJob::ChallengeResponse => 10,
+            ServerJob::ResumeResponse => 11,
+            ServerJob::PlayerReconnectPush => 12,
+            ServerJob::TargetedPushResponse => 13,
+            ServerJob::RoomResponse => 14,
+            ServerJob::RoomPush => 15,
         },
         match job_type.1 {
             ClientJob::NoClientAction => 0,
@@ -51,6 +72,13 @@ pub fn get_job_bytes(job_type: &JobType) -> (u8, u8) {
             ClientJob::PlayerEnterRequest => 3,
             ClientJob::PlayerLeaveRequest => 4,
             ClientJob::PingRequest => 5,
+            ClientJob::HeartbeatRequest => 6,
+            ClientJob::PushAck => 7,
+            ClientJob::ResumeRequest => 8,
+            ClientJob::PeerPunch => 9,
+            ClientJob::PeerDataPush => 10,
+            ClientJob::TargetedPushRequest => 11,
+            ClientJob::RoomRequest => 12,
         },
     )
 }
@@ -79,6 +107,13 @@ pub fn get_job_type(server_client: &(u8, u8)) -> Option<JobType> {
         6 => Some(ServerJob::PlayerLeaveResponse),
         7 => Some(ServerJob::PlayerLeavePush),
         8 => Some(ServerJob::PongResponse),
+        9 => Some(ServerJob::HeartbeatResponse),
+        10 => Some(ServerJob::ChallengeResponse),
+        11 => Some(ServerJob::ResumeResponse),
+        12 => Some(ServerJob::PlayerReconnectPush),
+        13 => Some(ServerJob::TargetedPushResponse),
+        14 => Some(ServerJob::RoomResponse),
+        15 => Some(ServerJob::RoomPush),
         __ => None,
     };
 
@@ -89,6 +124,13 @@ pub fn get_job_type(server_client: &(u8, u8)) -> Option<JobType> {
         3 => Some(ClientJob::PlayerEnterRequest),
         4 => Some(ClientJob::PlayerLeaveRequest),
         5 => Some(ClientJob::PingRequest),
+        6 => Some(ClientJob::HeartbeatRequest),
+        7 => Some(ClientJob::PushAck),
+        8 => Some(ClientJob::ResumeRequest),
+        9 => Some(ClientJob::PeerPunch),
+        10 => Some(ClientJob::PeerDataPush),
+        11 => Some(ClientJob::TargetedPushRequest),
+        12 => Some(ClientJob::RoomRequest),
         __ => None,
     };
 
@@ -1,6 +1,14 @@
+use crate::client::datahandlers::structs::request::{AbandonedRequest, RequestError};
+use crate::client::pendingrequests::PendingRequests;
+use crate::client::RequestEvents;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::heartbeat::create_heartbeat_echo;
 use crate::requests::jobs::Jobs;
 use crate::requests::Job;
 use crate::requests::JobAction;
+use crate::requests::JobHandle;
+use crate::requests::{ClientJob, ServerJob};
 use std::collections::HashMap;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
@@ -13,18 +21,28 @@ use std::thread::JoinHandle;
 use std::time::Duration;
 use std::time::Instant;
 
+// Busy connection sends heartbeat this often, so that server samples round trip time.
+const HEARTBEAT_PROBE_INTERVAL: Duration = Duration::from_secs(2);
+
 /*
  * Init job handler
  *
  * Job handler checks if there is problematic jobs
  *
- * Static function that takes jobs.jobs aka Arc<Mutex<HashMap<u8, Job>>> as first parameter.
+ * Static function that takes jobs.jobs aka Arc<Mutex<HashMap<JobHandle, Job>>> as first parameter.
+ * Jobs that fail after all resends are reported with RequestEvents::on_request_abandoned.
+ * Pending requests, which are not answered in time, are resolved with timeout.
+ * Cancelled requests are removed from jobs.
  */
-pub fn run_job_handler(
+pub fn run_job_handler<S>(
     jobs: Arc<Jobs>,
-    socket_send_channel_tx: Sender<(Vec<u8>, Job)>,
+    pending_requests: Arc<PendingRequests>,
+    socket_send_channel_tx: Sender<(Vec<u8>, Arc<Job>)>,
     time_to_die: Arc<AtomicBool>,
-) {
+    events: Arc<Mutex<S>>,
+) where
+    S: RequestEvents,
+{
     loop {
         thread::sleep(Duration::from_millis(10));
 
@@ -34,29 +52,46 @@ pub fn run_job_handler(
         }
 
         let mut jobs_changer = jobs.jobs.lock().unwrap();
+        // Cancelled requests are not resent.
+        for cancelled_handle in pending_requests.take_cancelled() {
+            jobs_changer.remove(&cancelled_handle);
+        }
         let now = Instant::now();
-        let mut failed_job_indexes = Vec::<u8>::new();
+        let mut failed_job_indexes = Vec::<JobHandle>::new();
+        let mut abandoned_requests = Vec::<AbandonedRequest>::new();
         for (job_handle, job) in &mut *jobs_changer {
-            if job.is_pending_request_late(now) {
+            let timeout = jobs.get_retransmission_timeout(job.requested_count);
+            if job.is_pending_request_late(now, timeout) {
                 if job.requested_count < 10 {
                     // resend failed message
                     println!("PACKED FAILED. RESEND NUMBER {}!", job.requested_count);
+                    // Late job is counted as lost, which slows sending down.
+                    jobs.add_lost_job();
                     job.reset_start_instant();
                     job.requested_count += 1;
                     //let result = socket.send(&job.raw_data);
-                    let result = socket_send_channel_tx.send((job.raw_data.clone(), job.clone()));
-                    if result.is_err() {
-                        panic!("channel error state");
-                    }
-                    // if connection fails, remove job.
-                    if result.is_err() {
-                        job.pending = false;
-                        failed_job_indexes.push(*job_handle);
+                    // Fragmented job resends only fragments that are not acknowledged.
+                    // Fragments share one copy of the job.
+                    let sent_job = Arc::new(job.clone());
+                    for datagram in job.get_unacked_datagrams() {
+                        let result = socket_send_channel_tx.send((datagram, Arc::clone(&sent_job)));
+                        // if sender thread is closed, remove job.
+                        if result.is_err() {
+                            job.pending = false;
+                            failed_job_indexes.push(*job_handle);
+                            break;
+                        }
                     }
                 // Too many retryes, let's cancel the job.
                 } else {
                     job.pending = false;
                     failed_job_indexes.push(*job_handle);
+                    abandoned_requests.push(AbandonedRequest {
+                        handle: *job_handle,
+                        client_job: job.get_client_job(),
+                        attempts: job.requested_count as u32 + 1,
+                        elapsed: job.get_elapsed(now),
+                    });
                 }
             }
         }
@@ -65,6 +100,86 @@ pub fn run_job_handler(
             println!("index remover {}", failed_job_index);
             (*jobs_changer).remove(&failed_job_index);
         }
+        // jobs are released before events are raised.
+        drop(jobs_changer);
+        for abandoned_request in abandoned_requests {
+            pending_requests.resolve(
+                abandoned_request.handle,
+                Err(RequestError::Abandoned(abandoned_request.clone())),
+            );
+            let mut events_changer = events.lock().unwrap();
+            (*events_changer).on_request_abandoned(abandoned_request);
+        }
+        pending_requests.expire(now);
+    }
+}
+
+/*
+ * Init heartbeat
+ *
+ * Heartbeat is sent, if nothing has been sent within keepalive interval, so that
+ * server knows client is alive. Server answers heartbeats, so idle connection
+ * receives data too. If nothing is received within connection timeout,
+ * RequestEvents::on_disconnect is raised.
+ *
+ * Heartbeat echoes the latest probe of server, which gives server a round trip
+ * time sample. Busy connection sends heartbeat every probe interval for that.
+ *
+ * Static function that takes Arc<Jobs> as first parameter.
+ */
+pub fn run_heartbeat<S>(
+    jobs: Arc<Jobs>,
+    socket_send_channel_tx: Sender<(Vec<u8>, Arc<Job>)>,
+    time_to_die: Arc<AtomicBool>,
+    events: Arc<Mutex<S>>,
+    keepalive_interval: Duration,
+    connection_timeout: Duration,
+) where
+    S: RequestEvents,
+{
+    let mut is_disconnected = false;
+    loop {
+        thread::sleep(Duration::from_millis(10));
+
+        // break if closing...
+        if time_to_die.load(Ordering::SeqCst) {
+            break;
+        }
+
+        let now = Instant::now();
+        if jobs.get_send_idle_time(now) > keepalive_interval
+            || jobs.get_heartbeat_idle_time(now) > HEARTBEAT_PROBE_INTERVAL
+        {
+            let mode = DeliveryMode::Unreliable;
+            let header = Header::new(
+                jobs.get_next_job_handle(),
+                (ServerJob::NoServerAction, ClientJob::HeartbeatRequest),
+                mode,
+                jobs.get_next_sequence(mode),
+            );
+            let mut raw_data = match jobs.take_heartbeat_probe(now) {
+                None => vec![],
+                Some((probe_id, hold_time)) => create_heartbeat_echo(probe_id, hold_time),
+            };
+            let job = Arc::new(Job::new(header, &mut raw_data));
+            for datagram in job.get_datagrams() {
+                let result = socket_send_channel_tx.send((datagram, Arc::clone(&job)));
+                if result.is_err() {
+                    println!("Socket send channel hang up.");
+                }
+            }
+            // Heartbeat is queued, do not queue more until it is sent.
+            jobs.mark_sent();
+            jobs.mark_heartbeat_sent(now);
+        }
+
+        // Disconnect is raised once, and again only after data has been received.
+        let is_timed_out = jobs.get_receive_idle_time(now) > connection_timeout;
+        if is_timed_out && !is_disconnected {
+            let mut events_changer = events.lock().unwrap();
+            (*events_changer).on_disconnect();
+        }
+        is_disconnected = is_timed_out;
     }
 }
 
@@ -75,7 +190,7 @@ pub fn run_job_handler(
  */
 pub fn run_job_channel_consumer(
     jobs: Arc<Jobs>,
-    job_channel_rx: Receiver<(JobAction, u8, Option<Job>)>,
+    job_channel_rx: Receiver<(JobAction, JobHandle, Option<Job>)>,
     time_to_die: Arc<AtomicBool>,
 ) {
     loop {
@@ -84,10 +199,11 @@ pub fn run_job_channel_consumer(
             break;
         }
 
+        // Channel is disconnected, when every sender has been dropped on close.
         let result = job_channel_rx.recv();
         match result {
-            Err(e) => {
-                println!("Job handler consumer failed: {}", e);
+            Err(_e) => {
+                break;
             }
             Ok(data) => {
                 // tuple of job_action, job_index, and optional job
@@ -107,12 +223,18 @@ pub fn run_job_channel_consumer(
                     JobAction::REMOVE => {
                         jobs.remove_job(job_handle);
                     }
+                    JobAction::ABANDON => {
+                        jobs.abandon_job(job_handle);
+                    }
                     JobAction::INCFAILED => {
                         jobs.add_packages_failed();
                     }
                     JobAction::INCHANDLED => {
                         jobs.add_packages_handled();
                     }
+                    JobAction::ACKFRAGMENT(fragment_index) => {
+                        jobs.ack_fragment(job_handle, fragment_index);
+                    }
                 }
             }
         }
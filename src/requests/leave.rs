@@ -0,0 +1,30 @@
+/*
+ * Leave reason
+ *
+ * Player leave push tells, why the player left. Reason is one byte after the
+ * player number.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LeaveReason {
+    // Player sent leave request.
+    Quit = 1,
+    // Nothing was received from the player, and session was not resumed in time.
+    Timeout = 2,
+    // Server removed the player.
+    Kicked = 3,
+}
+
+impl LeaveReason {
+    pub fn get_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<LeaveReason> {
+        match byte {
+            1 => Some(LeaveReason::Quit),
+            2 => Some(LeaveReason::Timeout),
+            3 => Some(LeaveReason::Kicked),
+            _ => None,
+        }
+    }
+}
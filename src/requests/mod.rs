@@ -1,31 +1,59 @@
+pub mod batching;
+pub mod challenge;
+pub mod congestion;
+pub mod data;
+pub mod delivery;
+pub mod fragmentation;
+pub mod header;
+pub mod heartbeat;
 pub mod job;
 pub mod jobs;
 pub mod jobtype;
 pub mod jobworkers;
+pub mod leave;
+pub mod room;
+pub mod rtt;
+pub mod session;
+pub mod targetedpush;
 
+use std::net::SocketAddr;
 use std::time::Instant;
 
 pub enum JobAction {
     REMOVE,
+    // Job could not be sent, it is removed without acknowledgement.
+    ABANDON,
     ADD,
     INCFAILED,
     INCHANDLED,
+    // Fragment of a job is received by the other end, holds fragment index.
+    ACKFRAGMENT(u8),
 }
 
 #[derive(Clone)]
 pub struct Job {
-    handle: u8,
+    handle: JobHandle,
+    // Instant of the latest send.
     pub start_instant: Instant,
+    // Instant of the first send.
+    created_instant: Instant,
     pub pending: bool,
     pub raw_data: Vec<u8>,
+    // Raw data as datagrams, more than one if message is fragmented.
+    datagrams: Vec<Vec<u8>>,
+    acked_datagrams: Vec<bool>,
     pub job_type: JobType,
     pub requested_count: i8,
-    job_should_complite: u128,
+    // Address of the player, if job is sent straight to a peer instead of server.
+    destination: Option<SocketAddr>,
 }
 
 pub type JobType = (ServerJob, ClientJob);
 
-#[derive(Clone)]
+// Job handle is 16 bits wide, so that handles are not reused while jobs are in flight.
+pub type JobHandle = u16;
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ClientJob {
     NoClientAction = 0,
     DataPushRequest = 1,
@@ -33,9 +61,16 @@ pub enum ClientJob {
     PlayerEnterRequest = 3,
     PlayerLeaveRequest = 4,
     PingRequest = 5,
+    HeartbeatRequest = 6,
+    PushAck = 7,
+    ResumeRequest = 8,
+    PeerPunch = 9,
+    PeerDataPush = 10,
+    TargetedPushRequest = 11,
+    RoomRequest = 12,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum ServerJob {
     NoServerAction = 0,
     DataPush = 1,
@@ -46,4 +81,11 @@ pub enum ServerJob {
     PlayerLeaveResponse = 6,
     PlayerLeavePush = 7,
     PongResponse = 8,
+    HeartbeatResponse = 9,
+    ChallengeResponse = 10,
+    ResumeResponse = 11,
+    PlayerReconnectPush = 12,
+    TargetedPushResponse = 13,
+    RoomResponse = 14,
+    RoomPush = 15,
 }
@@ -0,0 +1,314 @@
+pub const MAX_ROOM_NAME_LENGTH: usize = 32;
+
+const LIST_OPERATION: u8 = 1;
+const CREATE_OPERATION: u8 = 2;
+const JOIN_OPERATION: u8 = 3;
+const LEAVE_OPERATION: u8 = 4;
+
+const ROOMS_RESPONSE: u8 = 1;
+const JOINED_RESPONSE: u8 = 2;
+const LEFT_RESPONSE: u8 = 3;
+
+const CREATED_EVENT: u8 = 1;
+const PLAYER_JOINED_EVENT: u8 = 2;
+const PLAYER_LEFT_EVENT: u8 = 3;
+const CLOSED_EVENT: u8 = 4;
+
+/*
+ * Room operation
+ *
+ * Room request: [operation byte][operation data]
+ *
+ * List has no data. Create carries capacity byte and room name, creator joins
+ * the room. Join carries room id.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoomOperation {
+    List,
+    Create { name: String, capacity: u8 },
+    Join { room_id: u8 },
+    Leave,
+}
+
+// Status of room operation, first byte of room response.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RoomStatus {
+    Ok = 0,
+    NotFound = 1,
+    Full = 2,
+    // Player leaves the current room, before joining another.
+    AlreadyInRoom = 3,
+    NotInRoom = 4,
+    InvalidRequest = 5,
+    // Every room id is in use.
+    NoFreeRoom = 6,
+}
+
+impl RoomStatus {
+    pub fn get_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<RoomStatus> {
+        match byte {
+            0 => Some(RoomStatus::Ok),
+            1 => Some(RoomStatus::NotFound),
+            2 => Some(RoomStatus::Full),
+            3 => Some(RoomStatus::AlreadyInRoom),
+            4 => Some(RoomStatus::NotInRoom),
+            5 => Some(RoomStatus::InvalidRequest),
+            6 => Some(RoomStatus::NoFreeRoom),
+            _ => None,
+        }
+    }
+}
+
+// Room as listed: [room id][capacity][player count][name length][name]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoomInfo {
+    pub room_id: u8,
+    pub name: String,
+    pub capacity: u8,
+    pub player_count: u8,
+}
+
+// Player in a room, numbered inside the room: [player number][name length][name]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoomPlayer {
+    pub player_number: u8,
+    pub player_name: String,
+}
+
+// Room joined by the client, with own player number and players in the room.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JoinedRoom {
+    pub room: RoomInfo,
+    pub player_number: u8,
+    pub players: Vec<RoomPlayer>,
+}
+
+/*
+ * Room response
+ *
+ * [status byte], followed by [response type byte][response data] if status is Ok.
+ *
+ * Rooms: [room count][rooms]
+ * Joined: [room][own player number][player count][players]
+ * Left: [room id]
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoomResponse {
+    Rooms(Vec<RoomInfo>),
+    Joined(JoinedRoom),
+    Left(u8),
+    Error(RoomStatus),
+}
+
+/*
+ * Room event
+ *
+ * Room push: [event type byte][event data]
+ *
+ * Members of a room get pushes of players joining and leaving the room, and of
+ * room closing. Server code gets every event, creation included.
+ */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoomEvent {
+    Created(RoomInfo),
+    PlayerJoined { room_id: u8, player: RoomPlayer },
+    PlayerLeft { room_id: u8, player_number: u8 },
+    Closed { room_id: u8 },
+}
+
+pub fn create_room_request(operation: &RoomOperation) -> Vec<u8> {
+    match operation {
+        RoomOperation::List => vec![LIST_OPERATION],
+        RoomOperation::Create { name, capacity } => {
+            let mut raw_data = vec![CREATE_OPERATION, *capacity];
+            raw_data.extend(name.as_bytes());
+            raw_data
+        }
+        RoomOperation::Join { room_id } => vec![JOIN_OPERATION, *room_id],
+        RoomOperation::Leave => vec![LEAVE_OPERATION],
+    }
+}
+
+// Returns None, if request is malformed.
+pub fn parse_room_request(raw_data: &[u8]) -> Option<RoomOperation> {
+    match raw_data {
+        [LIST_OPERATION] => Some(RoomOperation::List),
+        [CREATE_OPERATION, capacity, name @ ..] => Some(RoomOperation::Create {
+            name: String::from_utf8(name.to_vec()).ok()?,
+            capacity: *capacity,
+        }),
+        [JOIN_OPERATION, room_id] => Some(RoomOperation::Join { room_id: *room_id }),
+        [LEAVE_OPERATION] => Some(RoomOperation::Leave),
+        _ => None,
+    }
+}
+
+pub fn create_room_response(response: &RoomResponse) -> Vec<u8> {
+    match response {
+        RoomResponse::Error(status) => vec![status.get_byte()],
+        RoomResponse::Rooms(rooms) => {
+            let mut raw_data = vec![RoomStatus::Ok.get_byte(), ROOMS_RESPONSE, rooms.len() as u8];
+            for room in rooms {
+                append_room(&mut raw_data, room);
+            }
+            raw_data
+        }
+        RoomResponse::Joined(joined_room) => {
+            let mut raw_data = vec![RoomStatus::Ok.get_byte(), JOINED_RESPONSE];
+            append_room(&mut raw_data, &joined_room.room);
+            raw_data.push(joined_room.player_number);
+            raw_data.push(joined_room.players.len() as u8);
+            for player in &joined_room.players {
+                append_player(&mut raw_data, player);
+            }
+            raw_data
+        }
+        RoomResponse::Left(room_id) => vec![RoomStatus::Ok.get_byte(), LEFT_RESPONSE, *room_id],
+    }
+}
+
+// Returns None, if response is malformed.
+pub fn parse_room_response(raw_data: &[u8]) -> Option<RoomResponse> {
+    let (&status_byte, rest) = raw_data.split_first()?;
+    let status = RoomStatus::from_byte(status_byte)?;
+    if status != RoomStatus::Ok {
+        return match rest.is_empty() {
+            true => Some(RoomResponse::Error(status)),
+            false => None,
+        };
+    }
+
+    let mut reader = Reader { raw_data: rest };
+    let response = match reader.read_byte()? {
+        ROOMS_RESPONSE => {
+            let room_count = reader.read_byte()?;
+            let mut rooms = vec![];
+            for _i in 0..room_count {
+                rooms.push(reader.read_room()?);
+            }
+            RoomResponse::Rooms(rooms)
+        }
+        JOINED_RESPONSE => {
+            let room = reader.read_room()?;
+            let player_number = reader.read_byte()?;
+            let player_count = reader.read_byte()?;
+            let mut players = vec![];
+            for _i in 0..player_count {
+                players.push(reader.read_player()?);
+            }
+            RoomResponse::Joined(JoinedRoom {
+                room,
+                player_number,
+                players,
+            })
+        }
+        LEFT_RESPONSE => RoomResponse::Left(reader.read_byte()?),
+        _ => return None,
+    };
+    reader.finish(response)
+}
+
+pub fn create_room_push(event: &RoomEvent) -> Vec<u8> {
+    match event {
+        RoomEvent::Created(room) => {
+            let mut raw_data = vec![CREATED_EVENT];
+            append_room(&mut raw_data, room);
+            raw_data
+        }
+        RoomEvent::PlayerJoined { room_id, player } => {
+            let mut raw_data = vec![PLAYER_JOINED_EVENT, *room_id];
+            append_player(&mut raw_data, player);
+            raw_data
+        }
+        RoomEvent::PlayerLeft {
+            room_id,
+            player_number,
+        } => vec![PLAYER_LEFT_EVENT, *room_id, *player_number],
+        RoomEvent::Closed { room_id } => vec![CLOSED_EVENT, *room_id],
+    }
+}
+
+// Returns None, if push is malformed.
+pub fn parse_room_push(raw_data: &[u8]) -> Option<RoomEvent> {
+    let mut reader = Reader { raw_data };
+    let event = match reader.read_byte()? {
+        CREATED_EVENT => RoomEvent::Created(reader.read_room()?),
+        PLAYER_JOINED_EVENT => RoomEvent::PlayerJoined {
+            room_id: reader.read_byte()?,
+            player: reader.read_player()?,
+        },
+        PLAYER_LEFT_EVENT => RoomEvent::PlayerLeft {
+            room_id: reader.read_byte()?,
+            player_number: reader.read_byte()?,
+        },
+        CLOSED_EVENT => RoomEvent::Closed {
+            room_id: reader.read_byte()?,
+        },
+        _ => return None,
+    };
+    reader.finish(event)
+}
+
+// Names are cut to 255 bytes by the length byte, room names are shorter anyway.
+fn append_name(raw_data: &mut Vec<u8>, name: &str) {
+    let name_length = name.len().min(u8::MAX as usize);
+    raw_data.push(name_length as u8);
+    raw_data.extend(&name.as_bytes()[..name_length]);
+}
+
+fn append_room(raw_data: &mut Vec<u8>, room: &RoomInfo) {
+    raw_data.extend([room.room_id, room.capacity, room.player_count]);
+    append_name(raw_data, &room.name);
+}
+
+fn append_player(raw_data: &mut Vec<u8>, player: &RoomPlayer) {
+    raw_data.push(player.player_number);
+    append_name(raw_data, &player.player_name);
+}
+
+struct Reader<'a> {
+    raw_data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn read_byte(&mut self) -> Option<u8> {
+        let (&byte, rest) = self.raw_data.split_first()?;
+        self.raw_data = rest;
+        Some(byte)
+    }
+
+    fn read_name(&mut self) -> Option<String> {
+        let name_length = self.read_byte()? as usize;
+        let name = self.raw_data.get(..name_length)?;
+        self.raw_data = &self.raw_data[name_length..];
+        Some(String::from_utf8_lossy(name).to_string())
+    }
+
+    fn read_room(&mut self) -> Option<RoomInfo> {
+        Some(RoomInfo {
+            room_id: self.read_byte()?,
+            capacity: self.read_byte()?,
+            player_count: self.read_byte()?,
+            name: self.read_name()?,
+        })
+    }
+
+    fn read_player(&mut self) -> Option<RoomPlayer> {
+        Some(RoomPlayer {
+            player_number: self.read_byte()?,
+            player_name: self.read_name()?,
+        })
+    }
+
+    // Value is accepted only if all data was read.
+    fn finish<T>(&self, value: T) -> Option<T> {
+        match self.raw_data.is_empty() {
+            true => Some(value),
+            false => None,
+        }
+    }
+}
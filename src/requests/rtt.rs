@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+// Timeout before the first round trip time sample.
+const INITIAL_RETRANSMISSION_TIMEOUT: Duration = Duration::from_secs(1);
+// Retransmission timeout limits, lower limit keeps timer granularity from
+// causing spurious resends.
+const MIN_RETRANSMISSION_TIMEOUT: Duration = Duration::from_millis(100);
+const MAX_RETRANSMISSION_TIMEOUT: Duration = Duration::from_secs(10);
+// Smoothing gains of round trip time (1/8) and its variance (1/4).
+const RTT_GAIN: f64 = 0.125;
+const RTT_VARIANCE_GAIN: f64 = 0.25;
+// Timeout is smoothed round trip time plus this many variances.
+const VARIANCE_FACTOR: f64 = 4.0;
+
+/*
+ * Round trip time estimator
+ *
+ * Smoothed round trip time and round trip time variance, updated on every
+ * acknowledged job (see RFC 6298). Samples of resent jobs must not be added,
+ * since it is not known which send the response answers (Karn's rule).
+ */
+pub struct RttEstimator {
+    smoothed_rtt: Option<f64>,
+    rtt_variance: f64,
+}
+
+impl RttEstimator {
+    pub fn new() -> RttEstimator {
+        RttEstimator {
+            smoothed_rtt: None,
+            rtt_variance: 0.0,
+        }
+    }
+
+    pub fn add_sample(&mut self, rtt: Duration) {
+        let rtt = rtt.as_secs_f64();
+        match self.smoothed_rtt {
+            None => {
+                self.smoothed_rtt = Some(rtt);
+                self.rtt_variance = rtt / 2.0;
+            }
+            Some(smoothed_rtt) => {
+                self.rtt_variance = (1.0 - RTT_VARIANCE_GAIN) * self.rtt_variance
+                    + RTT_VARIANCE_GAIN * (smoothed_rtt - rtt).abs();
+                self.smoothed_rtt = Some((1.0 - RTT_GAIN) * smoothed_rtt + RTT_GAIN * rtt);
+            }
+        }
+    }
+
+    pub fn get_retransmission_timeout(&self) -> Duration {
+        match self.smoothed_rtt {
+            None => INITIAL_RETRANSMISSION_TIMEOUT,
+            Some(smoothed_rtt) => {
+                let timeout = smoothed_rtt + VARIANCE_FACTOR * self.rtt_variance;
+                Duration::from_secs_f64(timeout)
+                    .max(MIN_RETRANSMISSION_TIMEOUT)
+                    .min(MAX_RETRANSMISSION_TIMEOUT)
+            }
+        }
+    }
+
+    pub fn get_smoothed_rtt(&self) -> Option<Duration> {
+        self.smoothed_rtt.map(Duration::from_secs_f64)
+    }
+
+    // Mean deviation of round trip time, i.e. jitter.
+    pub fn get_rtt_variance(&self) -> Duration {
+        Duration::from_secs_f64(self.rtt_variance)
+    }
+
+    // Timeout is doubled for every resend of the job.
+    pub fn get_backed_off_timeout(&self, resend_count: u32) -> Duration {
+        let backoff = 2u32.saturating_pow(resend_count);
+        self.get_retransmission_timeout()
+            .checked_mul(backoff)
+            .unwrap_or(MAX_RETRANSMISSION_TIMEOUT)
+            .min(MAX_RETRANSMISSION_TIMEOUT)
+    }
+}
@@ -0,0 +1,15 @@
+use std::convert::TryInto;
+
+// Session token identifies the player, also after address of the player changes.
+pub type SessionToken = u64;
+pub const SESSION_TOKEN_LENGTH: usize = 8;
+
+pub fn create_session_token_data(session_token: SessionToken) -> Vec<u8> {
+    session_token.to_be_bytes().to_vec()
+}
+
+// Returns None, if data is too short to hold a session token.
+pub fn get_session_token(raw_data: &[u8]) -> Option<SessionToken> {
+    let token_data = raw_data.get(..SESSION_TOKEN_LENGTH)?;
+    Some(SessionToken::from_be_bytes(token_data.try_into().unwrap()))
+}
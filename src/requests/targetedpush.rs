@@ -0,0 +1,71 @@
+/*
+ * Targeted push
+ *
+ * Data push to chosen players, routed by server:
+ * [target count byte][target player numbers][data]
+ *
+ * Server forwards data only to the targets, as data push starting with player
+ * number of the sender. Push is rejected as a whole, if any target is not
+ * another player of the server. Requests of addresses, which are not players,
+ * are dropped. Response is sent for reliable push, and for rejected push in
+ * every mode:
+ * [status byte][rejected player numbers]
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetedPushStatus {
+    Done = 0,
+    // Some targets are not other players of the server, push is not forwarded.
+    UnknownTargets = 1,
+    // No targets or request could not be read.
+    InvalidRequest = 2,
+}
+
+impl TargetedPushStatus {
+    pub fn get_byte(&self) -> u8 {
+        *self as u8
+    }
+
+    pub fn from_byte(byte: u8) -> Option<TargetedPushStatus> {
+        match byte {
+            0 => Some(TargetedPushStatus::Done),
+            1 => Some(TargetedPushStatus::UnknownTargets),
+            2 => Some(TargetedPushStatus::InvalidRequest),
+            _ => None,
+        }
+    }
+}
+
+pub fn create_targeted_push_request(player_numbers: &[u8], raw_data: &[u8]) -> Vec<u8> {
+    let mut data = vec![player_numbers.len() as u8];
+    data.extend(player_numbers);
+    data.extend(raw_data);
+    data
+}
+
+// Returns target player numbers and data, None if there are no targets.
+pub fn parse_targeted_push_request(raw_data: &[u8]) -> Option<(Vec<u8>, Vec<u8>)> {
+    let (&target_count, rest) = raw_data.split_first()?;
+    if target_count == 0 {
+        return None;
+    }
+    let player_numbers = rest.get(..target_count as usize)?.to_vec();
+    Some((player_numbers, rest[target_count as usize..].to_vec()))
+}
+
+pub fn create_targeted_push_response(
+    status: TargetedPushStatus,
+    rejected_player_numbers: &[u8],
+) -> Vec<u8> {
+    let mut data = vec![status.get_byte()];
+    data.extend(rejected_player_numbers);
+    data
+}
+
+pub fn parse_targeted_push_response(raw_data: &[u8]) -> Option<(TargetedPushStatus, Vec<u8>)> {
+    let (&status_byte, rejected_player_numbers) = raw_data.split_first()?;
+    Some((
+        TargetedPushStatus::from_byte(status_byte)?,
+        rejected_player_numbers.to_vec(),
+    ))
+}
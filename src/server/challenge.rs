@@ -0,0 +1,71 @@
+use crate::requests::challenge::COOKIE_LENGTH;
+use crate::requests::session::SessionToken;
+use std::collections::hash_map::RandomState;
+use std::convert::TryInto;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::net::SocketAddr;
+use std::time::Instant;
+
+// Cookie must be echoed within this time.
+const COOKIE_LIFETIME_SECS: u32 = 10;
+
+/*
+ * Challenges
+ *
+ * Address of a new player is verified, before connection is created. Server answers
+ * the first player enter request with a cookie, which only the owner of the address
+ * receives. Cookie is signed with a random secret, so server keeps no state of
+ * challenged addresses.
+ */
+pub struct Challenges {
+    // Keys of SipHash, random for every server.
+    secret: RandomState,
+    start_instant: Instant,
+}
+
+impl Challenges {
+    pub fn new() -> Challenges {
+        Challenges {
+            secret: RandomState::new(),
+            start_instant: Instant::now(),
+        }
+    }
+
+    pub fn create_cookie(&self, addr: SocketAddr) -> Vec<u8> {
+        let created = self.get_elapsed_secs();
+        let mut cookie = created.to_be_bytes().to_vec();
+        cookie.extend_from_slice(&self.sign(addr, created).to_be_bytes());
+        cookie
+    }
+
+    pub fn is_valid(&self, addr: SocketAddr, cookie: &[u8]) -> bool {
+        if cookie.len() != COOKIE_LENGTH {
+            return false;
+        }
+        let created = u32::from_be_bytes(cookie[..4].try_into().unwrap());
+        let signature = u64::from_be_bytes(cookie[4..].try_into().unwrap());
+        let age = self.get_elapsed_secs().checked_sub(created);
+        matches!(age, Some(age) if age <= COOKIE_LIFETIME_SECS)
+            && signature == self.sign(addr, created)
+    }
+
+    // Session token is signed with the same secret, so that it can not be guessed.
+    pub fn create_session_token(&self, addr: SocketAddr, player_number: u8) -> SessionToken {
+        let mut hasher = self.secret.build_hasher();
+        addr.hash(&mut hasher);
+        player_number.hash(&mut hasher);
+        self.start_instant.elapsed().hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn sign(&self, addr: SocketAddr, created: u32) -> u64 {
+        let mut hasher = self.secret.build_hasher();
+        addr.hash(&mut hasher);
+        created.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn get_elapsed_secs(&self) -> u32 {
+        self.start_instant.elapsed().as_secs() as u32
+    }
+}
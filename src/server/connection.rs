@@ -1,39 +1,551 @@
+use crate::requests::batching::{Batch, DEFAULT_FLUSH_INTERVAL};
+use crate::requests::congestion::CongestionControl;
+use crate::requests::delivery::{DeliveryMode, ReceiveChannels, SequenceCounters};
+use crate::requests::header::Header;
+use crate::requests::rtt::RttEstimator;
+use crate::requests::session::SessionToken;
+use crate::requests::{Job, JobHandle};
+use crate::server::challenge::Challenges;
+use crate::server::datastore::DataStore;
+use crate::server::rooms::Rooms;
+use crate::server::stats::{ConnectionStats, ServerCounters, StatsSampler};
+use crate::socket::Transport;
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Player is removed, if nothing is received from it within this time.
+const DEFAULT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+// Slot of a timed out player is held this long, so that player can resume the session.
+const DEFAULT_SESSION_GRACE_PERIOD: Duration = Duration::from_secs(30);
+// Reliable push is given up after this many resends.
+const MAX_PUSH_RESENDS: i8 = 10;
+// Resent requests are recognized among this many latest requests.
+const MAX_HANDLED_REQUESTS: usize = 64;
+
+// Reliable request handled for a connection, and responses sent to it.
+struct HandledRequest {
+    header: Vec<u8>,
+    responses: Vec<Vec<u8>>,
+}
 
 pub struct Connection {
-    pub connections_count: i32,
-    pub bytes_received: i128,
-    pub bytes_send: i128,
-    pub ping: u16,
     pub player_number: u8,
     pub player_name: String,
+    // Player resumes the session with the token, after address of the player changes.
+    pub session_token: SessionToken,
+    pub last_receive_instant: Instant,
+    // Listen socket, which received latest datagrams of the player. Datagrams to
+    // the player are sent with it, so that they come from the address the player sent to.
+    pub socket: Option<Arc<dyn Transport>>,
+    // Traffic of this connection, sampled continuously.
+    pub stats: StatsSampler,
+    // Sequence spaces for server originated pushes to this connection.
+    pub sequence_counters: SequenceCounters,
+    // Ordering of requests received from this connection.
+    pub receive_channels: ReceiveChannels<(Header, Vec<u8>)>,
+    // Send rate limit for this connection.
+    pub congestion_control: CongestionControl,
+    // Datagrams to this connection, waiting for flush.
+    pub batch: Batch,
+    // Reliable datagrams, which wait for send budget of the connection.
+    queued_datagrams: VecDeque<Vec<u8>>,
+    // Reliable pushes to this connection, followed up until client acknowledges them.
+    push_jobs: HashMap<JobHandle, Job>,
+    next_push_handle: JobHandle,
+    rtt_estimator: RttEstimator,
+    // Latest heartbeat probe sent to the client, and when it was sent.
+    heartbeat_probe: Option<(u16, Instant)>,
+    next_probe_id: u16,
+    // Latest reliable requests with cached responses, resent requests are
+    // answered from here and not handled again.
+    handled_requests: VecDeque<HandledRequest>,
 }
 
 impl Connection {
-    pub fn new(player_number: u8, player_name: String) -> Connection {
+    pub fn new(player_number: u8, player_name: String, session_token: SessionToken) -> Connection {
         Connection {
-            connections_count: 0,
-            bytes_received: 0,
-            bytes_send: 0,
-            ping: 0,
             player_name: player_name,
             player_number: player_number,
+            session_token: session_token,
+            last_receive_instant: Instant::now(),
+            socket: None,
+            stats: StatsSampler::new(Instant::now()),
+            sequence_counters: SequenceCounters::new(),
+            receive_channels: ReceiveChannels::new(),
+            congestion_control: CongestionControl::new(),
+            batch: Batch::new(),
+            queued_datagrams: VecDeque::new(),
+            push_jobs: HashMap::new(),
+            next_push_handle: 0,
+            rtt_estimator: RttEstimator::new(),
+            heartbeat_probe: None,
+            next_probe_id: 0,
+            handled_requests: VecDeque::new(),
+        }
+    }
+
+    // Session continues from a new address. Client may have started again, so its
+    // sequences and handles are tracked from scratch, and sequences of pushes start
+    // from 0. Pushes in flight are sent to the new address.
+    fn resume(&mut self, now: Instant) {
+        self.last_receive_instant = now;
+        self.receive_channels = ReceiveChannels::new();
+        self.sequence_counters = SequenceCounters::new();
+        self.stats.reset_sequences();
+        self.handled_requests.clear();
+        self.batch = Batch::new();
+        self.heartbeat_probe = None;
+    }
+
+    // Reliable datagram waits, if the send budget is exhausted or earlier datagrams
+    // are still waiting, so that reliable datagrams keep their order.
+    // Returns false, if datagram must not be sent now. Unreliable datagram is dropped then.
+    pub fn admit_datagram(&mut self, datagram: &[u8], mode: DeliveryMode) -> bool {
+        if mode.is_reliable() && !self.queued_datagrams.is_empty() {
+            self.queued_datagrams.push_back(datagram.to_vec());
+            return false;
+        }
+        if self.congestion_control.try_send(datagram.len(), mode) {
+            return true;
+        }
+        if mode.is_reliable() {
+            self.queued_datagrams.push_back(datagram.to_vec());
+        }
+        false
+    }
+
+    // Returns queued datagrams, which the send budget now allows, or every queued
+    // datagram if forced.
+    pub fn take_queued_datagrams(&mut self, force: bool) -> Vec<Vec<u8>> {
+        let mut datagrams = vec![];
+        while let Some(datagram) = self.queued_datagrams.front() {
+            if !force
+                && !self
+                    .congestion_control
+                    .try_send(datagram.len(), DeliveryMode::ReliableUnordered)
+            {
+                break;
+            }
+            datagrams.push(self.queued_datagrams.pop_front().unwrap());
+        }
+        datagrams
+    }
+
+    // Handle wraps around, handles of pushes still in flight are skipped.
+    pub fn get_next_push_handle(&mut self) -> JobHandle {
+        let mut handle = self.next_push_handle;
+        while self.push_jobs.contains_key(&handle) && self.push_jobs.len() < JobHandle::MAX as usize
+        {
+            handle = handle.wrapping_add(1);
+        }
+        self.next_push_handle = handle.wrapping_add(1);
+        handle
+    }
+
+    pub fn add_push_job(&mut self, job: Job) {
+        self.stats.add_sent_push(Instant::now());
+        self.push_jobs.insert(job.get_handle(), job);
+    }
+
+    pub fn get_stats(&self, addr: SocketAddr, now: Instant) -> ConnectionStats {
+        ConnectionStats {
+            player_number: self.player_number,
+            player_name: self.player_name.clone(),
+            addr,
+            rtt: self.rtt_estimator.get_smoothed_rtt(),
+            jitter: self.rtt_estimator.get_rtt_variance(),
+            loss_from_client: self.stats.get_loss_from_client(now),
+            loss_to_client: self.stats.get_loss_to_client(now),
+            packets_received_per_second: self.stats.get_packets_received_per_second(now),
+            packets_sent_per_second: self.stats.get_packets_sent_per_second(now),
+            bytes_received_per_second: self.stats.get_bytes_received_per_second(now),
+            bytes_sent_per_second: self.stats.get_bytes_sent_per_second(now),
+            since_last_packet: now.saturating_duration_since(self.last_receive_instant),
+            packets_received: self.stats.get_packets_received(),
+            packets_sent: self.stats.get_packets_sent(),
+            bytes_received: self.stats.get_bytes_received(),
+            bytes_sent: self.stats.get_bytes_sent(),
+        }
+    }
+
+    // Client acknowledged the push. Round trip time of a resent push is
+    // ambiguous, so it is not sampled (Karn's rule).
+    pub fn ack_push(&mut self, header: &Header) {
+        if !self.is_push_of(header) {
+            return;
+        }
+
+        let mut job = self.push_jobs.remove(&header.handle).unwrap();
+        let job_duration = job.finish();
+        let rtt = if job.requested_count == 0 {
+            self.rtt_estimator.add_sample(job_duration);
+            Some(job_duration)
+        } else {
+            None
+        };
+        self.congestion_control.on_ack(rtt);
+    }
+
+    // Heartbeat echoing the latest probe gives a round trip time sample, time the
+    // client held the probe excluded. Returns id of the next probe.
+    pub fn answer_heartbeat(&mut self, echo: Option<(u16, Duration)>, now: Instant) -> u16 {
+        if let (Some((probe_id, hold_time)), Some((sent_probe_id, sent_instant))) =
+            (echo, self.heartbeat_probe)
+        {
+            if probe_id == sent_probe_id {
+                if let Some(rtt) = now
+                    .saturating_duration_since(sent_instant)
+                    .checked_sub(hold_time)
+                {
+                    self.rtt_estimator.add_sample(rtt);
+                    self.congestion_control.on_rtt_sample(rtt);
+                }
+            }
+        }
+        let probe_id = self.next_probe_id;
+        self.next_probe_id = self.next_probe_id.wrapping_add(1);
+        self.heartbeat_probe = Some((probe_id, now));
+        probe_id
+    }
+
+    pub fn ack_push_fragment(&mut self, header: &Header, fragment_index: u8) {
+        if !self.is_push_of(header) {
+            return;
+        }
+        if let Some(job) = self.push_jobs.get_mut(&header.handle) {
+            job.ack_fragment(fragment_index);
+        }
+    }
+
+    // Acknowledgement carries the server job of the push, so that acknowledgements
+    // of responses are not mistaken for acknowledgements of pushes.
+    fn is_push_of(&self, header: &Header) -> bool {
+        match self.push_jobs.get(&header.handle) {
+            Some(job) => job.job_type.0 == header.get_server_job(),
+            None => false,
+        }
+    }
+
+    // Returns datagrams of late pushes, which must be sent again. Only fragments
+    // not acknowledged by the client are resent. Pushes are given up after
+    // too many resends. Resends and given up pushes are added to server counters.
+    pub fn get_late_push_datagrams(
+        &mut self,
+        now: Instant,
+        counters: &mut ServerCounters,
+    ) -> Vec<Vec<u8>> {
+        let mut datagrams = vec![];
+        let mut failed_handles = vec![];
+        for (handle, job) in &mut self.push_jobs {
+            let timeout = self
+                .rtt_estimator
+                .get_backed_off_timeout(job.requested_count as u32);
+            if !job.is_pending_request_late(now, timeout) {
+                continue;
+            }
+            if job.requested_count >= MAX_PUSH_RESENDS {
+                failed_handles.push(*handle);
+                continue;
+            }
+            job.reset_start_instant();
+            job.requested_count += 1;
+            self.stats.add_resent_push(now);
+            counters.pushes_resent += 1;
+            datagrams.append(&mut job.get_unacked_datagrams());
+        }
+
+        if !datagrams.is_empty() {
+            self.congestion_control.on_loss();
+        }
+        for handle in failed_handles {
+            println!(
+                "Push {} to player {} abandoned.",
+                handle, self.player_number
+            );
+            self.push_jobs.remove(&handle);
+            counters.pushes_abandoned += 1;
+        }
+        datagrams
+    }
+
+    // Returns cached responses, if request has already been handled.
+    pub fn get_cached_responses(&self, header: &Header) -> Option<Vec<Vec<u8>>> {
+        let header_data = header.get_raw_data();
+        self.handled_requests
+            .iter()
+            .find(|handled_request| handled_request.header == header_data)
+            .map(|handled_request| handled_request.responses.to_vec())
+    }
+
+    pub fn add_handled_request(&mut self, header: &Header) {
+        self.handled_requests.push_back(HandledRequest {
+            header: header.get_raw_data(),
+            responses: vec![],
+        });
+        if self.handled_requests.len() > MAX_HANDLED_REQUESTS {
+            self.handled_requests.pop_front();
         }
     }
+
+    // Response is cached for the request, request is added if it is not found
+    // (e.g. player enter request is received before connection exists).
+    pub fn cache_response(&mut self, header: &Header, response: &[u8]) {
+        let header_data = header.get_raw_data();
+        let handled_request_maybe = self
+            .handled_requests
+            .iter_mut()
+            .find(|handled_request| handled_request.header == header_data);
+        match handled_request_maybe {
+            Some(handled_request) => handled_request.responses.push(response.to_vec()),
+            None => {
+                self.add_handled_request(header);
+                self.handled_requests
+                    .back_mut()
+                    .unwrap()
+                    .responses
+                    .push(response.to_vec());
+            }
+        }
+    }
+}
+
+// Timed out connection, which holds its player slot during grace period.
+struct SuspendedConnection {
+    addr: SocketAddr,
+    connection: Connection,
+    suspend_instant: Instant,
 }
 
 pub struct Connections {
     pub connections: HashMap<SocketAddr, Connection>,
+    pub timeout: Duration,
+    pub session_grace_period: Duration,
+    suspended_connections: HashMap<SessionToken, SuspendedConnection>,
+    // Batched datagrams are sent, when flush interval has passed since last flush.
+    pub flush_interval: Duration,
+    flush_instant: Instant,
+    // Players, which server has kicked, removed by listener threads.
+    kicked_players: Vec<u8>,
+    // Traffic totals of the whole server.
+    pub counters: ServerCounters,
+    // Addresses of new players are verified with challenges. Listener threads share
+    // the secret, so that any of them accepts the cookie.
+    pub challenges: Challenges,
+    // Key-value data shared by the players.
+    pub data_store: DataStore,
+    // Game sessions, which players join from the lobby.
+    pub rooms: Rooms,
 }
 
 impl Connections {
     pub fn new() -> Connections {
         Connections {
             connections: HashMap::<SocketAddr, Connection>::new(),
+            timeout: DEFAULT_CONNECTION_TIMEOUT,
+            session_grace_period: DEFAULT_SESSION_GRACE_PERIOD,
+            suspended_connections: HashMap::new(),
+            flush_interval: DEFAULT_FLUSH_INTERVAL,
+            flush_instant: Instant::now(),
+            kicked_players: vec![],
+            counters: ServerCounters::default(),
+            challenges: Challenges::new(),
+            data_store: DataStore::new(),
+            rooms: Rooms::new(),
+        }
+    }
+
+    // Returns batched datagrams of every connection, if flush interval has passed
+    // or flush is forced.
+    pub fn take_batches(&mut self, now: Instant, force: bool) -> Vec<(SocketAddr, Vec<u8>)> {
+        if !force && now.duration_since(self.flush_instant) < self.flush_interval {
+            return vec![];
+        }
+
+        self.flush_instant = now;
+        let mut batches = vec![];
+        for (addr, connection) in self.connections.iter_mut() {
+            if let Some(datagram) = connection.batch.take() {
+                batches.push((*addr, datagram));
+            }
+        }
+        batches
+    }
+
+    // Connections, which have been silent longer than timeout, are suspended. Removes
+    // and returns suspended connections, whose grace period has passed.
+    pub fn remove_timed_out_connections(&mut self, now: Instant) -> Vec<(SocketAddr, Connection)> {
+        self.suspend_timed_out_connections(now);
+
+        let grace_period = self.session_grace_period;
+        let expired_tokens: Vec<SessionToken> = self
+            .suspended_connections
+            .iter()
+            .filter(|(_token, suspended)| {
+                now.duration_since(suspended.suspend_instant) >= grace_period
+            })
+            .map(|(token, _suspended)| *token)
+            .collect();
+
+        let mut expired_connections = vec![];
+        for token in expired_tokens {
+            let suspended = self.suspended_connections.remove(&token).unwrap();
+            expired_connections.push((suspended.addr, suspended.connection));
+        }
+        expired_connections
+    }
+
+    fn suspend_timed_out_connections(&mut self, now: Instant) {
+        let timeout = self.timeout;
+        let timed_out_addresses: Vec<SocketAddr> = self
+            .connections
+            .iter()
+            .filter(|(_addr, connection)| {
+                now.duration_since(connection.last_receive_instant) > timeout
+            })
+            .map(|(addr, _connection)| *addr)
+            .collect();
+
+        for addr in timed_out_addresses {
+            let connection = self.connections.remove(&addr).unwrap();
+            self.suspended_connections.insert(
+                connection.session_token,
+                SuspendedConnection {
+                    addr,
+                    connection,
+                    suspend_instant: now,
+                },
+            );
         }
     }
 
+    // Moves connection of the session to the new address. Session may be suspended
+    // or still active at the old address. Returns player number of the session,
+    // None if session is not found or the address belongs to another player.
+    pub fn resume_connection(
+        &mut self,
+        session_token: SessionToken,
+        addr: SocketAddr,
+        now: Instant,
+    ) -> Option<u8> {
+        if let Some(connection) = self.connections.get(&addr) {
+            if connection.session_token != session_token {
+                return None;
+            }
+        }
+        let active_addr = self
+            .connections
+            .iter()
+            .find(|(_addr, connection)| connection.session_token == session_token)
+            .map(|(addr, _connection)| *addr);
+        let mut connection = match active_addr {
+            Some(active_addr) => self.connections.remove(&active_addr).unwrap(),
+            None => {
+                self.suspended_connections
+                    .remove(&session_token)?
+                    .connection
+            }
+        };
+
+        connection.resume(now);
+        let player_number = connection.player_number;
+        self.connections.insert(addr, connection);
+        Some(player_number)
+    }
+
+    // Returns false, if player is not found.
+    pub fn kick_player(&mut self, player_number: u8) -> bool {
+        if !self
+            .get_players()
+            .any(|connection| connection.player_number == player_number)
+        {
+            return false;
+        }
+        self.kicked_players.push(player_number);
+        true
+    }
+
+    pub fn take_kicked_players(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.kicked_players)
+    }
+
+    // Returns false, if there is no suspended connection for the player.
+    pub fn remove_suspended_player(&mut self, player_number: u8) -> bool {
+        let session_token_maybe = self
+            .suspended_connections
+            .iter()
+            .find(|(_token, suspended)| suspended.connection.player_number == player_number)
+            .map(|(token, _suspended)| *token);
+        match session_token_maybe {
+            None => false,
+            Some(session_token) => self.suspended_connections.remove(&session_token).is_some(),
+        }
+    }
+
+    // Players of active and suspended connections.
+    fn get_players(&self) -> impl Iterator<Item = &Connection> {
+        self.connections.values().chain(
+            self.suspended_connections
+                .values()
+                .map(|suspended| &suspended.connection),
+        )
+    }
+
+    // Stats of active connections, ordered by player number.
+    pub fn get_stats(&self, now: Instant) -> Vec<ConnectionStats> {
+        let mut stats: Vec<ConnectionStats> = self
+            .connections
+            .iter()
+            .map(|(addr, connection)| connection.get_stats(*addr, now))
+            .collect();
+        stats.sort_by_key(|connection_stats| connection_stats.player_number);
+        stats
+    }
+
+    // Players, who get data pushes of the player: other members of its room, or other
+    // players in the lobby. Returns player number of the player in the scope, and
+    // player numbers and addresses of the receivers.
+    pub fn get_push_scope(&self, player_number: u8) -> (u8, Vec<(u8, SocketAddr)>) {
+        let (room_id, scope_player_number) = match self.rooms.get_room_of(player_number) {
+            None => {
+                let receivers = self
+                    .connections
+                    .iter()
+                    .filter(|(_addr, connection)| {
+                        connection.player_number != player_number
+                            && self.rooms.is_in_lobby(connection.player_number)
+                    })
+                    .map(|(addr, connection)| (connection.player_number, *addr))
+                    .collect();
+                return (player_number, receivers);
+            }
+            Some(room) => room,
+        };
+
+        let receivers = self
+            .rooms
+            .get_members(room_id)
+            .unwrap_or_default()
+            .iter()
+            .filter(|member| member.server_player_number != player_number)
+            .filter_map(|member| {
+                self.get_addr(member.server_player_number)
+                    .map(|addr| (member.player_number, addr))
+            })
+            .collect();
+        (scope_player_number, receivers)
+    }
+
+    // Address of an active player.
+    pub fn get_addr(&self, player_number: u8) -> Option<SocketAddr> {
+        self.connections
+            .iter()
+            .find(|(_addr, connection)| connection.player_number == player_number)
+            .map(|(addr, _connection)| *addr)
+    }
+
     pub fn is_ip_in_connections(&self, ip: SocketAddr) -> bool {
         if self.connections.get(&ip).is_some() {
             return true;
@@ -44,14 +556,15 @@ impl Connections {
     pub fn create_new_connection(&mut self, ip: SocketAddr, player_name: String) -> Option<u8> {
         for index in 1..=255 {
             let mut is_index_found = false;
-            for (_ip, connection) in &self.connections {
+            for connection in self.get_players() {
                 if connection.player_number == index {
                     is_index_found = true;
                 }
             }
             if !is_index_found {
+                let session_token = self.challenges.create_session_token(ip, index);
                 self.connections
-                    .insert(ip, Connection::new(index, player_name));
+                    .insert(ip, Connection::new(index, player_name, session_token));
                 return Some(index);
             }
         }
@@ -59,7 +572,7 @@ impl Connections {
     }
 
     pub fn is_name_taken(&self, name: String) -> bool {
-        for (_ip, conn) in &self.connections {
+        for conn in self.get_players() {
             if conn.player_name == name {
                 return true;
             }
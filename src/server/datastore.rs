@@ -0,0 +1,189 @@
+use crate::requests::data::{DataOperation, DataResponse, DataStatus, MAX_KEY_LENGTH};
+use std::collections::HashMap;
+
+const DEFAULT_MAX_VALUE_LENGTH: usize = 1024;
+const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+// What players may do with keys under a prefix. Server code can always read and write.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataAccess {
+    // Every player reads and writes.
+    ReadWrite,
+    // Every player reads, only the player who created the key writes it.
+    // Key is released, when its owner leaves.
+    OwnerWrite,
+    // Players read, only server writes.
+    ReadOnly,
+    // Players can not see the keys at all.
+    Hidden,
+}
+
+struct Entry {
+    value: Vec<u8>,
+    // Player who created the entry, None if server created it.
+    owner: Option<u8>,
+}
+
+/*
+ * Data store
+ *
+ * Key-value store shared by the players of the server. Players fetch and change
+ * values with data requests, server code uses the store directly.
+ *
+ * Access is decided by the rule with the longest matching key prefix, keys
+ * without rule are readable and writable by every player.
+ */
+pub struct DataStore {
+    entries: HashMap<String, Entry>,
+    rules: Vec<(String, DataAccess)>,
+    pub max_value_length: usize,
+    pub max_entries: usize,
+}
+
+impl DataStore {
+    pub fn new() -> DataStore {
+        DataStore {
+            entries: HashMap::new(),
+            rules: vec![],
+            max_value_length: DEFAULT_MAX_VALUE_LENGTH,
+            max_entries: DEFAULT_MAX_ENTRIES,
+        }
+    }
+
+    // Rule for the same prefix is replaced.
+    pub fn set_access(&mut self, prefix: String, access: DataAccess) {
+        self.rules
+            .retain(|(rule_prefix, _access)| *rule_prefix != prefix);
+        self.rules.push((prefix, access));
+    }
+
+    pub fn get_access(&self, key: &str) -> DataAccess {
+        self.rules
+            .iter()
+            .filter(|(prefix, _access)| key.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _access)| prefix.len())
+            .map(|(_prefix, access)| *access)
+            .unwrap_or(DataAccess::ReadWrite)
+    }
+
+    // Server side access, rules do not apply.
+    pub fn get(&self, key: &str) -> Option<Vec<u8>> {
+        self.entries.get(key).map(|entry| entry.value.clone())
+    }
+
+    // Server side access, rules do not apply. Limits do.
+    pub fn set(&mut self, key: String, value: Vec<u8>) -> Result<(), DataStatus> {
+        self.check_limits(&key, &value)?;
+        let owner = self.entries.get(&key).and_then(|entry| entry.owner);
+        self.entries.insert(key, Entry { value, owner });
+        Ok(())
+    }
+
+    // Server side access, returns false if key does not exist.
+    pub fn delete(&mut self, key: &str) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    // Entries of the player are not removed, but anybody with write access can
+    // take them over.
+    pub fn release_owner(&mut self, player_number: u8) {
+        for entry in self.entries.values_mut() {
+            if entry.owner == Some(player_number) {
+                entry.owner = None;
+            }
+        }
+    }
+
+    // Operation requested by the player.
+    pub fn handle(&mut self, player_number: u8, operation: DataOperation) -> DataResponse {
+        let key = operation.get_key();
+        if key.is_empty() || key.len() > MAX_KEY_LENGTH {
+            return DataResponse::from_status(DataStatus::InvalidRequest);
+        }
+        let access = self.get_access(key);
+        if access == DataAccess::Hidden {
+            return DataResponse::from_status(DataStatus::AccessDenied);
+        }
+
+        match operation {
+            DataOperation::Get { key } => match self.get(&key) {
+                None => DataResponse::from_status(DataStatus::NotFound),
+                Some(value) => DataResponse::new(DataStatus::Ok, Some(value)),
+            },
+            DataOperation::Set { key, value } => {
+                self.write(player_number, access, key, value, None)
+            }
+            DataOperation::CompareAndSet {
+                key,
+                expected,
+                value,
+            } => self.write(player_number, access, key, value, Some(expected)),
+            DataOperation::Delete { key } => {
+                if let Err(status) = self.check_write(player_number, access, &key) {
+                    return DataResponse::from_status(status);
+                }
+                match self.delete(&key) {
+                    false => DataResponse::from_status(DataStatus::NotFound),
+                    true => DataResponse::from_status(DataStatus::Ok),
+                }
+            }
+        }
+    }
+
+    // Compare-and-set has expected value, None inside means that key must not exist.
+    fn write(
+        &mut self,
+        player_number: u8,
+        access: DataAccess,
+        key: String,
+        value: Vec<u8>,
+        expected: Option<Option<Vec<u8>>>,
+    ) -> DataResponse {
+        if let Err(status) = self.check_write(player_number, access, &key) {
+            return DataResponse::from_status(status);
+        }
+        if let Some(expected) = expected {
+            let current = self.get(&key);
+            if current != expected {
+                return DataResponse::new(DataStatus::CompareFailed, current);
+            }
+        }
+        if let Err(status) = self.check_limits(&key, &value) {
+            return DataResponse::from_status(status);
+        }
+
+        let owner = match self.entries.get(&key) {
+            Some(entry) if entry.owner.is_some() => entry.owner,
+            _ if access == DataAccess::OwnerWrite => Some(player_number),
+            _ => None,
+        };
+        self.entries.insert(key, Entry { value, owner });
+        DataResponse::from_status(DataStatus::Ok)
+    }
+
+    fn check_write(
+        &self,
+        player_number: u8,
+        access: DataAccess,
+        key: &str,
+    ) -> Result<(), DataStatus> {
+        match access {
+            DataAccess::ReadWrite => Ok(()),
+            DataAccess::OwnerWrite => match self.entries.get(key).and_then(|entry| entry.owner) {
+                Some(owner) if owner != player_number => Err(DataStatus::AccessDenied),
+                _ => Ok(()),
+            },
+            DataAccess::ReadOnly | DataAccess::Hidden => Err(DataStatus::AccessDenied),
+        }
+    }
+
+    fn check_limits(&self, key: &str, value: &[u8]) -> Result<(), DataStatus> {
+        if value.len() > self.max_value_length {
+            return Err(DataStatus::TooLarge);
+        }
+        if !self.entries.contains_key(key) && self.entries.len() >= self.max_entries {
+            return Err(DataStatus::StoreFull);
+        }
+        Ok(())
+    }
+}
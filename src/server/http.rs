@@ -0,0 +1,371 @@
+use crate::server::connection::Connections;
+use crate::server::stats::{ConnectionStats, ServerCounters};
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// Closing is checked this often, while no request is waited.
+const ACCEPT_PAUSE: Duration = Duration::from_millis(50);
+// Slow or silent client can not hold the status thread longer than this.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(1);
+// Only the request line is needed, rest of the request is not read.
+const MAX_REQUEST_LENGTH: usize = 4096;
+
+/*
+ * Status HTTP listener
+ *
+ * Tiny HTTP/1.1 listener for containers and dashboards, one request per connection.
+ *
+ * /healthz  200 "ok" while listen sockets work, 503 if server is in error state.
+ * /status   JSON with uptime, player count and network stats of every connection.
+ * /metrics  Prometheus text format: packet counts, failed packets, round trip times,
+ *           loss and resend counts.
+ */
+pub struct StatusHttpListener {
+    listener: TcpListener,
+    connections: Arc<Mutex<Connections>>,
+    error_state_current: Arc<AtomicBool>,
+    time_to_die: Arc<AtomicBool>,
+    start_instant: Instant,
+}
+
+impl StatusHttpListener {
+    pub fn new(
+        listener: TcpListener,
+        connections: Arc<Mutex<Connections>>,
+        error_state_current: Arc<AtomicBool>,
+        time_to_die: Arc<AtomicBool>,
+        start_instant: Instant,
+    ) -> StatusHttpListener {
+        StatusHttpListener {
+            listener,
+            connections,
+            error_state_current,
+            time_to_die,
+            start_instant,
+        }
+    }
+
+    // Listener is non-blocking, so that thread notices closing.
+    pub fn run(&self) {
+        loop {
+            if self.time_to_die.load(Ordering::SeqCst) {
+                break;
+            }
+            match self.listener.accept() {
+                Ok((stream, _addr)) => {
+                    if let Err(e) = self.handle_stream(stream) {
+                        println!("Status request failed: {}", e);
+                    }
+                }
+                Err(e) if e.kind() == ErrorKind::WouldBlock => thread::sleep(ACCEPT_PAUSE),
+                Err(e) => {
+                    println!("Status listener failed: {}", e);
+                    thread::sleep(ACCEPT_PAUSE);
+                }
+            }
+        }
+    }
+
+    fn handle_stream(&self, mut stream: TcpStream) -> Result<(), std::io::Error> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+
+        let request_line = read_request_line(&mut stream)?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or("");
+        let path = parts.next().unwrap_or("");
+        // Query string does not change the answer.
+        let path = path.split('?').next().unwrap_or("");
+
+        let (status, content_type, body) = match (method, path) {
+            ("GET", "/healthz") => self.get_health(),
+            ("GET", "/status") => ("200 OK", "application/json", self.get_status()),
+            ("GET", "/metrics") => ("200 OK", "text/plain; version=0.0.4", self.get_metrics()),
+            ("GET", _) => ("404 Not Found", "text/plain", "not found\n".to_string()),
+            _ => (
+                "405 Method Not Allowed",
+                "text/plain",
+                "method not allowed\n".to_string(),
+            ),
+        };
+        let response = format!(
+            "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            content_type,
+            body.len(),
+            body
+        );
+        stream.write_all(response.as_bytes())?;
+        stream.flush()
+    }
+
+    fn get_health(&self) -> (&'static str, &'static str, String) {
+        if self.error_state_current.load(Ordering::SeqCst) {
+            return (
+                "503 Service Unavailable",
+                "text/plain",
+                "socket error\n".to_string(),
+            );
+        }
+        ("200 OK", "text/plain", "ok\n".to_string())
+    }
+
+    fn get_snapshot(&self) -> (ServerCounters, Vec<ConnectionStats>) {
+        let connections_changer = self.connections.lock().unwrap();
+        (
+            connections_changer.counters,
+            connections_changer.get_stats(Instant::now()),
+        )
+    }
+
+    fn get_status(&self) -> String {
+        let (_counters, all_stats) = self.get_snapshot();
+        create_status_json(self.start_instant.elapsed(), &all_stats)
+    }
+
+    fn get_metrics(&self) -> String {
+        let (counters, all_stats) = self.get_snapshot();
+        create_metrics(self.start_instant.elapsed(), &counters, &all_stats)
+    }
+}
+
+fn read_request_line(stream: &mut TcpStream) -> Result<String, std::io::Error> {
+    let mut request = vec![];
+    let mut buf = [0; 512];
+    while !request.windows(2).any(|window| window == b"\r\n") {
+        let number_of_bytes = stream.read(&mut buf)?;
+        if number_of_bytes == 0 {
+            break;
+        }
+        request.extend(&buf[..number_of_bytes]);
+        if request.len() > MAX_REQUEST_LENGTH {
+            break;
+        }
+    }
+    let request = String::from_utf8_lossy(&request);
+    Ok(request.lines().next().unwrap_or("").to_string())
+}
+
+// Player names are padded with zeros in protocol data.
+fn get_player_name(stats: &ConnectionStats) -> &str {
+    stats.player_name.trim_end_matches('\0')
+}
+
+fn escape_json(text: &str) -> String {
+    let mut escaped = String::new();
+    for character in text.chars() {
+        match character {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            character if (character as u32) < 0x20 => {
+                escaped.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => escaped.push(character),
+        }
+    }
+    escaped
+}
+
+// Label values are quoted, backslash, quote and newline are escaped.
+fn escape_label(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn create_status_json(uptime: Duration, all_stats: &[ConnectionStats]) -> String {
+    let connections: Vec<String> = all_stats
+        .iter()
+        .map(|stats| {
+            let rtt_ms = match stats.rtt {
+                None => "null".to_string(),
+                Some(rtt) => format!("{:.3}", rtt.as_secs_f64() * 1000.0),
+            };
+            format!(
+                concat!(
+                    "{{\"player_number\":{},\"player_name\":\"{}\",\"addr\":\"{}\",",
+                    "\"rtt_ms\":{},\"jitter_ms\":{:.3},",
+                    "\"loss_from_client\":{:.4},\"loss_to_client\":{:.4},",
+                    "\"packets_received_per_second\":{:.2},\"packets_sent_per_second\":{:.2},",
+                    "\"bytes_received_per_second\":{:.2},\"bytes_sent_per_second\":{:.2},",
+                    "\"since_last_packet_ms\":{},",
+                    "\"packets_received\":{},\"packets_sent\":{},",
+                    "\"bytes_received\":{},\"bytes_sent\":{}}}"
+                ),
+                stats.player_number,
+                escape_json(get_player_name(stats)),
+                stats.addr,
+                rtt_ms,
+                stats.jitter.as_secs_f64() * 1000.0,
+                stats.loss_from_client,
+                stats.loss_to_client,
+                stats.packets_received_per_second,
+                stats.packets_sent_per_second,
+                stats.bytes_received_per_second,
+                stats.bytes_sent_per_second,
+                stats.since_last_packet.as_millis(),
+                stats.packets_received,
+                stats.packets_sent,
+                stats.bytes_received,
+                stats.bytes_sent
+            )
+        })
+        .collect();
+    format!(
+        "{{\"uptime_seconds\":{},\"players\":{},\"connections\":[{}]}}\n",
+        uptime.as_secs(),
+        all_stats.len(),
+        connections.join(",")
+    )
+}
+
+fn create_metrics(
+    uptime: Duration,
+    counters: &ServerCounters,
+    all_stats: &[ConnectionStats],
+) -> String {
+    let mut metrics = String::new();
+    let mut add_metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        metrics.push_str(&format!(
+            "# HELP {} {}\n# TYPE {} {}\n",
+            name, help, name, kind
+        ));
+        for (labels, value) in samples {
+            metrics.push_str(&format!("{}{} {}\n", name, labels, value));
+        }
+    };
+    let total = |value: u64| vec![(String::new(), value.to_string())];
+    let per_player = |value: &dyn Fn(&ConnectionStats) -> String| {
+        all_stats
+            .iter()
+            .map(|stats| {
+                (
+                    format!(
+                        "{{player=\"{}\",name=\"{}\"}}",
+                        stats.player_number,
+                        escape_label(get_player_name(stats))
+                    ),
+                    value(stats),
+                )
+            })
+            .collect::<Vec<(String, String)>>()
+    };
+
+    add_metric(
+        "gum_uptime_seconds",
+        "gauge",
+        "Seconds since server start.",
+        total(uptime.as_secs()),
+    );
+    add_metric(
+        "gum_players",
+        "gauge",
+        "Connected players.",
+        total(all_stats.len() as u64),
+    );
+    add_metric(
+        "gum_packets_received_total",
+        "counter",
+        "Datagrams received.",
+        total(counters.packets_received),
+    );
+    add_metric(
+        "gum_packets_sent_total",
+        "counter",
+        "Datagrams sent.",
+        total(counters.packets_sent),
+    );
+    add_metric(
+        "gum_bytes_received_total",
+        "counter",
+        "Bytes received.",
+        total(counters.bytes_received),
+    );
+    add_metric(
+        "gum_bytes_sent_total",
+        "counter",
+        "Bytes sent.",
+        total(counters.bytes_sent),
+    );
+    add_metric(
+        "gum_packets_failed_total",
+        "counter",
+        "Received datagrams without a valid header.",
+        total(counters.packets_failed),
+    );
+    add_metric(
+        "gum_send_errors_total",
+        "counter",
+        "Datagrams, which could not be sent.",
+        total(counters.send_errors),
+    );
+    add_metric(
+        "gum_requests_resent_total",
+        "counter",
+        "Reliable requests resent by clients.",
+        total(counters.requests_resent),
+    );
+    add_metric(
+        "gum_pushes_resent_total",
+        "counter",
+        "Reliable pushes resent to clients.",
+        total(counters.pushes_resent),
+    );
+    add_metric(
+        "gum_pushes_abandoned_total",
+        "counter",
+        "Reliable pushes given up after too many resends.",
+        total(counters.pushes_abandoned),
+    );
+    add_metric(
+        "gum_connection_rtt_seconds",
+        "gauge",
+        "Smoothed round trip time of the connection.",
+        per_player(&|stats| match stats.rtt {
+            None => "NaN".to_string(),
+            Some(rtt) => rtt.as_secs_f64().to_string(),
+        }),
+    );
+    add_metric(
+        "gum_connection_jitter_seconds",
+        "gauge",
+        "Mean deviation of round trip time of the connection.",
+        per_player(&|stats| stats.jitter.as_secs_f64().to_string()),
+    );
+    add_metric(
+        "gum_connection_loss_from_client_ratio",
+        "gauge",
+        "Share of messages from client, which did not arrive.",
+        per_player(&|stats| stats.loss_from_client.to_string()),
+    );
+    add_metric(
+        "gum_connection_loss_to_client_ratio",
+        "gauge",
+        "Share of reliable pushes to client, which had to be resent.",
+        per_player(&|stats| stats.loss_to_client.to_string()),
+    );
+    add_metric(
+        "gum_connection_packets_received_total",
+        "counter",
+        "Datagrams received from the connection.",
+        per_player(&|stats| stats.packets_received.to_string()),
+    );
+    add_metric(
+        "gum_connection_packets_sent_total",
+        "counter",
+        "Datagrams sent to the connection.",
+        per_player(&|stats| stats.packets_sent.to_string()),
+    );
+    add_metric(
+        "gum_connection_since_last_packet_seconds",
+        "gauge",
+        "Seconds since anything was received from the connection.",
+        per_player(&|stats| stats.since_last_packet.as_secs_f64().to_string()),
+    );
+    metrics
+}
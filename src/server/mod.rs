@@ -1,15 +1,31 @@
+pub mod challenge;
 pub mod connection;
+pub mod datastore;
+pub mod http;
+pub mod rooms;
 pub mod server;
 pub mod socketlistener;
+pub mod stats;
 
 use crate::server::server::Server;
+use crate::socket::create_addr_string;
+use std::env;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::{thread, time};
 
 pub fn run(ip: String, port: String, thread_count: u8) -> Result<Server, std::io::Error> {
+    run_on_addrs(vec![create_addr_string(&ip, &port)], thread_count)
+}
+
+// Server listens every given address, e.g. "127.0.0.1:11111" and "[::1]:11111".
+// "[::]:11111" alone listens IPv6 and, on dual-stack systems, IPv4 too. Binding
+// "0.0.0.0:11111" beside it fails there, e.g. on Linux, as the port is taken.
+pub fn run_on_addrs(local_addrs: Vec<String>, thread_count: u8) -> Result<Server, std::io::Error> {
     let mut server = Server::new(thread_count);
-    server.connect(ip + ":" + &port)?;
+    for local_addr in local_addrs {
+        server.connect(local_addr)?;
+    }
     server.run();
     Ok(server)
 }
@@ -21,6 +37,13 @@ pub fn test_server() {
             println!("Server error: {}", e);
         }
         Ok(mut server) => {
+            // Status endpoint is served, if address is given, e.g. GUM_STATUS_ADDR=0.0.0.0:8080.
+            if let Ok(status_addr) = env::var("GUM_STATUS_ADDR") {
+                match server.run_status_http(status_addr) {
+                    Ok(addr) => println!("Status endpoint at http://{}/status", addr),
+                    Err(e) => println!("Status endpoint error: {}", e),
+                }
+            }
             // Test server will close with Ctrl-c. Let's create a loop.
             let running = Arc::new(AtomicBool::new(true));
             let r = running.clone();
@@ -0,0 +1,202 @@
+use crate::requests::room::{RoomEvent, RoomInfo, RoomPlayer, RoomStatus, MAX_ROOM_NAME_LENGTH};
+use std::collections::BTreeMap;
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+// Player in a room, with player numbers in the room and in the server.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RoomMember {
+    pub player_number: u8,
+    pub server_player_number: u8,
+    pub player_name: String,
+}
+
+struct Room {
+    name: String,
+    capacity: u8,
+    // Rooms created by server stay open when empty, rooms created by players
+    // close with their last player.
+    is_persistent: bool,
+    // Members by player number in the room.
+    members: BTreeMap<u8, RoomMember>,
+}
+
+impl Room {
+    fn get_info(&self, room_id: u8) -> RoomInfo {
+        RoomInfo {
+            room_id,
+            name: self.name.clone(),
+            capacity: self.capacity,
+            player_count: self.members.len() as u8,
+        }
+    }
+}
+
+/*
+ * Rooms
+ *
+ * Separate game sessions on one server. Player is in the lobby or in one room.
+ * Room numbers its players from 1 to capacity, and data pushes of a room member
+ * reach only the other members, with player numbers of the room.
+ *
+ * Every room event is sent to subscribers, so that server code can follow rooms.
+ */
+pub struct Rooms {
+    rooms: BTreeMap<u8, Room>,
+    // Rooms, which server has closed, members are told by listener threads.
+    closed_rooms: Vec<u8>,
+    event_senders: Vec<Sender<RoomEvent>>,
+}
+
+impl Rooms {
+    pub fn new() -> Rooms {
+        Rooms {
+            rooms: BTreeMap::new(),
+            closed_rooms: vec![],
+            event_senders: vec![],
+        }
+    }
+
+    pub fn subscribe(&mut self) -> Receiver<RoomEvent> {
+        let (event_tx, event_rx) = channel();
+        self.event_senders.push(event_tx);
+        event_rx
+    }
+
+    // Subscribers, which have dropped their receiver, are forgotten.
+    fn send_event(&mut self, event: RoomEvent) {
+        self.event_senders
+            .retain(|event_tx| event_tx.send(event.clone()).is_ok());
+    }
+
+    // Room gets the smallest free room id.
+    pub fn create(
+        &mut self,
+        name: String,
+        capacity: u8,
+        is_persistent: bool,
+    ) -> Result<RoomInfo, RoomStatus> {
+        if name.is_empty() || name.len() > MAX_ROOM_NAME_LENGTH || capacity == 0 {
+            return Err(RoomStatus::InvalidRequest);
+        }
+        let room_id = (1..=u8::MAX)
+            .find(|room_id| !self.rooms.contains_key(room_id))
+            .ok_or(RoomStatus::NoFreeRoom)?;
+        let room = Room {
+            name,
+            capacity,
+            is_persistent,
+            members: BTreeMap::new(),
+        };
+        let room_info = room.get_info(room_id);
+        self.rooms.insert(room_id, room);
+        self.send_event(RoomEvent::Created(room_info.clone()));
+        Ok(room_info)
+    }
+
+    // Returns player number of the player in the room.
+    pub fn join(
+        &mut self,
+        room_id: u8,
+        server_player_number: u8,
+        player_name: String,
+    ) -> Result<u8, RoomStatus> {
+        if self.get_room_of(server_player_number).is_some() {
+            return Err(RoomStatus::AlreadyInRoom);
+        }
+        let room = self.rooms.get_mut(&room_id).ok_or(RoomStatus::NotFound)?;
+        let player_number = (1..=room.capacity)
+            .find(|player_number| !room.members.contains_key(player_number))
+            .ok_or(RoomStatus::Full)?;
+        room.members.insert(
+            player_number,
+            RoomMember {
+                player_number,
+                server_player_number,
+                player_name: player_name.clone(),
+            },
+        );
+        self.send_event(RoomEvent::PlayerJoined {
+            room_id,
+            player: RoomPlayer {
+                player_number,
+                player_name,
+            },
+        });
+        Ok(player_number)
+    }
+
+    // Returns room id and player number in the room, None if player was in the lobby.
+    // Room created by a player closes, when its last player leaves.
+    pub fn leave(&mut self, server_player_number: u8) -> Option<(u8, u8)> {
+        let (room_id, player_number) = self.get_room_of(server_player_number)?;
+        let room = self.rooms.get_mut(&room_id)?;
+        room.members.remove(&player_number);
+        let is_closing = room.members.is_empty() && !room.is_persistent;
+        self.send_event(RoomEvent::PlayerLeft {
+            room_id,
+            player_number,
+        });
+        if is_closing {
+            self.rooms.remove(&room_id);
+            self.send_event(RoomEvent::Closed { room_id });
+        }
+        Some((room_id, player_number))
+    }
+
+    // Returns false, if room is not found.
+    pub fn close_room(&mut self, room_id: u8) -> bool {
+        if !self.rooms.contains_key(&room_id) {
+            return false;
+        }
+        if !self.closed_rooms.contains(&room_id) {
+            self.closed_rooms.push(room_id);
+        }
+        true
+    }
+
+    // Removes rooms closed by server, returns them with their members, who are
+    // back in the lobby.
+    pub fn take_closed_rooms(&mut self) -> Vec<(u8, Vec<RoomMember>)> {
+        let mut closed_rooms = vec![];
+        for room_id in std::mem::take(&mut self.closed_rooms) {
+            if let Some(room) = self.rooms.remove(&room_id) {
+                self.send_event(RoomEvent::Closed { room_id });
+                closed_rooms.push((room_id, room.members.into_values().collect()));
+            }
+        }
+        closed_rooms
+    }
+
+    pub fn get_room(&self, room_id: u8) -> Option<RoomInfo> {
+        self.rooms.get(&room_id).map(|room| room.get_info(room_id))
+    }
+
+    pub fn get_rooms(&self) -> Vec<RoomInfo> {
+        self.rooms
+            .iter()
+            .map(|(room_id, room)| room.get_info(*room_id))
+            .collect()
+    }
+
+    // Members ordered by player number in the room.
+    pub fn get_members(&self, room_id: u8) -> Option<Vec<RoomMember>> {
+        self.rooms
+            .get(&room_id)
+            .map(|room| room.members.values().cloned().collect())
+    }
+
+    // Returns room id and player number in the room.
+    pub fn get_room_of(&self, server_player_number: u8) -> Option<(u8, u8)> {
+        self.rooms.iter().find_map(|(room_id, room)| {
+            room.members
+                .values()
+                .find(|member| member.server_player_number == server_player_number)
+                .map(|member| (*room_id, member.player_number))
+        })
+    }
+
+    // Player is in the lobby, when it is not in any room.
+    pub fn is_in_lobby(&self, server_player_number: u8) -> bool {
+        self.get_room_of(server_player_number).is_none()
+    }
+}
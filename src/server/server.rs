@@ -1,23 +1,41 @@
-use crate::helpers::threadkiller::thread_killer;
 use crate::protocol::Protocol;
+use crate::requests::data::DataStatus;
+use crate::requests::delivery::{DeliveryMode, DeliveryModes};
+use crate::requests::fragmentation::Reassembler;
 use crate::requests::jobs::Jobs;
+use crate::requests::room::{RoomEvent, RoomInfo, RoomStatus};
+use crate::requests::ServerJob;
 use crate::server::connection::Connections;
+use crate::server::datastore::DataAccess;
+use crate::server::http::StatusHttpListener;
+use crate::server::rooms::RoomMember;
 use crate::server::socketlistener::ServerSocketListener;
-use crate::socket::SocketCombatible;
+use crate::server::stats::ConnectionStats;
+use crate::socket::{select_socket, SocketCombatible, Transport};
 use std::io::Error;
 use std::io::ErrorKind;
 use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::net::TcpListener;
 use std::net::UdpSocket;
 use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::thread::JoinHandle;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{thread, time};
 
+// Listener threads wake up this often, even if nothing is received, to check
+// timed out connections.
+const LISTENER_READ_TIMEOUT: Duration = Duration::from_millis(100);
+
 pub struct Server {
     jobs: Arc<Mutex<Jobs>>,
     protocols: Arc<Protocol>,
+    delivery_modes: Arc<Mutex<DeliveryModes>>,
+    // Fragments are collected here, since any listener thread may receive them.
+    reassembler: Arc<Mutex<Reassembler<SocketAddr>>>,
     time_to_die: Arc<AtomicBool>,
     is_running: Arc<AtomicBool>,
     ip: Option<IpAddr>,
@@ -25,8 +43,11 @@ pub struct Server {
     error_state_previous: Arc<AtomicBool>,
     error_state_current: Arc<AtomicBool>,
     error_state_start_time: Option<Instant>,
+    // Uptime of status endpoint is counted from here.
+    start_instant: Instant,
     thread_handles: Option<Vec<JoinHandle<()>>>,
-    socket: Option<Arc<UdpSocket>>,
+    // Server listens every socket, bound with connect.
+    sockets: Vec<Arc<dyn Transport>>,
     threads_count: u8,
     connections: Arc<Mutex<Connections>>,
     //handle_data_cb: Arc<Mutex<fn(job_type: JobType, raw_data: &mut [u8])>>,
@@ -44,9 +65,6 @@ impl SocketCombatible for Server {
     fn is_running(&self) -> bool {
         return self.is_running.load(Ordering::SeqCst);
     }
-    fn get_is_running_atomic(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.is_running)
-    }
 }
 
 impl Server {
@@ -57,6 +75,8 @@ impl Server {
         Server {
             jobs: Arc::new(Mutex::new(Jobs::new())),
             protocols: Arc::new(Protocol::new()),
+            delivery_modes: Arc::new(Mutex::new(DeliveryModes::new())),
+            reassembler: Arc::new(Mutex::new(Reassembler::new())),
             is_running: Arc::new(AtomicBool::new(false)),
             ip: None,
             port: None,
@@ -64,8 +84,9 @@ impl Server {
             error_state_previous: Arc::new(AtomicBool::new(false)),
             error_state_current: Arc::new(AtomicBool::new(false)),
             error_state_start_time: None,
+            start_instant: Instant::now(),
             thread_handles: None,
-            socket: None,
+            sockets: vec![],
             threads_count: threads_count,
             //handle_data_cb: Arc::new(Mutex::new(handle_data_cb)),
             connections: Arc::new(Mutex::new(Connections::new())),
@@ -79,42 +100,225 @@ impl Server {
             return;
         }
         self.init_status();
+        self.start_instant = Instant::now();
         self.is_running.store(true, Ordering::SeqCst);
     }
+
+    // Serves /healthz, /status and /metrics over HTTP on given address, e.g.
+    // "0.0.0.0:8080". Returns the bound address, listener closes with the server.
+    pub fn run_status_http(&mut self, local_addr: String) -> Result<SocketAddr, std::io::Error> {
+        let listener = TcpListener::bind(local_addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let status_http_listener = StatusHttpListener::new(
+            listener,
+            Arc::clone(&self.connections),
+            Arc::clone(&self.error_state_current),
+            Arc::clone(&self.time_to_die),
+            self.start_instant,
+        );
+
+        let status_http_thread = thread::Builder::new().name(format!("Status HTTP thread"));
+        let handle = status_http_thread.spawn(move || status_http_listener.run())?;
+        self.push_handles(vec![handle]);
+        Ok(addr)
+    }
     // Takes ownership of thread handles and joins threads.
-    // UDP listenings and job handling ends.
+    // UDP listenings and job handling ends. Listener threads notice closing
+    // within socket read timeout.
     pub fn die(&mut self) -> bool {
         self.time_to_die.store(true, Ordering::SeqCst);
 
-        match thread_killer(self) {
-            None => return false,
-            Some(thread_killer_handle) => {
-                let handles = self.thread_handles.take();
-                if handles.is_none() {
-                    return true;
-                }
-                handles.unwrap().into_iter().for_each(|handle| {
-                    print!("Closing '{}' ...", handle.thread().name().unwrap());
-                    handle.join().unwrap();
-                    println!("success.")
-                });
-                self.is_running.store(false, Ordering::SeqCst);
-                thread_killer_handle.join().unwrap();
-
-                return true;
+        let handles = self.thread_handles.take();
+        if handles.is_none() {
+            return true;
+        }
+        handles.unwrap().into_iter().for_each(|handle| {
+            print!("Closing '{}' ...", handle.thread().name().unwrap());
+            handle.join().unwrap();
+            println!("success.")
+        });
+        self.sockets.iter().for_each(|socket| socket.shutdown());
+        self.is_running.store(false, Ordering::SeqCst);
+        true
+    }
+
+    // Sets the delivery mode of server originated pushes. Responses use the mode
+    // of the request and forwarded data pushes the mode of the push request.
+    pub fn set_delivery_mode(&mut self, server_job_type: ServerJob, mode: DeliveryMode) {
+        let mut delivery_modes_changer = self.delivery_modes.lock().unwrap();
+        (*delivery_modes_changer).set_server_job_mode(server_job_type, mode);
+    }
+
+    // Players, which have not sent anything (heartbeats included) within timeout,
+    // are removed and other players are informed with player leave push.
+    pub fn set_connection_timeout(&mut self, timeout: Duration) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        (*connections_changer).timeout = timeout;
+    }
+
+    // Slot of a timed out player is held for grace period, so that player can resume
+    // the session from a new address. Player leave push is sent after grace period.
+    pub fn set_session_grace_period(&mut self, grace_period: Duration) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        (*connections_changer).session_grace_period = grace_period;
+    }
+
+    // Datagrams to the same player are batched and sent once per flush interval.
+    // Listener threads wake up at least once per flush interval.
+    pub fn set_flush_interval(&mut self, flush_interval: Duration) -> Result<(), std::io::Error> {
+        if flush_interval > Duration::from_millis(0) {
+            for socket in &self.sockets {
+                socket.set_read_timeout(Some(flush_interval.min(LISTENER_READ_TIMEOUT)))?;
             }
         }
+        let mut connections_changer = self.connections.lock().unwrap();
+        (*connections_changer).flush_interval = flush_interval;
+        Ok(())
+    }
+
+    // Removes the player, every player including the kicked one gets player leave
+    // push. Returns false, if player is not found.
+    pub fn kick(&self, player_number: u8) -> bool {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.kick_player(player_number)
+    }
+
+    // Access of players to data store keys starting with prefix. Longest matching
+    // prefix decides, keys without rule are readable and writable by every player.
+    pub fn set_data_access(&mut self, prefix: String, access: DataAccess) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        (*connections_changer).data_store.set_access(prefix, access);
+    }
+
+    // Larger values are refused with TooLarge, new keys over max entries with StoreFull.
+    pub fn set_data_limits(&mut self, max_value_length: usize, max_entries: usize) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        (*connections_changer).data_store.max_value_length = max_value_length;
+        (*connections_changer).data_store.max_entries = max_entries;
     }
 
+    // Server code reads and writes data store regardless of access rules.
+    pub fn get_data(&self, key: &str) -> Option<Vec<u8>> {
+        let connections_changer = self.connections.lock().unwrap();
+        connections_changer.data_store.get(key)
+    }
+
+    pub fn set_data(&self, key: String, value: Vec<u8>) -> Result<(), DataStatus> {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.data_store.set(key, value)
+    }
+
+    // Returns false, if key does not exist.
+    pub fn delete_data(&self, key: &str) -> bool {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.data_store.delete(key)
+    }
+
+    // Room created by server stays open when empty, until server closes it.
+    pub fn create_room(&self, name: String, capacity: u8) -> Result<RoomInfo, RoomStatus> {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.rooms.create(name, capacity, true)
+    }
+
+    // Players in the room are moved back to the lobby, and they get room closed push.
+    // Returns false, if room is not found.
+    pub fn close_room(&self, room_id: u8) -> bool {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.rooms.close_room(room_id)
+    }
+
+    pub fn rooms(&self) -> Vec<RoomInfo> {
+        let connections_changer = self.connections.lock().unwrap();
+        connections_changer.rooms.get_rooms()
+    }
+
+    // Players in the room with their server player numbers, None if room is not found.
+    pub fn room_members(&self, room_id: u8) -> Option<Vec<RoomMember>> {
+        let connections_changer = self.connections.lock().unwrap();
+        connections_changer.rooms.get_members(room_id)
+    }
+
+    // Every room event from now on, created and closed rooms included.
+    pub fn room_events(&self) -> Receiver<RoomEvent> {
+        let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.rooms.subscribe()
+    }
+
+    // Network stats of the player, None if player is not connected.
+    pub fn connection_stats(&self, player_number: u8) -> Option<ConnectionStats> {
+        let connections_changer = self.connections.lock().unwrap();
+        connections_changer
+            .connections
+            .iter()
+            .find(|(_addr, connection)| connection.player_number == player_number)
+            .map(|(addr, connection)| connection.get_stats(*addr, Instant::now()))
+    }
+
+    // Network stats of every connected player, ordered by player number.
+    pub fn all_stats(&self) -> Vec<ConnectionStats> {
+        let connections_changer = self.connections.lock().unwrap();
+        connections_changer.get_stats(Instant::now())
+    }
+
+    // Sends batched datagrams at once, without waiting for flush interval.
+    pub fn flush(&self) -> Result<(), std::io::Error> {
+        if self.sockets.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                "Cannot flush without activated socket.",
+            ));
+        }
+
+        let mut connections_changer = self.connections.lock().unwrap();
+        for (addr, datagram) in (*connections_changer).take_batches(Instant::now(), true) {
+            match connections_changer
+                .connections
+                .get(&addr)
+                .and_then(|connection| connection.socket.as_ref())
+            {
+                Some(socket) => socket.send_to(&datagram, addr)?,
+                None => select_socket(&self.sockets, addr).send_to(&datagram, addr)?,
+            };
+            connections_changer.counters.packets_sent += 1;
+            connections_changer.counters.bytes_sent += datagram.len() as u64;
+            if let Some(connection) = (*connections_changer).connections.get_mut(&addr) {
+                connection.stats.add_sent(datagram.len(), Instant::now());
+            }
+        }
+        Ok(())
+    }
+
+    // Binds a listen socket, may be called for several addresses, e.g. "127.0.0.1:11111"
+    // and "[::1]:11111". "[::]:11111" is dual-stack on most systems, and serves IPv4
+    // without "0.0.0.0:11111". Ip and port of the server are the ones of the first socket.
     pub fn connect(&mut self, local_ip: String) -> Result<(), std::io::Error> {
         let socket = UdpSocket::bind(local_ip)?;
-        let socket_addr = socket.local_addr()?;
-        self.ip = Some(socket_addr.ip());
-        self.port = Some(socket_addr.port());
-        self.socket = Some(Arc::new(socket));
+        self.connect_with_transport(Arc::new(socket))
+    }
+
+    // Listens given transport, e.g. memory transport, instead of a bound socket.
+    pub fn connect_with_transport(
+        &mut self,
+        transport: Arc<dyn Transport>,
+    ) -> Result<(), std::io::Error> {
+        transport.set_read_timeout(Some(LISTENER_READ_TIMEOUT))?;
+        let socket_addr = transport.local_addr()?;
+        if self.sockets.is_empty() {
+            self.ip = Some(socket_addr.ip());
+            self.port = Some(socket_addr.port());
+        }
+        self.sockets.push(transport);
         Ok(())
     }
 
+    pub fn get_local_addrs(&self) -> Vec<SocketAddr> {
+        self.sockets
+            .iter()
+            .filter_map(|socket| socket.local_addr().ok())
+            .collect()
+    }
+
     fn push_handles(&mut self, mut handles_to_append: Vec<JoinHandle<()>>) {
         if self.thread_handles.is_none() {
             self.thread_handles = Some(handles_to_append);
@@ -143,19 +347,19 @@ impl Server {
                 break;
             }
 
-            println!("List of connections in 10 seconds: ");
-            let mut connection_changer = connections.lock().unwrap();
-            for (ip, connection) in &mut (*connection_changer).connections {
+            println!("List of connections: ");
+            let all_stats = connections.lock().unwrap().get_stats(Instant::now());
+            for stats in all_stats {
                 println!(
-                "{} / connections in 10 sec {}, bytes received {}, bytes sent {}, player name {}, player number {}",
-                ip,
-                connection.connections_count,
-                connection.bytes_received,
-                connection.bytes_send,
-                connection.player_name,
-                connection.player_number
+                "{} / packets received {} ({:.1}/s), bytes received {}, bytes sent {}, player name {}, player number {}",
+                stats.addr,
+                stats.packets_received,
+                stats.packets_received_per_second,
+                stats.bytes_received,
+                stats.bytes_sent,
+                stats.player_name,
+                stats.player_number
             );
-                (*connection).connections_count = 0;
             }
         });
 
@@ -163,20 +367,24 @@ impl Server {
     }
 
     pub fn init_listeners(&mut self) -> Result<(), std::io::Error> {
-        if self.socket.is_none() {
+        if self.sockets.is_empty() {
             return Err(Error::new(
                 ErrorKind::Other,
                 "Cannot send without activated socket. Hint: has port or ip address failed?",
             ));
         }
-        let socket = self.socket.as_ref().unwrap();
-
         let mut handles: Vec<JoinHandle<()>> = Vec::new();
-        for handle_index in 1..self.threads_count {
-            let socket = Arc::clone(socket);
+        // Every listen socket gets listener threads of its own.
+        let listeners = self
+            .sockets
+            .iter()
+            .flat_map(|socket| (1..self.threads_count).map(move |_index| Arc::clone(socket)));
+        for (handle_index, socket) in (1..).zip(listeners) {
             let connections = Arc::clone(&self.connections);
             let time_to_die = Arc::clone(&self.time_to_die);
             let protocols = Arc::clone(&self.protocols);
+            let delivery_modes = Arc::clone(&self.delivery_modes);
+            let reassembler = Arc::clone(&self.reassembler);
             let error_state_current = Arc::clone(&self.error_state_current);
             let error_state_previous = Arc::clone(&self.error_state_previous);
             let jobs = Arc::clone(&self.jobs);
@@ -189,6 +397,8 @@ impl Server {
                     connections,
                     jobs,
                     protocols,
+                    delivery_modes,
+                    reassembler,
                     socket,
                     time_to_die,
                     error_state_current,
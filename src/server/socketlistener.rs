@@ -1,15 +1,34 @@
-use crate::protocol::bithelpers::get_u8_from_bit_slice;
-use crate::protocol::datahelpers::{create_player_created_response, create_player_enter_push};
+use crate::protocol::datahelpers::{
+    create_player_created_response, create_player_enter_push, create_player_leave_push,
+};
 use crate::protocol::Protocol;
+use crate::requests::batching::split_batch;
+use crate::requests::challenge::remove_cookie;
+use crate::requests::data::{create_data_response, parse_data_request, DataResponse, DataStatus};
+use crate::requests::delivery::{DeliveryMode, DeliveryModes};
+use crate::requests::fragmentation::{
+    create_datagrams, create_fragment_ack, get_fragment_index_and_count, is_message_too_large,
+    Reassembler, RECEIVE_BUFFER_SIZE,
+};
+use crate::requests::header::{Header, HEADER_LENGTH};
+use crate::requests::heartbeat::{create_heartbeat_probe, get_heartbeat_echo};
 use crate::requests::jobs::Jobs;
-use crate::requests::jobtype::get_job_single_byte;
-use crate::requests::jobtype::get_job_type;
+use crate::requests::leave::LeaveReason;
+use crate::requests::room::{
+    create_room_push, create_room_response, parse_room_request, JoinedRoom, RoomEvent,
+    RoomOperation, RoomPlayer, RoomResponse, RoomStatus,
+};
+use crate::requests::session::get_session_token;
+use crate::requests::targetedpush::{
+    create_targeted_push_response, parse_targeted_push_request, TargetedPushStatus,
+};
 use crate::requests::ClientJob;
-use crate::requests::JobType;
+use crate::requests::Job;
 use crate::requests::ServerJob;
 use crate::server::connection::Connections;
+use crate::socket::Transport;
+use std::io::ErrorKind;
 use std::net::SocketAddr;
-use std::net::UdpSocket;
 use std::string::String;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
@@ -18,13 +37,16 @@ use std::sync::Mutex;
 use std::sync::MutexGuard;
 use std::thread;
 use std::time::Duration;
+use std::time::Instant;
 
 pub struct ServerSocketListener {
     connections: Arc<Mutex<Connections>>,
     protocols: Arc<Protocol>,
+    delivery_modes: Arc<Mutex<DeliveryModes>>,
+    reassembler: Arc<Mutex<Reassembler<SocketAddr>>>,
     jobs: Arc<Mutex<Jobs>>,
     time_to_die: Arc<AtomicBool>,
-    socket: Arc<UdpSocket>,
+    socket: Arc<dyn Transport>,
     error_state_current: Arc<AtomicBool>,
     error_state_previous: Arc<AtomicBool>,
 }
@@ -34,7 +56,9 @@ impl ServerSocketListener {
         connections: Arc<Mutex<Connections>>,
         jobs: Arc<Mutex<Jobs>>,
         protocols: Arc<Protocol>,
-        socket: Arc<UdpSocket>,
+        delivery_modes: Arc<Mutex<DeliveryModes>>,
+        reassembler: Arc<Mutex<Reassembler<SocketAddr>>>,
+        socket: Arc<dyn Transport>,
         time_to_die: Arc<AtomicBool>,
         error_state_current: Arc<AtomicBool>,
         error_state_previous: Arc<AtomicBool>,
@@ -42,6 +66,8 @@ impl ServerSocketListener {
         ServerSocketListener {
             jobs: jobs,
             protocols: protocols,
+            delivery_modes: delivery_modes,
+            reassembler: reassembler,
             socket: socket,
             time_to_die: time_to_die,
             error_state_current: error_state_current,
@@ -59,6 +85,14 @@ impl ServerSocketListener {
 
             let raw_data_maybe = self.read_socket();
 
+            self.remove_timed_out_players();
+            self.remove_kicked_players();
+            self.remove_closed_rooms();
+            self.release_late_requests();
+            self.resend_late_pushes();
+            self.send_queued_datagrams();
+            self.flush_batches();
+
             // if connection state has changed, raise event.
             if self.error_state_current.load(Ordering::SeqCst)
                 != self.error_state_previous.load(Ordering::SeqCst)
@@ -77,35 +111,599 @@ impl ServerSocketListener {
 
             // Thread sleeps 1 second, if connection is lost...
             if raw_data_maybe.is_none() {
-                thread::sleep(Duration::new(1, 0));
+                if self.error_state_current.load(Ordering::SeqCst) {
+                    thread::sleep(Duration::new(1, 0));
+                }
                 continue;
             }
 
             // Data is received, lets work on it.
             let (raw_data, src_addr) = raw_data_maybe.unwrap();
 
-            self.set_connection_stats(src_addr, raw_data.len() as i128);
+            self.set_connection_stats(src_addr, raw_data.len());
 
-            let index_and_type_maybe = self.get_index_and_type(&raw_data);
+            // Batch datagram holds several datagrams, they are handled one by one.
+            for raw_data in split_batch(raw_data) {
+                self.handle_datagram(src_addr, raw_data);
+            }
+            self.set_connection_socket(src_addr);
+            self.flush_batches();
+        }
+    }
+
+    fn handle_datagram(&mut self, src_addr: SocketAddr, raw_data: Vec<u8>) {
+        let header_maybe = self.get_header(&raw_data);
+
+        // Received misformed data
+        if header_maybe.is_none() {
+            return;
+        }
 
-            // Received misformed data
-            if index_and_type_maybe.is_none() {
+        let header = header_maybe.unwrap();
+
+        // Fragments are collected, until whole message is received.
+        let reassembled = self.reassemble(src_addr, header, raw_data);
+        if reassembled.is_none() {
+            return;
+        }
+        let (header, raw_data) = reassembled.unwrap();
+
+        // header job type contains serverjob and clientjob as tuple, second item is client request job.
+        for (header, raw_data) in self.order_received(src_addr, header, raw_data) {
+            // Resent request is answered from cache, it is not handled again.
+            if self.answer_resent_request(src_addr, &header) {
                 continue;
             }
+            self.handle_data(src_addr, header, raw_data);
+        }
+    }
+
+    // Ordered requests, which waited a request the client did not deliver, are handled.
+    fn release_late_requests(&mut self) {
+        let mut released = vec![];
+        let now = Instant::now();
+        let mut connections_changer = self.connections.lock().unwrap();
+        for (addr, connection) in connections_changer.connections.iter_mut() {
+            for (header, raw_data) in connection.receive_channels.release_late(now) {
+                released.push((*addr, header, raw_data));
+            }
+        }
+        drop(connections_changer);
+
+        for (src_addr, header, raw_data) in released {
+            if self.answer_resent_request(src_addr, &header) {
+                continue;
+            }
+            self.handle_data(src_addr, header, raw_data);
+        }
+    }
+
+    // Requests of registered players are released in the order their delivery mode
+    // requires. Requests from unknown addresses are handled at once.
+    fn order_received(
+        &self,
+        src_addr: SocketAddr,
+        header: Header,
+        raw_data: Vec<u8>,
+    ) -> Vec<(Header, Vec<u8>)> {
+        let mut connections_changer = self.connections.lock().unwrap();
+        match connections_changer.connections.get_mut(&src_addr) {
+            None => vec![(header, raw_data)],
+            Some(connection) => {
+                let (mode, sequence) = (header.delivery_mode, header.sequence);
+                // Acknowledgements carry sequence of the push, not of client's messages.
+                if header.get_client_job() != ClientJob::PushAck {
+                    connection
+                        .stats
+                        .add_received_sequence(mode, sequence, Instant::now());
+                }
+                connection
+                    .receive_channels
+                    .receive(mode, sequence, (header, raw_data))
+            }
+        }
+    }
+
+    // Returns whole message, or None if datagram is fragment acknowledgement or fragment
+    // of message, which is not yet fully received.
+    fn reassemble(
+        &self,
+        src_addr: SocketAddr,
+        header: Header,
+        raw_data: Vec<u8>,
+    ) -> Option<(Header, Vec<u8>)> {
+        // Reliable push is kept, until every fragment is acknowledged or push is acknowledged.
+        if header.is_fragment_ack() {
+            if raw_data.len() > HEADER_LENGTH {
+                let fragment_index = raw_data[HEADER_LENGTH];
+                let mut connections_changer = self.connections.lock().unwrap();
+                if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                    connection.ack_push_fragment(&header, fragment_index);
+                }
+            }
+            return None;
+        }
+
+        if !header.is_fragment() {
+            return Some((header, raw_data));
+        }
+
+        // Every fragment of reliable message is acknowledged, so that client
+        // resends only fragments that are lost.
+        if header.delivery_mode.is_reliable() {
+            if let Some((fragment_index, _fragment_count)) = get_fragment_index_and_count(&raw_data)
+            {
+                let ack = create_fragment_ack(&header, fragment_index);
+                let mut connections_changer = self.connections.lock().unwrap();
+                self.send_to_socket(src_addr, &ack, &mut connections_changer);
+            }
+        }
+
+        let mut reassembler_changer = self.reassembler.lock().unwrap();
+        let raw_data = (*reassembler_changer).receive(src_addr, &header, &raw_data)?;
+        Some((header.with_flags(0), raw_data))
+    }
+
+    // Returns true, if reliable request has already been handled. Cached responses
+    // are sent again. Request handled already means that response was lost, so
+    // resend decreases send rate.
+    fn answer_resent_request(&self, src_addr: SocketAddr, header: &Header) -> bool {
+        if !header.delivery_mode.is_reliable() {
+            return false;
+        }
+
+        let mut connections_changer = self.connections.lock().unwrap();
+        let cached_responses = match connections_changer.connections.get_mut(&src_addr) {
+            None => return false,
+            Some(connection) => match connection.get_cached_responses(header) {
+                None => {
+                    connection.add_handled_request(header);
+                    return false;
+                }
+                Some(cached_responses) => {
+                    connection.congestion_control.on_loss();
+                    cached_responses
+                }
+            },
+        };
+        connections_changer.counters.requests_resent += 1;
+
+        for response in cached_responses {
+            self.send_to_socket(src_addr, &response, &mut connections_changer);
+        }
+        true
+    }
+
+    // Response to a reliable request is cached, so that resent request can be answered.
+    fn send_response(
+        &self,
+        src_addr: SocketAddr,
+        request_header: &Header,
+        raw_data: &[u8],
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        if request_header.delivery_mode.is_reliable() {
+            if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                connection.cache_response(request_header, raw_data);
+            }
+        }
+        self.send_to_socket(src_addr, raw_data, connections_changer);
+    }
+
+    // Connection is created or resumed only for verified address. Request starts with
+    // the cookie of server's challenge, returns request without the cookie if address
+    // is verified. Otherwise challenge is sent, if it is not larger than the request,
+    // so that server can not be used to amplify traffic to a spoofed address.
+    fn verify_address(
+        &self,
+        src_addr: SocketAddr,
+        header: &Header,
+        raw_data: &[u8],
+        connections_changer: &mut MutexGuard<Connections>,
+    ) -> Option<Vec<u8>> {
+        let (cookie, request) = match remove_cookie(raw_data) {
+            None => (vec![], raw_data[..HEADER_LENGTH].to_vec()),
+            Some(cookie_and_request) => cookie_and_request,
+        };
+
+        let is_known = connections_changer.connections.contains_key(&src_addr);
+        if is_known || connections_changer.challenges.is_valid(src_addr, &cookie) {
+            return Some(request);
+        }
+
+        let mut data = header
+            .create_response(ServerJob::ChallengeResponse)
+            .get_raw_data();
+        data.append(&mut connections_changer.challenges.create_cookie(src_addr));
+        if data.len() <= raw_data.len() {
+            self.send_to_socket(src_addr, &data, connections_changer);
+        }
+        None
+    }
+
+    // Players that have been silent longer than connection timeout are suspended.
+    // Players, whose session is not resumed within grace period, are removed and
+    // remaining players get player leave push with the player number.
+    fn remove_timed_out_players(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        let timed_out_connections =
+            connections_changer.remove_timed_out_connections(Instant::now());
+        if timed_out_connections.is_empty() {
+            return;
+        }
+
+        for (timed_out_addr, timed_out_connection) in timed_out_connections {
+            println!(
+                "'{}' ({}) timed out as player number {}, session expired",
+                timed_out_connection.player_name,
+                timed_out_addr,
+                timed_out_connection.player_number
+            );
+            self.push_player_leave(
+                timed_out_connection.player_number,
+                LeaveReason::Timeout,
+                &mut connections_changer,
+            );
+        }
+    }
+
+    // Players kicked by the server are removed. Kicked player is told with player
+    // leave push too, as long as it is connected.
+    fn remove_kicked_players(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        for player_number in connections_changer.take_kicked_players() {
+            let active_addr = connections_changer
+                .connections
+                .iter()
+                .find(|(_addr, connection)| connection.player_number == player_number)
+                .map(|(addr, _connection)| *addr);
+            match active_addr {
+                Some(addr) => {
+                    let mode = self.get_player_leave_mode();
+                    let data = self.create_player_leave_data(player_number, LeaveReason::Kicked);
+                    self.send_push(
+                        addr,
+                        ServerJob::PlayerLeavePush,
+                        ClientJob::NoClientAction,
+                        mode,
+                        data,
+                        &mut connections_changer,
+                    );
+                    self.remove_connection(addr, &mut connections_changer);
+                }
+                None => {
+                    if !connections_changer.remove_suspended_player(player_number) {
+                        continue;
+                    }
+                }
+            }
+            println!("Player number {} kicked.", player_number);
+            self.push_player_leave(player_number, LeaveReason::Kicked, &mut connections_changer);
+        }
+    }
+
+    // Members of rooms closed by the server are back in the lobby, and they get
+    // room closed push.
+    fn remove_closed_rooms(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        for (room_id, members) in connections_changer.rooms.take_closed_rooms() {
+            println!("Room {} closed.", room_id);
+            let player_numbers: Vec<u8> = members
+                .iter()
+                .map(|member| member.server_player_number)
+                .collect();
+            self.push_room_event(
+                &RoomEvent::Closed { room_id },
+                &player_numbers,
+                &mut connections_changer,
+            );
+        }
+    }
+
+    fn handle_room_operation(
+        &self,
+        player_number: u8,
+        player_name: String,
+        operation: RoomOperation,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) -> RoomResponse {
+        match operation {
+            RoomOperation::List => RoomResponse::Rooms(connections_changer.rooms.get_rooms()),
+            RoomOperation::Create { name, capacity } => {
+                // Room is not created for a player, who can not join it.
+                if !connections_changer.rooms.is_in_lobby(player_number) {
+                    return RoomResponse::Error(RoomStatus::AlreadyInRoom);
+                }
+                match connections_changer.rooms.create(name, capacity, false) {
+                    Err(status) => RoomResponse::Error(status),
+                    Ok(room) => {
+                        println!("Room {} '{}' created.", room.room_id, room.name);
+                        self.join_room(
+                            room.room_id,
+                            player_number,
+                            player_name,
+                            connections_changer,
+                        )
+                    }
+                }
+            }
+            RoomOperation::Join { room_id } => {
+                self.join_room(room_id, player_number, player_name, connections_changer)
+            }
+            RoomOperation::Leave => match self.leave_room(player_number, connections_changer) {
+                None => RoomResponse::Error(RoomStatus::NotInRoom),
+                Some(room_id) => RoomResponse::Left(room_id),
+            },
+        }
+    }
 
-            let (job_index, job_type) = index_and_type_maybe.unwrap();
-            // job type contains serverjob and clientjob as tuple, second item is client request job.
-            self.handle_data(src_addr, job_index, job_type, raw_data);
+    // Other members of the room get room push of the joined player.
+    fn join_room(
+        &self,
+        room_id: u8,
+        player_number: u8,
+        player_name: String,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) -> RoomResponse {
+        let room_player_number =
+            match connections_changer
+                .rooms
+                .join(room_id, player_number, player_name.clone())
+            {
+                Err(status) => return RoomResponse::Error(status),
+                Ok(room_player_number) => room_player_number,
+            };
+        let members = connections_changer
+            .rooms
+            .get_members(room_id)
+            .unwrap_or_default();
+        let other_player_numbers: Vec<u8> = members
+            .iter()
+            .map(|member| member.server_player_number)
+            .filter(|member_player_number| *member_player_number != player_number)
+            .collect();
+        let event = RoomEvent::PlayerJoined {
+            room_id,
+            player: RoomPlayer {
+                player_number: room_player_number,
+                player_name,
+            },
+        };
+        self.push_room_event(&event, &other_player_numbers, connections_changer);
+
+        match connections_changer.rooms.get_room(room_id) {
+            None => RoomResponse::Error(RoomStatus::NotFound),
+            Some(room) => RoomResponse::Joined(JoinedRoom {
+                room,
+                player_number: room_player_number,
+                players: members
+                    .into_iter()
+                    .map(|member| RoomPlayer {
+                        player_number: member.player_number,
+                        player_name: member.player_name,
+                    })
+                    .collect(),
+            }),
+        }
+    }
+
+    // Remaining members of the room get room push of the left player. Returns room
+    // id, None if player was in the lobby.
+    fn leave_room(
+        &self,
+        player_number: u8,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) -> Option<u8> {
+        let (room_id, room_player_number) = connections_changer.rooms.leave(player_number)?;
+        let member_player_numbers: Vec<u8> = connections_changer
+            .rooms
+            .get_members(room_id)
+            .unwrap_or_default()
+            .iter()
+            .map(|member| member.server_player_number)
+            .collect();
+        let event = RoomEvent::PlayerLeft {
+            room_id,
+            player_number: room_player_number,
+        };
+        self.push_room_event(&event, &member_player_numbers, connections_changer);
+        Some(room_id)
+    }
+
+    fn push_room_event(
+        &self,
+        event: &RoomEvent,
+        player_numbers: &[u8],
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        let mode = self
+            .delivery_modes
+            .lock()
+            .unwrap()
+            .get_server_job_mode(&ServerJob::RoomPush);
+        let addresses: Vec<SocketAddr> = player_numbers
+            .iter()
+            .filter_map(|player_number| connections_changer.get_addr(*player_number))
+            .collect();
+        for addr in addresses {
+            self.send_push(
+                addr,
+                ServerJob::RoomPush,
+                ClientJob::NoClientAction,
+                mode,
+                create_room_push(event),
+                connections_changer,
+            );
+        }
+    }
+
+    fn get_player_leave_mode(&self) -> DeliveryMode {
+        self.delivery_modes
+            .lock()
+            .unwrap()
+            .get_server_job_mode(&ServerJob::PlayerLeavePush)
+    }
+
+    fn create_player_leave_data(&self, player_number: u8, reason: LeaveReason) -> Vec<u8> {
+        create_player_leave_push(&self.protocols, player_number, reason)
+            .expect("Player leave push protocol failed")
+    }
+
+    // Every remaining player is told, that the player left.
+    fn push_player_leave(
+        &self,
+        player_number: u8,
+        reason: LeaveReason,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        connections_changer.data_store.release_owner(player_number);
+        self.leave_room(player_number, connections_changer);
+        let mode = self.get_player_leave_mode();
+        let connection_addresses: Vec<SocketAddr> =
+            connections_changer.connections.keys().copied().collect();
+        for addr in connection_addresses {
+            let data = self.create_player_leave_data(player_number, reason);
+            self.send_push(
+                addr,
+                ServerJob::PlayerLeavePush,
+                ClientJob::NoClientAction,
+                mode,
+                data,
+                connections_changer,
+            );
         }
     }
 
-    // Read socket, blocking method, return None is socket read fails for connection error.
+    // Data is forwarded to target players, if every target is another player in
+    // the room of the sender or in the lobby. Otherwise returns the targets, which
+    // are not.
+    fn send_targeted_push(
+        &self,
+        player_number: u8,
+        target_player_numbers: &[u8],
+        data: Vec<u8>,
+        client_request_type: &ClientJob,
+        header: &Header,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) -> (TargetedPushStatus, Vec<u8>) {
+        let (scope_player_number, receivers) = connections_changer.get_push_scope(player_number);
+        let mut target_addresses: Vec<SocketAddr> = vec![];
+        let mut rejected_player_numbers = vec![];
+        for target_player_number in target_player_numbers {
+            let target_addr_maybe = receivers
+                .iter()
+                .find(|(receiver_player_number, _addr)| {
+                    receiver_player_number == target_player_number
+                })
+                .map(|(_receiver_player_number, addr)| *addr);
+            match target_addr_maybe {
+                None => rejected_player_numbers.push(*target_player_number),
+                // Player named twice gets the push once.
+                Some(target_addr) if !target_addresses.contains(&target_addr) => {
+                    target_addresses.push(target_addr)
+                }
+                Some(_target_addr) => {}
+            }
+        }
+        if !rejected_player_numbers.is_empty() {
+            return (TargetedPushStatus::UnknownTargets, rejected_player_numbers);
+        }
+
+        for target_addr in target_addresses {
+            // Forwarded push keeps the delivery mode of the push request.
+            let mut push_data = vec![scope_player_number];
+            push_data.extend(&data);
+            self.send_push(
+                target_addr,
+                ServerJob::DataPush,
+                client_request_type.clone(),
+                header.delivery_mode,
+                push_data,
+                connections_changer,
+            );
+        }
+        (TargetedPushStatus::Done, vec![])
+    }
+
+    // Datagrams batched and queued to the connection are sent, before connection
+    // is removed.
+    fn remove_connection(
+        &self,
+        addr: SocketAddr,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        let (batch_datagram, queued_datagrams) =
+            match connections_changer.connections.get_mut(&addr) {
+                None => return,
+                Some(connection) => (
+                    connection.batch.take(),
+                    connection.take_queued_datagrams(true),
+                ),
+            };
+        for datagram in batch_datagram.into_iter().chain(queued_datagrams) {
+            self.send_datagram(addr, &datagram, connections_changer);
+        }
+        connections_changer.connections.remove(&addr);
+    }
+
+    // Server originated push. Handle and sequence are taken from receiver's
+    // handle and sequence spaces. Reliable push is kept as job of the connection,
+    // until client acknowledges it.
+    fn send_push(
+        &self,
+        dst_addr: SocketAddr,
+        server_job: ServerJob,
+        client_job: ClientJob,
+        mode: DeliveryMode,
+        mut raw_data: Vec<u8>,
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        let (handle, sequence) = match connections_changer.connections.get_mut(&dst_addr) {
+            None => (0, 0),
+            Some(connection) => (
+                connection.get_next_push_handle(),
+                connection.sequence_counters.get_next_sequence(mode),
+            ),
+        };
+        let header = Header::new(handle, (server_job, client_job), mode, sequence);
+        let job = Job::new(header, &mut raw_data);
+        let data = job.get_raw_data();
+
+        if mode.is_reliable() {
+            if let Some(connection) = connections_changer.connections.get_mut(&dst_addr) {
+                connection.add_push_job(job);
+            }
+        }
+        self.send_to_socket(dst_addr, &data, connections_changer);
+    }
+
+    // Reliable pushes, which clients have not acknowledged in time, are sent again.
+    fn resend_late_pushes(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        let now = Instant::now();
+        let mut late_datagrams = vec![];
+        let connections = &mut *connections_changer;
+        for (addr, connection) in connections.connections.iter_mut() {
+            for datagram in connection.get_late_push_datagrams(now, &mut connections.counters) {
+                late_datagrams.push((*addr, datagram));
+            }
+        }
+
+        for (addr, datagram) in late_datagrams {
+            self.send_to_socket(addr, &datagram, &mut connections_changer);
+        }
+    }
+
+    // Read socket, blocking method, return None is socket read fails for connection error,
+    // or if nothing is received within read timeout.
     fn read_socket(&self) -> Option<(Vec<u8>, SocketAddr)> {
-        let mut buf = [0; 100];
+        let mut buf = [0; RECEIVE_BUFFER_SIZE];
 
         let result = self.socket.recv_from(&mut buf);
-        if result.is_err() {
-            self.error_state_current.store(true, Ordering::SeqCst);
+        if let Err(e) = result {
+            if e.kind() != ErrorKind::WouldBlock && e.kind() != ErrorKind::TimedOut {
+                self.error_state_current.store(true, Ordering::SeqCst);
+            }
             return None;
         }
 
@@ -124,62 +722,143 @@ impl ServerSocketListener {
         raw_data: &[u8],
         connections_changer: &mut MutexGuard<Connections>,
     ) {
-        let result = self.socket.send_to(&raw_data, src_addr);
+        if is_message_too_large(raw_data.len()) {
+            println!(
+                "Message is too large to be fragmented, {} bytes.",
+                raw_data.len()
+            );
+            return;
+        }
+
+        let mode = match Header::from_raw_data(raw_data) {
+            Some(header) => header.delivery_mode,
+            None => DeliveryMode::Unreliable,
+        };
+
+        // Large data is split to fragments, each fragment is sent as own datagram.
+        for datagram in create_datagrams(raw_data) {
+            // Unreliable datagram is dropped, if connection's send budget is exhausted.
+            // Reliable datagram is queued until budget allows it.
+            let connection_maybe = connections_changer.connections.get_mut(&src_addr);
+            if connection_maybe.is_none() {
+                // Unknown address has no batch, datagram is sent at once.
+                self.send_datagram(src_addr, &datagram, connections_changer);
+                continue;
+            }
+
+            let connection = connection_maybe.unwrap();
+            if !connection.admit_datagram(&datagram, mode) {
+                if !mode.is_reliable() {
+                    println!("Send budget exhausted, datagram to {} dropped.", src_addr);
+                }
+                continue;
+            }
+
+            // Datagrams to registered players are batched until next flush.
+            if let Some(batch_datagram) = connection.batch.push(datagram) {
+                self.send_datagram(src_addr, &batch_datagram, connections_changer);
+            }
+        }
+    }
+
+    // Queued reliable datagrams are batched, as far as send budget of the
+    // connection allows.
+    fn send_queued_datagrams(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        let mut batch_datagrams = vec![];
+        for (addr, connection) in connections_changer.connections.iter_mut() {
+            for datagram in connection.take_queued_datagrams(false) {
+                if let Some(batch_datagram) = connection.batch.push(datagram) {
+                    batch_datagrams.push((*addr, batch_datagram));
+                }
+            }
+        }
+
+        for (addr, datagram) in batch_datagrams {
+            self.send_datagram(addr, &datagram, &mut connections_changer);
+        }
+    }
+
+    // Sends batched datagrams, if flush interval has passed.
+    fn flush_batches(&self) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        for (addr, datagram) in connections_changer.take_batches(Instant::now(), false) {
+            self.send_datagram(addr, &datagram, &mut connections_changer);
+        }
+    }
+
+    // Datagram to a player is sent with the socket, which received the player.
+    // Other datagrams answer datagrams received by the socket of this listener.
+    fn send_datagram(
+        &self,
+        dst_addr: SocketAddr,
+        datagram: &[u8],
+        connections_changer: &mut MutexGuard<Connections>,
+    ) {
+        let socket = connections_changer
+            .connections
+            .get(&dst_addr)
+            .and_then(|connection| connection.socket.clone())
+            .unwrap_or_else(|| Arc::clone(&self.socket));
+        let result = socket.send_to(datagram, dst_addr);
 
         if result.is_err() {
+            connections_changer.counters.send_errors += 1;
             self.error_state_current.store(true, Ordering::SeqCst);
             return;
         }
+        connections_changer.counters.packets_sent += 1;
+        connections_changer.counters.bytes_sent += datagram.len() as u64;
 
         if self.error_state_current.load(Ordering::SeqCst) {
             self.error_state_current.store(false, Ordering::SeqCst);
         }
 
-        let connection_maybe = (*connections_changer).connections.get_mut(&src_addr);
+        let connection_maybe = (*connections_changer).connections.get_mut(&dst_addr);
         if connection_maybe.is_some() {
-            let mut connection = connection_maybe.unwrap();
-            (*connection).bytes_send += raw_data.len() as i128;
+            let connection = connection_maybe.unwrap();
+            connection.stats.add_sent(datagram.len(), Instant::now());
         }
     }
 
-    fn get_index_and_type(&self, raw_data: &Vec<u8>) -> Option<(u8, JobType)> {
-        if raw_data.len() < 2 {
-            //let mut jobs_changer = self.jobs.lock().unwrap();
-            //(*jobs_changer).packages_failed += 1;
+    fn get_header(&self, raw_data: &[u8]) -> Option<Header> {
+        let header = Header::from_raw_data(raw_data);
+        if header.is_none() {
+            let mut connections_changer = self.connections.lock().unwrap();
+            connections_changer.counters.packets_failed += 1;
             return None;
         }
-        let index = raw_data[0];
-
-        let server_client = (
-            get_u8_from_bit_slice(raw_data[1], 0, 4),
-            get_u8_from_bit_slice(raw_data[1], 4, 4),
-        );
-
-        let job_type = get_job_type(&server_client);
-        if job_type.is_none() {
-            let mut jobs_changer = self.jobs.lock().unwrap();
-            //(*jobs_changer).packages_failed += 1;
-            return None;
-        }
-        Some((index, job_type.unwrap()))
+        header
     }
 
     fn handle_connection_change(&self, error_state: bool) {
         print!("hello {}", error_state);
     }
 
-    fn set_connection_stats(&self, src_addr: SocketAddr, number_of_bytes: i128) {
+    // Player, which is created or resumed from the address, is answered through
+    // the socket of this listener.
+    fn set_connection_socket(&self, src_addr: SocketAddr) {
+        let mut connections_changer = self.connections.lock().unwrap();
+        if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+            connection.socket = Some(Arc::clone(&self.socket));
+        }
+    }
+
+    fn set_connection_stats(&self, src_addr: SocketAddr, number_of_bytes: usize) {
         // lock connections for changes.
         let mut connections_changer = self.connections.lock().unwrap();
+        connections_changer.counters.packets_received += 1;
+        connections_changer.counters.bytes_received += number_of_bytes as u64;
 
         // add connection stats to to connection struct
         if (*connections_changer).connections.contains_key(&src_addr) {
-            let mut connection = (*connections_changer)
+            let connection = (*connections_changer)
                 .connections
                 .get_mut(&src_addr)
                 .unwrap();
-            connection.connections_count += 1;
-            connection.bytes_received += number_of_bytes;
+            let now = Instant::now();
+            connection.stats.add_received(number_of_bytes, now);
+            connection.last_receive_instant = now;
         }
     }
 
@@ -190,14 +869,8 @@ impl ServerSocketListener {
 
     // Call implemented trait (RequestEvent) methods according
     // what kind of (JobType) data is reveiced.
-    fn handle_data(
-        &mut self,
-        src_addr: SocketAddr,
-        job_index: u8,
-        job: JobType,
-        raw_data: Vec<u8>,
-    ) {
-        let client_request_type = job.1;
+    fn handle_data(&mut self, src_addr: SocketAddr, header: Header, raw_data: Vec<u8>) {
+        let client_request_type = header.get_client_job();
 
         let mut job_duration = Duration::new(0, 0);
 
@@ -219,7 +892,6 @@ impl ServerSocketListener {
                 }
                 let job = job_maybe.unwrap();
                 job_duration = job.finish();
-                (*jobs_changer).add_finish_time(job_duration);
                 (*jobs_changer).packages_handled += 1;
             }
         }*/
@@ -230,9 +902,6 @@ impl ServerSocketListener {
             ClientJob::NoClientAction => {}
 
             ClientJob::DataPushRequest => {
-                let job: JobType = (ServerJob::DataPush, client_request_type.clone());
-                let job_single_byte = get_job_single_byte(&job);
-
                 let mut player_number = 0;
                 // get sender player number
                 if (*connections_changer).connections.contains_key(&src_addr) {
@@ -248,23 +917,28 @@ impl ServerSocketListener {
                     return;
                 }
 
-                // create return data and job single byte for DataPush.
-                let mut dynamic_data = raw_data[2..].to_vec();
+                // create return data for DataPush.
+                let dynamic_data = raw_data[HEADER_LENGTH..].to_vec();
 
                 // for debugging, remove if not used.
                 //let _data_string = String::from_utf8_lossy(&dynamic_data);
-                let mut data = vec![job_index, job_single_byte, player_number];
-                data.append(&mut dynamic_data);
 
-                // Send data to everyone but the request sender.
-                let mut connection_addresses: Vec<SocketAddr> = Vec::new();
-                for (connection_addr, _connection) in &connections_changer.connections {
-                    if *connection_addr != src_addr {
-                        connection_addresses.push(*connection_addr);
-                    }
-                }
-                for addr in connection_addresses {
-                    self.send_to_socket(addr, &data, &mut connections_changer);
+                // Send data to everyone but the request sender, in the room of the
+                // sender or in the lobby.
+                let (scope_player_number, receivers) =
+                    connections_changer.get_push_scope(player_number);
+                for (_receiver_player_number, addr) in receivers {
+                    // Forwarded push keeps the delivery mode of the push request.
+                    let mut data = vec![scope_player_number];
+                    data.extend(&dynamic_data);
+                    self.send_push(
+                        addr,
+                        ServerJob::DataPush,
+                        client_request_type.clone(),
+                        header.delivery_mode,
+                        data,
+                        &mut connections_changer,
+                    );
                 }
 
                 /*
@@ -276,56 +950,136 @@ impl ServerSocketListener {
                     }
                 }*/
 
-                // Inform client that data push has been done.
-                let job: JobType = (ServerJob::DataPushDoneResponse, client_request_type);
-                let job_single_byte = get_job_single_byte(&job);
-                let data = [job_index, job_single_byte];
-                self.send_to_socket(src_addr, &data, &mut connections_changer);
+                // Inform client that data push has been done. Unreliable pushes
+                // are not followed up by the client, so there is nobody to inform.
+                if header.delivery_mode.is_reliable() {
+                    let data = header
+                        .create_response(ServerJob::DataPushDoneResponse)
+                        .get_raw_data();
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
+                }
 
                 //self.socket.send_to(&data, src_addr).expect("Socket fail!");
             }
-            ClientJob::DataRequest => {
-                /*
-                println!("received");
-                if raw_data.len() < 3 {
-                    let mut jobs_changer = self.jobs.lock().unwrap();
-                    (*jobs_changer).packages_failed += 1;
-                    let mut events_changer = self.events.lock().unwrap();
-                    (*events_changer).on_error();
-                    return;
+            ClientJob::TargetedPushRequest => {
+                // Only players can push, requests of other addresses are dropped.
+                let player_number = match connections_changer.connections.get(&src_addr) {
+                    None => return,
+                    Some(connection) => connection.player_number,
+                };
+                let (status, rejected_player_numbers) =
+                    match parse_targeted_push_request(&raw_data[HEADER_LENGTH..]) {
+                        None => (TargetedPushStatus::InvalidRequest, vec![]),
+                        Some((target_player_numbers, data)) => self.send_targeted_push(
+                            player_number,
+                            &target_player_numbers,
+                            data,
+                            &client_request_type,
+                            &header,
+                            &mut connections_changer,
+                        ),
+                    };
+
+                // Unreliable push is not followed up, unless it is rejected.
+                if header.delivery_mode.is_reliable() || status != TargetedPushStatus::Done {
+                    let mut data = header
+                        .create_response(ServerJob::TargetedPushResponse)
+                        .get_raw_data();
+                    data.append(&mut create_targeted_push_response(
+                        status,
+                        &rejected_player_numbers,
+                    ));
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                 }
-                let player = raw_data[2];
-                let mut events_changer = self.events.lock().unwrap();
-                (*events_changer).on_data_push_received(player, raw_data);
-                */
+            }
+            ClientJob::RoomRequest => {
+                // Only players can use rooms, requests of other addresses are dropped,
+                // since room list could be larger than the request.
+                let (player_number, player_name) =
+                    match connections_changer.connections.get(&src_addr) {
+                        None => return,
+                        Some(connection) => (
+                            connection.player_number,
+                            connection.player_name.trim_end_matches('\0').to_string(),
+                        ),
+                    };
+                let response = match parse_room_request(&raw_data[HEADER_LENGTH..]) {
+                    None => RoomResponse::Error(RoomStatus::InvalidRequest),
+                    Some(operation) => self.handle_room_operation(
+                        player_number,
+                        player_name,
+                        operation,
+                        &mut connections_changer,
+                    ),
+                };
+                let mut data = header
+                    .create_response(ServerJob::RoomResponse)
+                    .get_raw_data();
+                data.append(&mut create_room_response(&response));
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
+            }
+            ClientJob::PeerPunch | ClientJob::PeerDataPush => {
+                // Peer datagrams are sent between clients, server ignores them.
+            }
+            ClientJob::DataRequest => {
+                // Players fetch and change values of the shared data store. Requests
+                // of other addresses are dropped, since a value could be larger than
+                // the request.
+                let player_number = match connections_changer.connections.get(&src_addr) {
+                    None => return,
+                    Some(connection) => connection.player_number,
+                };
+                let response = match parse_data_request(&raw_data[HEADER_LENGTH..]) {
+                    None => DataResponse::from_status(DataStatus::InvalidRequest),
+                    Some(operation) => connections_changer
+                        .data_store
+                        .handle(player_number, operation),
+                };
+                let mut data = header
+                    .create_response(ServerJob::DataResponse)
+                    .get_raw_data();
+                data.append(&mut create_data_response(&response));
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
             }
             ClientJob::PlayerEnterRequest => {
                 println!("Player enter request");
 
+                let raw_data = match self.verify_address(
+                    src_addr,
+                    &header,
+                    &raw_data,
+                    &mut connections_changer,
+                ) {
+                    None => return,
+                    Some(raw_data) => raw_data,
+                };
+
                 let raw_len = raw_data.len();
 
-                if raw_len < 4 {
+                if raw_len < HEADER_LENGTH + 2 {
                     println!("invalid package.");
 
                     self.fail_package();
-                    let job: JobType = (ServerJob::PlayerCreatedResponse, client_request_type);
-                    let job_single_byte = get_job_single_byte(&job);
-                    let data = [job_index, job_single_byte, 100];
-                    self.send_to_socket(src_addr, &data, &mut connections_changer);
+                    let mut data = header
+                        .create_response(ServerJob::PlayerCreatedResponse)
+                        .get_raw_data();
+                    data.push(100);
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                     return;
                 }
 
-                let player_name = String::from_utf8_lossy(&raw_data[2..raw_len]);
+                let player_name = String::from_utf8_lossy(&raw_data[HEADER_LENGTH..raw_len]);
 
                 let is_name_taken = (*connections_changer).is_name_taken(player_name.to_string());
                 if is_name_taken {
                     println!("name is taken");
 
                     self.fail_package();
-                    let job: JobType = (ServerJob::PlayerCreatedResponse, client_request_type);
-                    let job_single_byte = get_job_single_byte(&job);
-                    let data = [job_index, job_single_byte, 101];
-                    self.send_to_socket(src_addr, &data, &mut connections_changer);
+                    let mut data = header
+                        .create_response(ServerJob::PlayerCreatedResponse)
+                        .get_raw_data();
+                    data.push(101);
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                     return;
                 }
                 let player_number =
@@ -335,34 +1089,40 @@ impl ServerSocketListener {
                 if player_number.is_none() {
                     println!("too many players");
 
-                    let job: JobType = (ServerJob::PlayerCreatedResponse, client_request_type);
-                    let job_single_byte = get_job_single_byte(&job);
-                    let data = [job_index, job_single_byte, 102];
-                    self.send_to_socket(src_addr, &data, &mut connections_changer);
+                    let mut data = header
+                        .create_response(ServerJob::PlayerCreatedResponse)
+                        .get_raw_data();
+                    data.push(102);
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                     return;
                 }
+                // Enter request was handled before requests of the player were ordered.
+                if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                    connection
+                        .receive_channels
+                        .mark_handled(header.delivery_mode, header.sequence);
+                }
 
-                let job: JobType = (
-                    ServerJob::PlayerCreatedResponse,
-                    client_request_type.clone(),
-                );
-                let job_single_byte = get_job_single_byte(&job);
                 //let data = [job_index, job_single_byte, 1, player_number.unwrap()];
 
                 // let's create player created response. With array data
                 // of other players, names, numbers, ips.
+                let session_token = connections_changer.connections[&src_addr].session_token;
                 let raw_data_result = create_player_created_response(
                     &self.protocols,
                     1,
                     player_name.to_string(),
                     player_number.unwrap(),
+                    session_token,
                     &connections_changer.connections,
                 );
 
                 if raw_data_result.is_ok() {
-                    let mut data = vec![job_index, job_single_byte];
+                    let mut data = header
+                        .create_response(ServerJob::PlayerCreatedResponse)
+                        .get_raw_data();
                     data.append(&mut raw_data_result.unwrap());
-                    self.send_to_socket(src_addr, &data, &mut connections_changer);
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                     // logging...
                     println!(
                         "'{}' entered as player number {}",
@@ -377,15 +1137,19 @@ impl ServerSocketListener {
                     );
 
                     self.fail_package();
-                    let job: JobType = (ServerJob::PlayerCreatedResponse, client_request_type);
-                    let job_single_byte = get_job_single_byte(&job);
-                    let data = [job_index, job_single_byte, 100];
-                    self.send_to_socket(src_addr, &data, &mut connections_changer);
+                    let mut data = header
+                        .create_response(ServerJob::PlayerCreatedResponse)
+                        .get_raw_data();
+                    data.push(100);
+                    self.send_response(src_addr, &header, &data, &mut connections_changer);
                     return;
                 }
 
-                let job: JobType = (ServerJob::PlayerEnterPush, client_request_type);
-                let job_single_byte = get_job_single_byte(&job);
+                let mode = self
+                    .delivery_modes
+                    .lock()
+                    .unwrap()
+                    .get_server_job_mode(&ServerJob::PlayerEnterPush);
 
                 // Send new player information to other players.
                 let mut connection_addresses: Vec<SocketAddr> = Vec::new();
@@ -403,10 +1167,14 @@ impl ServerSocketListener {
                     );
 
                     if raw_data_result.is_ok() {
-                        let mut data = vec![job_index, job_single_byte];
-                        data.append(&mut raw_data_result.unwrap());
-
-                        self.send_to_socket(addr, &data, &mut connections_changer);
+                        self.send_push(
+                            addr,
+                            ServerJob::PlayerEnterPush,
+                            client_request_type.clone(),
+                            mode,
+                            raw_data_result.unwrap(),
+                            &mut connections_changer,
+                        );
                     }
                 }
                 /*
@@ -424,26 +1192,121 @@ impl ServerSocketListener {
                 */
             }
             ClientJob::PlayerLeaveRequest => {
-                /*
-                println!("enter");
-                if raw_data.len() < 4 {
-                    let mut jobs_changer = self.jobs.lock().unwrap();
-                    (*jobs_changer).packages_failed += 1;
-                    let mut events_changer = self.events.lock().unwrap();
-                    (*events_changer).on_error();
-                    return;
+                // Response holds player number of the left player. Address, which is
+                // not a player, gets only the header, so that the response is not
+                // larger than the request. Resent request of a left player is answered
+                // from the response cache.
+                let mut data = header
+                    .create_response(ServerJob::PlayerLeaveResponse)
+                    .get_raw_data();
+                let player_number = match connections_changer.connections.get(&src_addr) {
+                    None => {
+                        self.send_response(src_addr, &header, &data, &mut connections_changer);
+                        return;
+                    }
+                    Some(connection) => connection.player_number,
+                };
+                data.push(player_number);
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
+
+                println!("Player number {} ({}) left.", player_number, src_addr);
+                self.remove_connection(src_addr, &mut connections_changer);
+                self.push_player_leave(player_number, LeaveReason::Quit, &mut connections_changer);
+            }
+            ClientJob::ResumeRequest => {
+                let raw_data = match self.verify_address(
+                    src_addr,
+                    &header,
+                    &raw_data,
+                    &mut connections_changer,
+                ) {
+                    None => return,
+                    Some(raw_data) => raw_data,
+                };
+
+                // Response holds status, and player number if session is resumed.
+                let mut data = header
+                    .create_response(ServerJob::ResumeResponse)
+                    .get_raw_data();
+                let player_number =
+                    get_session_token(&raw_data[HEADER_LENGTH..]).and_then(|session_token| {
+                        connections_changer.resume_connection(
+                            session_token,
+                            src_addr,
+                            Instant::now(),
+                        )
+                    });
+                let player_number = match player_number {
+                    None => {
+                        println!("Session of {} can not be resumed.", src_addr);
+                        data.push(0);
+                        self.send_response(src_addr, &header, &data, &mut connections_changer);
+                        return;
+                    }
+                    Some(player_number) => player_number,
+                };
+                // Client starts its sequences again with resume request.
+                if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                    connection
+                        .receive_channels
+                        .mark_handled(header.delivery_mode, header.sequence);
+                }
+                data.extend(&[1, player_number]);
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
+                println!(
+                    "Player number {} resumed session from {}",
+                    player_number, src_addr
+                );
+
+                // Other players are informed, that player is back with a new address.
+                let mode = self
+                    .delivery_modes
+                    .lock()
+                    .unwrap()
+                    .get_server_job_mode(&ServerJob::PlayerReconnectPush);
+                let connection_addresses: Vec<SocketAddr> = connections_changer
+                    .connections
+                    .keys()
+                    .filter(|addr| **addr != src_addr)
+                    .copied()
+                    .collect();
+                for addr in connection_addresses {
+                    self.send_push(
+                        addr,
+                        ServerJob::PlayerReconnectPush,
+                        client_request_type.clone(),
+                        mode,
+                        vec![player_number],
+                        &mut connections_changer,
+                    );
+                }
+            }
+            ClientJob::HeartbeatRequest => {
+                // Heartbeat keeps connection alive, answer lets client know server is alive.
+                // Answer to a player carries the next heartbeat probe, other addresses
+                // get only the header.
+                let mut data = header
+                    .create_response(ServerJob::HeartbeatResponse)
+                    .get_raw_data();
+                if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                    let echo = get_heartbeat_echo(&raw_data[HEADER_LENGTH..]);
+                    let probe_id = connection.answer_heartbeat(echo, Instant::now());
+                    data.append(&mut create_heartbeat_probe(probe_id));
+                }
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
+            }
+            ClientJob::PushAck => {
+                // Client has received reliable push, it is not sent again.
+                if let Some(connection) = connections_changer.connections.get_mut(&src_addr) {
+                    connection.ack_push(&header);
                 }
-                let player_number = raw_data[2];
-                let mut events_changer = self.events.lock().unwrap();
-                (*events_changer).on_player_enter(player_number, raw_data);
-                */
             }
             ClientJob::PingRequest => {
                 // Inform client that data push has been done.
-                let job: JobType = (ServerJob::PongResponse, client_request_type);
-                let job_single_byte = get_job_single_byte(&job);
-                let data = [job_index, job_single_byte];
-                self.socket.send_to(&data, src_addr).expect("Socket fail!");
+                let data = header
+                    .create_response(ServerJob::PongResponse)
+                    .get_raw_data();
+                self.send_response(src_addr, &header, &data, &mut connections_changer);
             }
         }
     }
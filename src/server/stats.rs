@@ -0,0 +1,259 @@
+use crate::requests::delivery::{is_sequence_newer, DeliveryMode, Sequence};
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+// Rates and loss are counted over this many latest seconds.
+const STATS_WINDOW_SECONDS: u64 = 5;
+
+/*
+ * Connection stats
+ *
+ * Snapshot of the network stats of one connection. Rates and loss are counted over
+ * the latest few seconds, totals since the connection was created. Round trip time
+ * and jitter (mean deviation of round trip time) are sampled from acknowledged
+ * reliable pushes and heartbeat probes, so round trip time is None until the first
+ * push is acknowledged or the first probe is echoed.
+ */
+#[derive(Clone, Debug)]
+pub struct ConnectionStats {
+    pub player_number: u8,
+    pub player_name: String,
+    pub addr: SocketAddr,
+    pub rtt: Option<Duration>,
+    pub jitter: Duration,
+    // Share of messages from client, which did not arrive, 0.0 - 1.0.
+    pub loss_from_client: f64,
+    // Share of reliable pushes to client, which had to be resent, 0.0 - 1.0.
+    pub loss_to_client: f64,
+    pub packets_received_per_second: f64,
+    pub packets_sent_per_second: f64,
+    pub bytes_received_per_second: f64,
+    pub bytes_sent_per_second: f64,
+    pub since_last_packet: Duration,
+    pub packets_received: u64,
+    pub packets_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+}
+
+// Counts of one second.
+#[derive(Clone, Copy, Default)]
+struct Sample {
+    second: u64,
+    packets_received: u64,
+    packets_sent: u64,
+    bytes_received: u64,
+    bytes_sent: u64,
+    messages_expected: u64,
+    messages_received: u64,
+    pushes_sent: u64,
+    pushes_resent: u64,
+}
+
+/*
+ * Stats sampler
+ *
+ * Counts traffic of one connection continuously to one second samples. Samples older
+ * than the stats window are dropped, nothing is reset by reading the stats.
+ *
+ * Loss from client is seen from gaps in the sequences of client's messages, one
+ * sequence space per delivery mode. Late messages, which arrive after a newer one,
+ * are counted as lost.
+ */
+pub struct StatsSampler {
+    start_instant: Instant,
+    samples: VecDeque<Sample>,
+    latest_sequences: HashMap<DeliveryMode, Sequence>,
+    packets_received: u64,
+    packets_sent: u64,
+    bytes_received: u64,
+    bytes_sent: u64,
+}
+
+impl StatsSampler {
+    pub fn new(now: Instant) -> StatsSampler {
+        StatsSampler {
+            start_instant: now,
+            samples: VecDeque::new(),
+            latest_sequences: HashMap::new(),
+            packets_received: 0,
+            packets_sent: 0,
+            bytes_received: 0,
+            bytes_sent: 0,
+        }
+    }
+
+    pub fn add_received(&mut self, number_of_bytes: usize, now: Instant) {
+        self.packets_received += 1;
+        self.bytes_received += number_of_bytes as u64;
+        let sample = self.get_sample(now);
+        sample.packets_received += 1;
+        sample.bytes_received += number_of_bytes as u64;
+    }
+
+    pub fn add_sent(&mut self, number_of_bytes: usize, now: Instant) {
+        self.packets_sent += 1;
+        self.bytes_sent += number_of_bytes as u64;
+        let sample = self.get_sample(now);
+        sample.packets_sent += 1;
+        sample.bytes_sent += number_of_bytes as u64;
+    }
+
+    // Message from client, sequence is of the message's delivery mode.
+    pub fn add_received_sequence(&mut self, mode: DeliveryMode, sequence: Sequence, now: Instant) {
+        let expected = match self.latest_sequences.get(&mode) {
+            None => 1,
+            Some(latest_sequence) if is_sequence_newer(sequence, *latest_sequence) => {
+                sequence.wrapping_sub(*latest_sequence) as u64
+            }
+            // Resent or late message, its sequence has already been counted.
+            Some(_) => return,
+        };
+        self.latest_sequences.insert(mode, sequence);
+        let sample = self.get_sample(now);
+        sample.messages_expected += expected;
+        sample.messages_received += 1;
+    }
+
+    pub fn add_sent_push(&mut self, now: Instant) {
+        self.get_sample(now).pushes_sent += 1;
+    }
+
+    pub fn add_resent_push(&mut self, now: Instant) {
+        self.get_sample(now).pushes_resent += 1;
+    }
+
+    // Client may have started again, its sequences are tracked from scratch.
+    pub fn reset_sequences(&mut self) {
+        self.latest_sequences.clear();
+    }
+
+    pub fn get_packets_received(&self) -> u64 {
+        self.packets_received
+    }
+
+    pub fn get_packets_sent(&self) -> u64 {
+        self.packets_sent
+    }
+
+    pub fn get_bytes_received(&self) -> u64 {
+        self.bytes_received
+    }
+
+    pub fn get_bytes_sent(&self) -> u64 {
+        self.bytes_sent
+    }
+
+    // Sums of the samples within stats window, and the window length. Window is
+    // shorter for a new connection, but at least one second.
+    fn get_window_sum(&self, now: Instant) -> (Sample, f64) {
+        let current_second = self.get_second(now);
+        let mut sum = Sample::default();
+        for sample in self.samples.iter() {
+            if sample.second + STATS_WINDOW_SECONDS <= current_second {
+                continue;
+            }
+            sum.packets_received += sample.packets_received;
+            sum.packets_sent += sample.packets_sent;
+            sum.bytes_received += sample.bytes_received;
+            sum.bytes_sent += sample.bytes_sent;
+            sum.messages_expected += sample.messages_expected;
+            sum.messages_received += sample.messages_received;
+            sum.pushes_sent += sample.pushes_sent;
+            sum.pushes_resent += sample.pushes_resent;
+        }
+        let window_seconds = now
+            .saturating_duration_since(self.start_instant)
+            .as_secs_f64()
+            .min(STATS_WINDOW_SECONDS as f64)
+            .max(1.0);
+        (sum, window_seconds)
+    }
+
+    pub fn get_packets_received_per_second(&self, now: Instant) -> f64 {
+        let (sum, window_seconds) = self.get_window_sum(now);
+        sum.packets_received as f64 / window_seconds
+    }
+
+    pub fn get_packets_sent_per_second(&self, now: Instant) -> f64 {
+        let (sum, window_seconds) = self.get_window_sum(now);
+        sum.packets_sent as f64 / window_seconds
+    }
+
+    pub fn get_bytes_received_per_second(&self, now: Instant) -> f64 {
+        let (sum, window_seconds) = self.get_window_sum(now);
+        sum.bytes_received as f64 / window_seconds
+    }
+
+    pub fn get_bytes_sent_per_second(&self, now: Instant) -> f64 {
+        let (sum, window_seconds) = self.get_window_sum(now);
+        sum.bytes_sent as f64 / window_seconds
+    }
+
+    pub fn get_loss_from_client(&self, now: Instant) -> f64 {
+        let (sum, _window_seconds) = self.get_window_sum(now);
+        get_share(
+            sum.messages_expected.saturating_sub(sum.messages_received),
+            sum.messages_expected,
+        )
+    }
+
+    pub fn get_loss_to_client(&self, now: Instant) -> f64 {
+        let (sum, _window_seconds) = self.get_window_sum(now);
+        get_share(sum.pushes_resent, sum.pushes_sent)
+    }
+
+    fn get_second(&self, now: Instant) -> u64 {
+        now.saturating_duration_since(self.start_instant).as_secs()
+    }
+
+    // Sample of the current second, samples out of stats window are dropped.
+    // Listener threads may add with a slightly older instant, it goes to the latest sample.
+    fn get_sample(&mut self, now: Instant) -> &mut Sample {
+        let latest_second = self.samples.back().map_or(0, |sample| sample.second);
+        let second = self.get_second(now).max(latest_second);
+        while let Some(sample) = self.samples.front() {
+            if sample.second + STATS_WINDOW_SECONDS > second {
+                break;
+            }
+            self.samples.pop_front();
+        }
+        if self.samples.back().map(|sample| sample.second) != Some(second) {
+            self.samples.push_back(Sample {
+                second,
+                ..Sample::default()
+            });
+        }
+        self.samples.back_mut().unwrap()
+    }
+}
+
+fn get_share(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    (part as f64 / total as f64).min(1.0)
+}
+
+/*
+ * Server counters
+ *
+ * Totals of the whole server since start, for metrics. Unlike connection stats,
+ * these include traffic of unknown addresses and of players, who have left.
+ */
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ServerCounters {
+    pub packets_received: u64,
+    pub packets_sent: u64,
+    pub bytes_received: u64,
+    pub bytes_sent: u64,
+    // Received datagrams without a valid header.
+    pub packets_failed: u64,
+    pub send_errors: u64,
+    // Reliable requests resent by clients, answered from cache.
+    pub requests_resent: u64,
+    pub pushes_resent: u64,
+    pub pushes_abandoned: u64,
+}
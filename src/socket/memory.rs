@@ -0,0 +1,154 @@
+use crate::socket::Transport;
+use std::collections::HashMap;
+use std::io;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Ports given for addresses bound with port 0 start here.
+const FIRST_DYNAMIC_PORT: u16 = 49152;
+
+type Datagram = (Vec<u8>, SocketAddr);
+
+struct Endpoints {
+    senders: HashMap<SocketAddr, Sender<Datagram>>,
+    next_port: u16,
+}
+
+/*
+ * Memory network
+ *
+ * In-process network of memory transports. Datagrams are passed through channels,
+ * datagram to an address nobody is bound to is lost, like on UDP. Addresses are
+ * only names, any ip can be used.
+ */
+#[derive(Clone)]
+pub struct MemoryNetwork {
+    endpoints: Arc<Mutex<Endpoints>>,
+}
+
+impl MemoryNetwork {
+    pub fn new() -> MemoryNetwork {
+        MemoryNetwork {
+            endpoints: Arc::new(Mutex::new(Endpoints {
+                senders: HashMap::new(),
+                next_port: FIRST_DYNAMIC_PORT,
+            })),
+        }
+    }
+
+    // Port 0 is replaced with a free port, like on socket bind.
+    pub fn bind(&self, addr: SocketAddr) -> io::Result<MemoryTransport> {
+        let mut endpoints_changer = self.endpoints.lock().unwrap();
+        let mut addr = addr;
+        if addr.port() == 0 {
+            while endpoints_changer
+                .senders
+                .contains_key(&SocketAddr::new(addr.ip(), endpoints_changer.next_port))
+            {
+                endpoints_changer.next_port = endpoints_changer.next_port.wrapping_add(1).max(1);
+            }
+            addr.set_port(endpoints_changer.next_port);
+            endpoints_changer.next_port = endpoints_changer.next_port.wrapping_add(1).max(1);
+        }
+        if endpoints_changer.senders.contains_key(&addr) {
+            return Err(io::Error::new(
+                ErrorKind::AddrInUse,
+                format!("Memory address {} is in use.", addr),
+            ));
+        }
+
+        let (tx, rx) = channel();
+        endpoints_changer.senders.insert(addr, tx);
+        Ok(MemoryTransport {
+            addr,
+            network: self.clone(),
+            rx: Mutex::new(rx),
+            read_timeout: Mutex::new(None),
+            is_shutdown: AtomicBool::new(false),
+        })
+    }
+
+    fn send(&self, src_addr: SocketAddr, dst_addr: SocketAddr, buf: &[u8]) {
+        let endpoints_changer = self.endpoints.lock().unwrap();
+        if let Some(tx) = endpoints_changer.senders.get(&dst_addr) {
+            let _ = tx.send((buf.to_vec(), src_addr));
+        }
+    }
+
+    fn unbind(&self, addr: SocketAddr) {
+        let mut endpoints_changer = self.endpoints.lock().unwrap();
+        endpoints_changer.senders.remove(&addr);
+    }
+}
+
+pub struct MemoryTransport {
+    addr: SocketAddr,
+    network: MemoryNetwork,
+    rx: Mutex<Receiver<Datagram>>,
+    read_timeout: Mutex<Option<Duration>>,
+    is_shutdown: AtomicBool,
+}
+
+impl Transport for MemoryTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        if self.is_shutdown.load(Ordering::SeqCst) {
+            return Err(io::Error::new(
+                ErrorKind::NotConnected,
+                "Transport is shut down.",
+            ));
+        }
+        self.network.send(self.addr, addr, buf);
+        Ok(buf.len())
+    }
+
+    // Datagram larger than the buffer is truncated, like on UDP.
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        let read_timeout = *self.read_timeout.lock().unwrap();
+        let rx = self.rx.lock().unwrap();
+        let received = match read_timeout {
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            Some(timeout) => rx.recv_timeout(timeout),
+        };
+        match received {
+            Ok((data, src_addr)) => {
+                let number_of_bytes = data.len().min(buf.len());
+                buf[..number_of_bytes].copy_from_slice(&data[..number_of_bytes]);
+                Ok((number_of_bytes, src_addr))
+            }
+            Err(RecvTimeoutError::Timeout) => Err(io::Error::new(
+                ErrorKind::WouldBlock,
+                "Nothing received within read timeout.",
+            )),
+            Err(RecvTimeoutError::Disconnected) => Err(io::Error::new(
+                ErrorKind::NotConnected,
+                "Transport is shut down.",
+            )),
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        *self.read_timeout.lock().unwrap() = timeout;
+        Ok(())
+    }
+
+    // Address is freed, datagrams sent to it are lost from now on.
+    fn shutdown(&self) {
+        if !self.is_shutdown.swap(true, Ordering::SeqCst) {
+            self.network.unbind(self.addr);
+        }
+    }
+}
+
+impl Drop for MemoryTransport {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
@@ -1,10 +1,73 @@
+pub mod memory;
+
+use std::io;
 use std::net::IpAddr;
-use std::sync::atomic::AtomicBool;
+use std::net::SocketAddr;
+use std::net::UdpSocket;
 use std::sync::Arc;
+use std::time::Duration;
 
 pub trait SocketCombatible {
     fn is_running(&self) -> bool;
     fn get_port(&self) -> Option<u16>;
     fn get_ip(&self) -> Option<IpAddr>;
-    fn get_is_running_atomic(&self) -> Arc<AtomicBool>;
+}
+
+/*
+ * Transport
+ *
+ * Datagram transport of clients and servers. UdpSocket is the default, memory
+ * transport runs clients and servers inside one process without sockets.
+ * Receive blocks at most read timeout, and returns WouldBlock or TimedOut error
+ * if nothing is received, so that threads notice closing.
+ */
+pub trait Transport: Send + Sync {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize>;
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)>;
+    fn local_addr(&self) -> io::Result<SocketAddr>;
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()>;
+    // Called when owner closes, after its threads have ended.
+    fn shutdown(&self);
+}
+
+impl Transport for UdpSocket {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        UdpSocket::send_to(self, buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        UdpSocket::recv_from(self, buf)
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        UdpSocket::local_addr(self)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        UdpSocket::set_read_timeout(self, timeout)
+    }
+
+    // Socket is closed, when the last reference is dropped.
+    fn shutdown(&self) {}
+}
+
+// Joins ip and port to an address string, IPv6 literals are put in brackets.
+pub fn create_addr_string(ip: &str, port: &str) -> String {
+    if ip.contains(':') && !ip.starts_with('[') {
+        return format!("[{}]:{}", ip, port);
+    }
+    format!("{}:{}", ip, port)
+}
+
+// Returns transport, which can reach the address. Transport with the same address
+// family is preferred, a dual-stack IPv6 socket reaches IPv4 addresses too.
+pub fn select_socket(sockets: &[Arc<dyn Transport>], dst_addr: SocketAddr) -> &dyn Transport {
+    sockets
+        .iter()
+        .find(|socket| match socket.local_addr() {
+            Ok(local_addr) => local_addr.is_ipv4() == dst_addr.is_ipv4(),
+            Err(_) => false,
+        })
+        .unwrap_or(&sockets[0])
+        .as_ref()
 }
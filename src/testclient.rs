@@ -3,11 +3,14 @@ use crate::client::client::Client;
 use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
 use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
 use crate::client::datahandlers::structs::player::PlayerData;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
+use crate::client::datahandlers::structs::request::AbandonedRequest;
 use crate::client::RequestEvents;
 use crate::protocol::builders::RawDataBuilder;
 use crate::protocol::datahelpers;
 use crate::protocol::datastructure::StructuredData;
 use crate::protocol::Protocol;
+use crate::requests::delivery::DeliveryMode;
 use crate::requests::ClientJob;
 use std::io::stdin;
 use std::sync::atomic::AtomicBool;
@@ -105,8 +108,11 @@ impl RequestEvents for Eventful {
         }
     }
 
-    fn on_player_leave(&mut self, _: std::vec::Vec<u8>) {
-        todo!()
+    fn on_player_leave(&mut self, player: PlayerLeaveData) {
+        println!(
+            "Player {} left ({:?}).",
+            player.player_number, player.reason
+        );
     }
     fn on_error(&mut self) {
         println!("UDP ERROR!!!");
@@ -119,6 +125,15 @@ impl RequestEvents for Eventful {
             println!("COnnection established");
         }
     }
+    fn on_request_abandoned(&mut self, request: AbandonedRequest) {
+        println!(
+            "Request {} ({:?}) abandoned after {} attempts in {} ms",
+            request.handle,
+            request.client_job,
+            request.attempts,
+            request.elapsed.as_millis()
+        );
+    }
 }
 
 pub fn testclient() {
@@ -134,6 +149,11 @@ pub fn testclient() {
     local_ip.push_str(&port.trim());
 
     let mut client = Client::new(3);
+    // Data pushes are sent continuously, only the latest one matters.
+    client.set_delivery_mode(
+        ClientJob::DataPushRequest,
+        DeliveryMode::UnreliableSequenced,
+    );
 
     let result = client.connect(local_ip, "localhost:11111".to_string());
     if result.is_err() {
@@ -196,14 +216,18 @@ pub fn testclient() {
                 break;
             }
             "a" => {
-                let _data_owned = data.trim().to_owned().as_bytes().to_vec();
+                let mut data_owned = data.trim().to_owned().as_bytes().to_vec();
 
-                //let _result = client.send_request(ClientJob::DataPushRequest, &mut data_owned);
+                let _result = client.send_request(ClientJob::DataPushRequest, &mut data_owned);
             }
             _ => {}
         }
     }
 
     println!("Exiting...");
+    // Other players are told at once, instead of after connection timeout.
+    if let Ok(request) = client.leave() {
+        let _result = request.wait(Duration::from_secs(1));
+    }
     client.die();
 }
@@ -0,0 +1,105 @@
+use crate::client::pendingrequests::PendingRequests;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::jobs::Jobs;
+use crate::requests::jobworkers::run_job_handler;
+use crate::requests::{ClientJob, Job, ServerJob};
+use crate::tests::common::events::create_events;
+use crate::tests::common::pause;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::channel;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_request_is_abandoned_after_resends() {
+    let events = create_events();
+    let jobs = Arc::new(Jobs::new());
+    let time_to_die = Arc::new(AtomicBool::new(false));
+    let (socket_send_channel_tx, _socket_send_channel_rx) = channel::<(Vec<u8>, Arc<Job>)>();
+
+    // job has been resent 10 times and the last resend is late too.
+    let header = Header::new(
+        42,
+        (ServerJob::NoServerAction, ClientJob::PlayerEnterRequest),
+        DeliveryMode::ReliableUnordered,
+        0,
+    );
+    let mut job = Job::new(header, &mut vec![1, 2, 3]);
+    job.requested_count = 10;
+    job.start_instant = Instant::now() - Duration::from_secs(60);
+    jobs.insert_job(42, job);
+
+    let handler_jobs = Arc::clone(&jobs);
+    let handler_time_to_die = Arc::clone(&time_to_die);
+    let handler_events = Arc::clone(&events);
+    let handle = thread::spawn(move || {
+        run_job_handler(
+            handler_jobs,
+            Arc::new(PendingRequests::new()),
+            socket_send_channel_tx,
+            handler_time_to_die,
+            handler_events,
+        )
+    });
+
+    for _i in 0..50 {
+        if !events.lock().unwrap().abandoned_requests.is_empty() {
+            break;
+        }
+        pause(10);
+    }
+    time_to_die.store(true, Ordering::SeqCst);
+    handle.join().unwrap();
+
+    let events = events.lock().unwrap();
+    assert_eq!(events.abandoned_requests.len(), 1);
+    let request = &events.abandoned_requests[0];
+    assert_eq!(request.handle, 42);
+    assert_eq!(request.client_job, ClientJob::PlayerEnterRequest);
+    assert_eq!(request.attempts, 11);
+    assert!(jobs.jobs.lock().unwrap().is_empty());
+}
+
+#[test]
+fn test_late_job_is_removed_when_sender_is_closed() {
+    let jobs = Arc::new(Jobs::new());
+    let time_to_die = Arc::new(AtomicBool::new(false));
+    // sender thread has closed, so resend can not be queued.
+    let (socket_send_channel_tx, socket_send_channel_rx) = channel::<(Vec<u8>, Arc<Job>)>();
+    drop(socket_send_channel_rx);
+
+    let header = Header::new(
+        7,
+        (ServerJob::NoServerAction, ClientJob::DataPushRequest),
+        DeliveryMode::ReliableUnordered,
+        0,
+    );
+    let mut job = Job::new(header, &mut vec![1, 2, 3]);
+    job.start_instant = Instant::now() - Duration::from_secs(60);
+    jobs.insert_job(7, job);
+
+    let handler_jobs = Arc::clone(&jobs);
+    let handler_time_to_die = Arc::clone(&time_to_die);
+    let handle = thread::spawn(move || {
+        run_job_handler(
+            handler_jobs,
+            Arc::new(PendingRequests::new()),
+            socket_send_channel_tx,
+            handler_time_to_die,
+            create_events(),
+        )
+    });
+
+    for _i in 0..50 {
+        if jobs.jobs.lock().unwrap().is_empty() {
+            break;
+        }
+        pause(10);
+    }
+    time_to_die.store(true, Ordering::SeqCst);
+    // job handler does not panic, it removes the job.
+    handle.join().unwrap();
+    assert!(jobs.jobs.lock().unwrap().is_empty());
+}
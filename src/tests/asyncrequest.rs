@@ -0,0 +1,78 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{RequestError, Response};
+use crate::client::pendingrequests::{PendingRequests, ResponseFuture};
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use std::future::Future;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use crate::client;
+
+// Minimal executor, thread sleeps until future wakes it up.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut context = Context::from_waker(&waker);
+    loop {
+        match future.as_mut().poll(&mut context) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+fn create_client(server_addr: String) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    client
+}
+
+#[test]
+fn test_ping_future_resolves_to_pong() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let mut client = create_client(format!("localhost:{}", server.get_port().unwrap()));
+
+    let future = client
+        .send_request_async(ClientJob::PingRequest, &mut vec![], Duration::from_secs(3))
+        .unwrap();
+
+    match block_on(future) {
+        Ok(Response::Pong(_time)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    client.die();
+    server.die();
+}
+
+#[test]
+fn test_unanswered_request_future_times_out() {
+    let pending_requests = Arc::new(PendingRequests::new());
+    pending_requests.add(42, Some(Duration::from_millis(0)));
+    let future = ResponseFuture::new(42, Arc::clone(&pending_requests));
+
+    // job handler expires requests, which are past their deadline.
+    pending_requests.expire(Instant::now());
+
+    match block_on(future) {
+        Err(RequestError::Timeout) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    assert!(!pending_requests.is_pending(42));
+}
@@ -0,0 +1,133 @@
+use crate::client::client::Client;
+use crate::requests::batching::{split_batch, Batch};
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::fragmentation::MAX_DATAGRAM_SIZE;
+use crate::requests::header::Header;
+use crate::requests::{ClientJob, ServerJob};
+use crate::tests::common::events::create_events;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_datagram(handle: u16, data_length: usize) -> Vec<u8> {
+    let header = Header::new(
+        handle,
+        (ServerJob::NoServerAction, ClientJob::DataPushRequest),
+        DeliveryMode::Unreliable,
+        handle,
+    );
+    let mut raw_data = header.get_raw_data();
+    raw_data.extend(vec![7; data_length]);
+    raw_data
+}
+
+// Client, which sends to a socket of the test, so that sent datagrams can be inspected.
+fn create_client_with_flush_interval(flush_interval: Duration) -> (Client, UdpSocket) {
+    let server_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    server_socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+    let server_addr = server_socket.local_addr().unwrap().to_string();
+
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client.set_flush_interval(flush_interval);
+    client::run(&mut client, create_events());
+    (client, server_socket)
+}
+
+#[test]
+fn test_batch_is_split_to_original_datagrams() {
+    let mut batch = Batch::new();
+    assert!(batch.push(create_datagram(1, 10)).is_none());
+    assert!(batch.push(create_datagram(2, 20)).is_none());
+
+    let batch_datagram = batch.take().unwrap();
+    let header = Header::from_raw_data(&batch_datagram).unwrap();
+
+    assert!(header.is_batch());
+    assert!(batch.is_empty());
+    assert_eq!(
+        split_batch(batch_datagram),
+        vec![create_datagram(1, 10), create_datagram(2, 20)]
+    );
+}
+
+#[test]
+fn test_batch_of_one_datagram_has_no_overhead() {
+    let mut batch = Batch::new();
+    batch.push(create_datagram(1, 10));
+
+    let datagram = batch.take().unwrap();
+
+    assert_eq!(datagram, create_datagram(1, 10));
+    assert_eq!(split_batch(datagram), vec![create_datagram(1, 10)]);
+}
+
+#[test]
+fn test_full_batch_is_flushed_before_next_datagram() {
+    let mut batch = Batch::new();
+    batch.push(create_datagram(1, MAX_DATAGRAM_SIZE / 2));
+
+    let flushed = batch.push(create_datagram(2, MAX_DATAGRAM_SIZE / 2));
+
+    assert_eq!(flushed, Some(create_datagram(1, MAX_DATAGRAM_SIZE / 2)));
+    assert_eq!(
+        batch.take(),
+        Some(create_datagram(2, MAX_DATAGRAM_SIZE / 2))
+    );
+}
+
+#[test]
+fn test_requests_are_sent_in_one_datagram_per_flush() {
+    let (client, server_socket) = create_client_with_flush_interval(Duration::from_millis(200));
+
+    client
+        .send_request_with_mode(
+            ClientJob::PingRequest,
+            &mut vec![],
+            DeliveryMode::Unreliable,
+        )
+        .unwrap();
+    client
+        .send_request_with_mode(
+            ClientJob::DataPushRequest,
+            &mut vec![1, 2, 3],
+            DeliveryMode::Unreliable,
+        )
+        .unwrap();
+
+    let mut buf = [0; 1500];
+    let number_of_bytes = server_socket.recv(&mut buf).unwrap();
+    let datagrams = split_batch(buf[..number_of_bytes].to_vec());
+
+    assert_eq!(datagrams.len(), 2);
+    let first_header = Header::from_raw_data(&datagrams[0]).unwrap();
+    let second_header = Header::from_raw_data(&datagrams[1]).unwrap();
+    assert_eq!(first_header.get_client_job(), ClientJob::PingRequest);
+    assert_eq!(second_header.get_client_job(), ClientJob::DataPushRequest);
+    assert_eq!(datagrams[1][datagrams[1].len() - 3..], [1, 2, 3]);
+}
+
+#[test]
+fn test_explicit_flush_does_not_wait_for_flush_interval() {
+    let (client, server_socket) = create_client_with_flush_interval(Duration::from_secs(60));
+
+    client
+        .send_request_with_mode(
+            ClientJob::PingRequest,
+            &mut vec![],
+            DeliveryMode::Unreliable,
+        )
+        .unwrap();
+    client.flush().unwrap();
+
+    let mut buf = [0; 1500];
+    let number_of_bytes = server_socket.recv(&mut buf).unwrap();
+    let header = Header::from_raw_data(&buf[..number_of_bytes]).unwrap();
+
+    assert_eq!(header.get_client_job(), ClientJob::PingRequest);
+}
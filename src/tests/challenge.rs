@@ -0,0 +1,114 @@
+use crate::requests::challenge::COOKIE_LENGTH;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::{ClientJob, ServerJob};
+use crate::server;
+use crate::server::server::Server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::rawclient::{create_player_enter_request, request_challenge_cookie};
+use std::net::UdpSocket;
+use std::time::Duration;
+
+// Server of its own, so that players of other tests are not challenged.
+fn create_server() -> Server {
+    server::run("localhost".to_string(), "0".to_string(), 3).unwrap()
+}
+
+fn create_socket(server: &Server) -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .connect(format!("localhost:{}", server.get_port().unwrap()))
+        .unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    socket
+}
+
+fn receive(socket: &UdpSocket) -> Option<Vec<u8>> {
+    let mut buf = [0; 2048];
+    let number_of_bytes = socket.recv(&mut buf).ok()?;
+    Some(buf[..number_of_bytes].to_vec())
+}
+
+#[test]
+fn test_unverified_player_gets_challenge_not_larger_than_request() {
+    let mut server = create_server();
+    let socket = create_socket(&server);
+
+    let request = create_player_enter_request(0, "Challenged", &[0; COOKIE_LENGTH]);
+    socket.send(&request).unwrap();
+
+    let challenge = receive(&socket).unwrap();
+    let header = Header::from_raw_data(&challenge).unwrap();
+    assert_eq!(header.get_server_job(), ServerJob::ChallengeResponse);
+    assert!(challenge.len() <= request.len());
+    // no player is created, so there is nothing else to receive.
+    assert!(receive(&socket).is_none());
+
+    server.die();
+}
+
+#[test]
+fn test_echoed_cookie_creates_player() {
+    let mut server = create_server();
+    let socket = create_socket(&server);
+
+    let cookie = request_challenge_cookie(&socket, "Verified");
+    socket
+        .send(&create_player_enter_request(1, "Verified", &cookie))
+        .unwrap();
+
+    let response = receive(&socket).unwrap();
+    let header = Header::from_raw_data(&response).unwrap();
+    assert_eq!(header.get_server_job(), ServerJob::PlayerCreatedResponse);
+
+    server.die();
+}
+
+#[test]
+fn test_forged_or_borrowed_cookie_is_challenged_again() {
+    let mut server = create_server();
+    let socket = create_socket(&server);
+    let other_socket = create_socket(&server);
+
+    socket
+        .send(&create_player_enter_request(
+            1,
+            "Forger",
+            &[1; COOKIE_LENGTH],
+        ))
+        .unwrap();
+    let response = receive(&socket).unwrap();
+    let header = Header::from_raw_data(&response).unwrap();
+    assert_eq!(header.get_server_job(), ServerJob::ChallengeResponse);
+
+    // cookie is valid only for the address it was sent to.
+    let cookie = request_challenge_cookie(&other_socket, "Borrower");
+    socket
+        .send(&create_player_enter_request(2, "Borrower", &cookie))
+        .unwrap();
+    let response = receive(&socket).unwrap();
+    let header = Header::from_raw_data(&response).unwrap();
+    assert_eq!(header.get_server_job(), ServerJob::ChallengeResponse);
+
+    server.die();
+}
+
+#[test]
+fn test_request_smaller_than_challenge_is_not_answered() {
+    let mut server = create_server();
+    let socket = create_socket(&server);
+
+    let header = Header::new(
+        0,
+        (ServerJob::NoServerAction, ClientJob::PlayerEnterRequest),
+        DeliveryMode::ReliableUnordered,
+        0,
+    );
+    socket.send(&header.get_raw_data()).unwrap();
+
+    assert!(receive(&socket).is_none());
+
+    server.die();
+}
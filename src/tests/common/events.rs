@@ -0,0 +1,76 @@
+use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
+use crate::client::datahandlers::structs::player::{
+    PlayerCreatedResponseData, PlayerData, PlayerLeaveData,
+};
+use crate::client::datahandlers::structs::request::AbandonedRequest;
+use crate::client::peers::PeerPath;
+use crate::client::RequestEvents;
+use crate::requests::room::RoomEvent;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Client events of a test, tests check them after requests.
+#[derive(Default)]
+pub struct RecordingEvents {
+    // player will be stored here, if server returns correctly
+    pub player: Option<PlayerData>,
+    // players from player created response and player enter pushes
+    pub other_players: Vec<PlayerData>,
+    pub pushes: Vec<(u8, Vec<u8>)>,
+    pub left_players: Vec<PlayerLeaveData>,
+    pub reconnected_players: Vec<u8>,
+    pub abandoned_requests: Vec<AbandonedRequest>,
+    pub path_changes: Vec<(u8, PeerPath)>,
+    pub room_events: Vec<RoomEvent>,
+    pub is_disconnected: bool,
+}
+
+impl RequestEvents for RecordingEvents {
+    fn on_data_push_action(&mut self, _raw_data: Vec<u8>) {}
+    fn on_pong(&mut self, _time: Duration) {}
+    fn on_data_push_received(&mut self, player: u8, raw_data: Vec<u8>) {
+        self.pushes.push((player, raw_data));
+    }
+    fn on_data_request(&mut self, _: std::vec::Vec<u8>) {}
+    fn on_player_enter_push(&mut self, player: PlayerData) {
+        self.other_players.push(player);
+    }
+    fn on_player_created(
+        &mut self,
+        player_created_response_data_result: Result<
+            PlayerCreatedResponseData,
+            PlayerCreatedServerError,
+        >,
+    ) {
+        if let Ok(player_created_response_data) = player_created_response_data_result {
+            self.player = Some(player_created_response_data.player);
+            self.other_players
+                .extend(player_created_response_data.others_players);
+        }
+    }
+    fn on_player_leave(&mut self, player: PlayerLeaveData) {
+        self.left_players.push(player);
+    }
+    fn on_error(&mut self) {}
+    fn on_connection_state_change(&mut self, _error_state: bool) {}
+    fn on_request_abandoned(&mut self, request: AbandonedRequest) {
+        self.abandoned_requests.push(request);
+    }
+    fn on_disconnect(&mut self) {
+        self.is_disconnected = true;
+    }
+    fn on_player_reconnect_push(&mut self, player_number: u8) {
+        self.reconnected_players.push(player_number);
+    }
+    fn on_peer_path_change(&mut self, player_number: u8, path: PeerPath) {
+        self.path_changes.push((player_number, path));
+    }
+    fn on_room_event(&mut self, event: RoomEvent) {
+        self.room_events.push(event);
+    }
+}
+
+pub fn create_events() -> Arc<Mutex<RecordingEvents>> {
+    Arc::new(Mutex::new(RecordingEvents::default()))
+}
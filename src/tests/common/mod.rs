@@ -3,6 +3,8 @@ use crate::tests::common::testserver::{global_test_server, wait_till_server_is_r
 use std::thread;
 use std::time::Duration;
 
+pub mod events;
+pub mod rawclient;
 pub mod testclient;
 pub mod testcounter;
 pub mod testserver;
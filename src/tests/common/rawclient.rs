@@ -0,0 +1,38 @@
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::challenge::COOKIE_LENGTH;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::{Header, HEADER_LENGTH};
+use crate::requests::{ClientJob, ServerJob};
+use std::net::UdpSocket;
+
+// Player enter request sent without client. Cookie echoes the challenge of the server.
+pub fn create_player_enter_request(handle: u16, name: &str, cookie: &[u8]) -> Vec<u8> {
+    let protocols = Protocol::new();
+    let header = Header::new(
+        handle,
+        (ServerJob::NoServerAction, ClientJob::PlayerEnterRequest),
+        DeliveryMode::ReliableUnordered,
+        handle,
+    );
+    let mut raw_data = header.get_raw_data();
+    raw_data.extend_from_slice(cookie);
+    raw_data.append(&mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap());
+    raw_data
+}
+
+// Sends player enter request with an empty cookie, returns cookie of the challenge.
+// Other datagrams are skipped.
+pub fn request_challenge_cookie(socket: &UdpSocket, name: &str) -> Vec<u8> {
+    let request = create_player_enter_request(0, name, &[0; COOKIE_LENGTH]);
+    socket.send(&request).unwrap();
+
+    let mut buf = [0; 2048];
+    loop {
+        let number_of_bytes = socket.recv(&mut buf).unwrap();
+        let header = Header::from_raw_data(&buf[..number_of_bytes]).unwrap();
+        if header.get_server_job() == ServerJob::ChallengeResponse {
+            return buf[HEADER_LENGTH..number_of_bytes].to_vec();
+        }
+    }
+}
@@ -1,19 +1,16 @@
 use crate::client::client::Client;
 use crate::tests::common::testserver::get_server_port;
 
+// Port is chosen by the system, so that port of a closed client is not reused.
+// Server would take the new client for the closed one.
 pub fn create_client() -> Client {
     let mut client = Client::new(3);
     let server_port = get_server_port().unwrap();
-    for port in 49152..65535 {
-        let local_ip: String = format!("127.0.0.1:{}", port);
-        let result = client.connect(local_ip, format!("localhost:{}", server_port));
-        match result {
-            Err(_e) => {}
-            Ok(_r) => {
-                println!("Client using port {}", port);
-                break;
-            }
-        }
-    }
+    client
+        .connect(
+            "127.0.0.1:0".to_string(),
+            format!("localhost:{}", server_port),
+        )
+        .unwrap();
     client
 }
@@ -40,5 +40,8 @@ pub fn add_finished_count() {
     global_test_counter().lock().unwrap().add_finished_count();
 }
 pub fn are_all_tests_complited() -> bool {
-    global_test_counter().lock().unwrap().are_all_tests_complited()
+    global_test_counter()
+        .lock()
+        .unwrap()
+        .are_all_tests_complited()
 }
@@ -0,0 +1,132 @@
+use crate::client;
+use crate::client::client::Client;
+use crate::requests::congestion::CongestionControl;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::fragmentation::MAX_DATAGRAM_SIZE;
+use crate::requests::ClientJob;
+use crate::server::connection::Connection;
+use crate::socket::Transport;
+use crate::tests::common::events::create_events;
+use crate::tests::common::pause;
+use std::io;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+// Transport of a client, whose every send fails.
+struct FailingTransport {
+    addr: SocketAddr,
+}
+
+impl Transport for FailingTransport {
+    fn send_to(&self, _buf: &[u8], _addr: SocketAddr) -> io::Result<usize> {
+        Err(io::Error::new(ErrorKind::Other, "Network is unreachable."))
+    }
+
+    fn recv_from(&self, _buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        pause(20);
+        Err(io::Error::new(ErrorKind::WouldBlock, "Nothing received."))
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        Ok(self.addr)
+    }
+
+    fn set_read_timeout(&self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn shutdown(&self) {}
+}
+
+#[test]
+fn test_unreliable_is_dropped_before_reliable() {
+    let mut congestion_control = CongestionControl::new();
+
+    // exhaust budget with unreliable datagrams, reserve is left for reliable ones.
+    while congestion_control.try_send(MAX_DATAGRAM_SIZE, DeliveryMode::Unreliable) {}
+
+    assert!(!congestion_control.try_send(MAX_DATAGRAM_SIZE, DeliveryMode::UnreliableSequenced));
+    assert!(congestion_control.try_send(MAX_DATAGRAM_SIZE, DeliveryMode::ReliableOrdered));
+}
+
+#[test]
+fn test_loss_decreases_and_ack_increases_rate() {
+    let mut congestion_control = CongestionControl::new();
+    let initial_rate = congestion_control.get_bytes_per_second();
+
+    congestion_control.on_ack(None);
+    let increased_rate = congestion_control.get_bytes_per_second();
+    assert!(increased_rate > initial_rate);
+
+    congestion_control.on_loss();
+    let decreased_rate = congestion_control.get_bytes_per_second();
+    assert!(decreased_rate < increased_rate);
+
+    // losses right after each other are one congestion event.
+    congestion_control.on_loss();
+    assert_eq!(congestion_control.get_bytes_per_second(), decreased_rate);
+}
+
+#[test]
+fn test_growing_round_trip_time_decreases_rate() {
+    let mut congestion_control = CongestionControl::new();
+    let initial_rate = congestion_control.get_bytes_per_second();
+
+    assert!(!congestion_control.on_rtt_sample(Duration::from_millis(50)));
+    assert_eq!(congestion_control.get_bytes_per_second(), initial_rate);
+    assert!(congestion_control.on_rtt_sample(Duration::from_millis(400)));
+    assert!(congestion_control.get_bytes_per_second() < initial_rate);
+}
+
+#[test]
+fn test_reliable_datagrams_wait_for_budget() {
+    let mut connection = Connection::new(1, "Player".to_string(), 1);
+    let datagram = vec![0; MAX_DATAGRAM_SIZE];
+
+    // exhaust budget, unreliable datagrams are dropped and reliable ones queued.
+    while connection.admit_datagram(&datagram, DeliveryMode::ReliableOrdered) {}
+    assert!(!connection.admit_datagram(&datagram, DeliveryMode::Unreliable));
+    assert!(!connection.admit_datagram(&datagram, DeliveryMode::ReliableUnordered));
+    assert!(connection.take_queued_datagrams(false).is_empty());
+
+    // queued datagrams are sent, when budget refills.
+    std::thread::sleep(Duration::from_millis(100));
+    let sent_datagrams = connection.take_queued_datagrams(false);
+    assert!(!sent_datagrams.is_empty());
+    let left_datagrams = connection.take_queued_datagrams(true);
+    assert_eq!(sent_datagrams.len() + left_datagrams.len(), 2);
+}
+
+#[test]
+fn test_send_error_is_not_acknowledgement() {
+    let transport = Arc::new(FailingTransport {
+        addr: "127.0.0.1:40000".parse().unwrap(),
+    });
+    let mut client = Client::new(3);
+    client
+        .connect_with_transport(transport, "127.0.0.1:40001".parse().unwrap())
+        .unwrap();
+    let events = create_events();
+    client::run(&mut client, Arc::clone(&events));
+    let initial_rate = client.get_send_rate();
+
+    client
+        .send_request(ClientJob::DataPushRequest, &mut b"lost".to_vec())
+        .unwrap();
+    for _i in 0..40 {
+        if !events.lock().unwrap().abandoned_requests.is_empty() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(events.lock().unwrap().abandoned_requests.len(), 1);
+    pause(100);
+
+    // send rate is not raised, and round trip time is not sampled.
+    assert!(client.get_send_rate() <= initial_rate);
+    assert_eq!(client.get_job_ping(), 500.0);
+
+    client.die();
+}
@@ -0,0 +1,136 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::server::stats::StatsSampler;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use crate::tests::common::pause;
+use std::time::{Duration, Instant};
+
+use crate::client;
+
+fn create_client(server_addr: String) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> u8 {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_loss_is_counted_from_sequence_gaps_and_resends() {
+    let now = Instant::now();
+    let mut sampler = StatsSampler::new(now);
+    // sequence 2 is late, it is counted as lost as well as sequence 4.
+    for sequence in [0, 1, 3, 2, 5] {
+        sampler.add_received_sequence(DeliveryMode::ReliableUnordered, sequence, now);
+    }
+    // other delivery modes have their own sequences.
+    sampler.add_received_sequence(DeliveryMode::Unreliable, 7, now);
+    assert!((sampler.get_loss_from_client(now) - 2.0 / 7.0).abs() < 0.001);
+
+    for _i in 0..4 {
+        sampler.add_sent_push(now);
+    }
+    sampler.add_resent_push(now);
+    assert!((sampler.get_loss_to_client(now) - 0.25).abs() < 0.001);
+
+    // samples fall out of stats window.
+    let later = now + Duration::from_secs(10);
+    assert_eq!(sampler.get_loss_from_client(later), 0.0);
+    assert_eq!(sampler.get_loss_to_client(later), 0.0);
+}
+
+#[test]
+fn test_server_samples_connection_stats() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let mut first = create_client(server_addr.clone());
+    let first_number = enter_player(&first, "First");
+    // enter push of second player is acknowledged by first, which gives round trip time.
+    let mut second = create_client(server_addr);
+    let second_number = enter_player(&second, "Second");
+    // data pushes may be batched to one datagram.
+    for _i in 0..5 {
+        first
+            .send_request(ClientJob::DataPushRequest, &mut b"stats".to_vec())
+            .unwrap();
+    }
+
+    let mut stats = server.connection_stats(first_number).unwrap();
+    for _i in 0..60 {
+        if stats.rtt.is_some() && stats.packets_received >= 2 {
+            break;
+        }
+        pause(50);
+        stats = server.connection_stats(first_number).unwrap();
+    }
+    assert!(stats.player_name.starts_with("First"));
+    assert!(stats.rtt.is_some());
+    assert!(stats.packets_received >= 2);
+    assert!(stats.packets_sent >= 1);
+    assert!(stats.bytes_received_per_second > 0.0);
+    assert!(stats.bytes_sent_per_second > 0.0);
+    assert_eq!(stats.loss_from_client, 0.0);
+    assert!(stats.since_last_packet < Duration::from_secs(1));
+
+    // reading stats does not reset them.
+    let all_stats = server.all_stats();
+    let player_numbers: Vec<u8> = all_stats.iter().map(|stats| stats.player_number).collect();
+    assert_eq!(player_numbers, vec![first_number, second_number]);
+    assert!(all_stats[0].packets_received >= stats.packets_received);
+    assert!(all_stats[0].bytes_received >= stats.bytes_received);
+    assert!(server.connection_stats(99).is_none());
+
+    first.die();
+    second.die();
+    server.die();
+}
+
+#[test]
+fn test_round_trip_time_is_sampled_from_heartbeats() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    // only player gets no reliable pushes, heartbeats give round trip time.
+    let mut client = Client::new(3);
+    client.set_keepalive_interval(Duration::from_millis(50));
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    let player_number = enter_player(&client, "Lonely");
+
+    let mut rtt = None;
+    for _i in 0..40 {
+        rtt = server.connection_stats(player_number).unwrap().rtt;
+        if rtt.is_some() {
+            break;
+        }
+        pause(50);
+    }
+    assert!(rtt.unwrap() < Duration::from_secs(1));
+
+    client.die();
+    server.die();
+}
@@ -1,6 +1,7 @@
 use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
 use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
 use crate::client::datahandlers::structs::player::PlayerData;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
 use crate::client::RequestEvents;
 use crate::protocol::Protocol;
 use crate::tests::common::init_test_environment;
@@ -63,7 +64,7 @@ impl RequestEvents for Eventful {
         }
     }
 
-    fn on_player_leave(&mut self, _: std::vec::Vec<u8>) {
+    fn on_player_leave(&mut self, _player: PlayerLeaveData) {
         todo!()
     }
     fn on_error(&mut self) {
@@ -0,0 +1,214 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{DataError, RequestError, Response};
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::data::{
+    create_data_request, parse_data_request, DataOperation, DataResponse, DataStatus,
+};
+use crate::requests::ClientJob;
+use crate::server;
+use crate::server::datastore::{DataAccess, DataStore};
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use std::time::Duration;
+
+use crate::client;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+fn create_client(server_addr: String) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> u8 {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(TIMEOUT) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_data_requests_survive_encoding() {
+    let operations = vec![
+        DataOperation::Get {
+            key: "score".to_string(),
+        },
+        DataOperation::Set {
+            key: "score".to_string(),
+            value: vec![1, 2, 3],
+        },
+        DataOperation::CompareAndSet {
+            key: "score".to_string(),
+            expected: None,
+            value: vec![4],
+        },
+        DataOperation::CompareAndSet {
+            key: "score".to_string(),
+            expected: Some(vec![]),
+            value: vec![],
+        },
+        DataOperation::Delete {
+            key: "score".to_string(),
+        },
+    ];
+    for operation in operations {
+        assert_eq!(
+            parse_data_request(&create_data_request(&operation)),
+            Some(operation)
+        );
+    }
+    // key length exceeds the data.
+    assert_eq!(parse_data_request(&[1, 10, b'a']), None);
+    assert_eq!(parse_data_request(&[9, 1, b'a']), None);
+}
+
+#[test]
+fn test_access_rules_and_limits() {
+    let mut store = DataStore::new();
+    store.set_access("match/".to_string(), DataAccess::ReadOnly);
+    store.set_access("match/public/".to_string(), DataAccess::ReadWrite);
+    store.set_access("player/".to_string(), DataAccess::OwnerWrite);
+    store.set_access("secret/".to_string(), DataAccess::Hidden);
+    store.max_value_length = 4;
+    store.max_entries = 3;
+    let set = |key: &str, value: &[u8]| DataOperation::Set {
+        key: key.to_string(),
+        value: value.to_vec(),
+    };
+
+    // longest prefix decides.
+    assert_eq!(
+        store.handle(1, set("match/state", b"a")).status,
+        DataStatus::AccessDenied
+    );
+    assert_eq!(
+        store.handle(1, set("match/public/x", b"a")).status,
+        DataStatus::Ok
+    );
+    store
+        .set("secret/seed".to_string(), b"42".to_vec())
+        .unwrap();
+    let get_secret = DataOperation::Get {
+        key: "secret/seed".to_string(),
+    };
+    assert_eq!(store.handle(1, get_secret).status, DataStatus::AccessDenied);
+    assert_eq!(store.get("secret/seed"), Some(b"42".to_vec()));
+
+    // only creator writes its key, until it leaves.
+    assert_eq!(
+        store.handle(1, set("player/1", b"a")).status,
+        DataStatus::Ok
+    );
+    assert_eq!(
+        store.handle(2, set("player/1", b"b")).status,
+        DataStatus::AccessDenied
+    );
+    store.release_owner(1);
+    assert_eq!(
+        store.handle(2, set("player/1", b"b")).status,
+        DataStatus::Ok
+    );
+
+    assert_eq!(
+        store.handle(1, set("value", b"12345")).status,
+        DataStatus::TooLarge
+    );
+    assert_eq!(
+        store.handle(1, set("value", b"1")).status,
+        DataStatus::StoreFull
+    );
+    assert_eq!(
+        store.handle(1, set("", b"1")).status,
+        DataStatus::InvalidRequest
+    );
+}
+
+#[test]
+fn test_clients_share_data_through_server() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    server.set_data_access("rules/".to_string(), DataAccess::ReadOnly);
+    server
+        .set_data("rules/rounds".to_string(), vec![3])
+        .unwrap();
+
+    let mut first = create_client(server_addr.clone());
+    // only players can use data store, others are not answered.
+    assert!(matches!(
+        first.get_data("rules/rounds", Duration::from_millis(300)),
+        Err(DataError::Request(RequestError::Timeout))
+    ));
+    enter_player(&first, "First");
+    let mut second = create_client(server_addr);
+    enter_player(&second, "Second");
+
+    assert_eq!(
+        first.get_data("rules/rounds", TIMEOUT).unwrap(),
+        Some(vec![3])
+    );
+    assert!(matches!(
+        first.set_data("rules/rounds", &[5], TIMEOUT),
+        Err(DataError::AccessDenied)
+    ));
+    assert_eq!(first.get_data("score", TIMEOUT).unwrap(), None);
+
+    first.compare_and_set("score", None, &[1], TIMEOUT).unwrap();
+    // second player has stale view of the value.
+    match second.compare_and_set("score", None, &[2], TIMEOUT) {
+        Err(DataError::CompareFailed(current)) => assert_eq!(current, Some(vec![1])),
+        result => panic!("unexpected result: {:?}", result),
+    }
+    second
+        .compare_and_set("score", Some(&[1]), &[2], TIMEOUT)
+        .unwrap();
+    assert_eq!(first.get_data("score", TIMEOUT).unwrap(), Some(vec![2]));
+    assert_eq!(server.get_data("score"), Some(vec![2]));
+
+    // default limit is 1024 bytes.
+    assert!(matches!(
+        second.set_data("large", &[0; 2000], TIMEOUT),
+        Err(DataError::TooLarge)
+    ));
+    server.set_data_limits(2048, 1024);
+    // value larger than one datagram is fragmented.
+    second.set_data("large", &[0; 2000], TIMEOUT).unwrap();
+    assert_eq!(
+        first.get_data("large", TIMEOUT).unwrap(),
+        Some(vec![0; 2000])
+    );
+
+    assert!(first.delete_data("score", TIMEOUT).unwrap());
+    assert!(!first.delete_data("score", TIMEOUT).unwrap());
+
+    // raw response through the request handle.
+    let request = first
+        .send_data_request(&DataOperation::Get {
+            key: "large".to_string(),
+        })
+        .unwrap();
+    match request.wait(TIMEOUT) {
+        Ok(Response::Data(response)) => {
+            assert_eq!(
+                response,
+                DataResponse::new(DataStatus::Ok, Some(vec![0; 2000]))
+            )
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    first.die();
+    second.die();
+    server.die();
+}
@@ -0,0 +1,86 @@
+use crate::requests::delivery::{DeliveryMode, ReceiveChannels};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_unreliable_sequenced_drops_stale_data() {
+    let mut channels = ReceiveChannels::<u16>::new();
+    let mode = DeliveryMode::UnreliableSequenced;
+
+    assert_eq!(channels.receive(mode, 10, 10), vec![10]);
+    assert_eq!(channels.receive(mode, 12, 12), vec![12]);
+    // older than latest released, dropped.
+    assert_eq!(channels.receive(mode, 11, 11), Vec::<u16>::new());
+    // sequence is not wrapped at u8 range.
+    assert_eq!(channels.receive(mode, 256, 256), vec![256]);
+    assert_eq!(channels.receive(mode, 255, 255), Vec::<u16>::new());
+    // sequence wraps around.
+    assert_eq!(channels.receive(mode, 30000, 30000), vec![30000]);
+    assert_eq!(channels.receive(mode, 55000, 55000), vec![55000]);
+    assert_eq!(channels.receive(mode, 65535, 65535), vec![65535]);
+    assert_eq!(channels.receive(mode, 0, 0), vec![0]);
+}
+
+#[test]
+fn test_reliable_ordered_buffers_until_sequence_is_complete() {
+    let mut channels = ReceiveChannels::<u16>::new();
+    let mode = DeliveryMode::ReliableOrdered;
+
+    for sequence in 0..65534 {
+        assert_eq!(channels.receive(mode, sequence, sequence), vec![sequence]);
+    }
+    assert_eq!(channels.receive(mode, 65534, 65534), vec![65534]);
+    assert_eq!(channels.receive(mode, 0, 0), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 1, 1), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 65535, 65535), vec![65535, 0, 1]);
+    // resent message is passed through, since sender waits for an answer.
+    assert_eq!(channels.receive(mode, 0, 0), vec![0]);
+}
+
+#[test]
+fn test_reliable_ordered_waits_for_lost_first_message() {
+    let mut channels = ReceiveChannels::<u16>::new();
+    let mode = DeliveryMode::ReliableOrdered;
+
+    // first message is lost, later ones wait for its resend.
+    assert_eq!(channels.receive(mode, 1, 1), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 2, 2), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 0, 0), vec![0, 1, 2]);
+}
+
+#[test]
+fn test_reliable_ordered_skips_gap_after_timeout() {
+    let mut channels = ReceiveChannels::<u16>::new();
+    let mode = DeliveryMode::ReliableOrdered;
+
+    assert_eq!(channels.receive(mode, 0, 0), vec![0]);
+    assert_eq!(channels.receive(mode, 2, 2), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 3, 3), Vec::<u16>::new());
+    assert_eq!(channels.receive(mode, 5, 5), Vec::<u16>::new());
+    assert_eq!(channels.release_late(Instant::now()), Vec::<u16>::new());
+
+    // missing sequence 1 is given up, and later gap waits its own timeout.
+    let now = Instant::now() + Duration::from_secs(5);
+    assert_eq!(channels.release_late(now), vec![2, 3]);
+    assert_eq!(channels.release_late(now), Vec::<u16>::new());
+    assert_eq!(channels.release_late(now + Duration::from_secs(5)), vec![5]);
+    assert_eq!(channels.receive(mode, 6, 6), vec![6]);
+}
+
+#[test]
+fn test_modes_have_own_sequence_spaces() {
+    let mut channels = ReceiveChannels::<u16>::new();
+
+    assert_eq!(
+        channels.receive(DeliveryMode::ReliableOrdered, 0, 0),
+        vec![0]
+    );
+    assert_eq!(
+        channels.receive(DeliveryMode::ReliableOrdered, 2, 2),
+        Vec::<u16>::new()
+    );
+    assert_eq!(
+        channels.receive(DeliveryMode::ReliableUnordered, 5, 5),
+        vec![5]
+    );
+    assert_eq!(channels.receive(DeliveryMode::Unreliable, 1, 1), vec![1]);
+}
@@ -0,0 +1,107 @@
+use crate::client::client::Client;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::header::Header;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::server::server::Server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use crate::tests::common::pause;
+use crate::tests::common::rawclient::{create_player_enter_request, request_challenge_cookie};
+use std::net::UdpSocket;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_client_with_heartbeat(server_addr: String) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client.set_keepalive_interval(Duration::from_millis(50));
+    client.set_connection_timeout(Duration::from_millis(300));
+    client
+}
+
+// Server of its own, since global test server must keep default timeout.
+// Port is chosen by the system, so that port of closed global test server is not reused.
+fn create_server() -> Server {
+    server::run("localhost".to_string(), "0".to_string(), 3).unwrap()
+}
+
+#[test]
+fn test_client_is_disconnected_when_server_is_silent() {
+    // socket that never answers.
+    let silent_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = silent_server.local_addr().unwrap().to_string();
+
+    let events = create_events();
+    let mut client = create_client_with_heartbeat(server_addr);
+    client::run(&mut client, Arc::clone(&events));
+
+    for _i in 0..40 {
+        if events.lock().unwrap().is_disconnected {
+            break;
+        }
+        pause(50);
+    }
+
+    // heartbeats were sent, even though nothing was requested.
+    let mut buf = [0; 64];
+    silent_server
+        .set_read_timeout(Some(Duration::from_secs(1)))
+        .unwrap();
+    let number_of_bytes = silent_server.recv(&mut buf).unwrap();
+    let header = Header::from_raw_data(&buf[..number_of_bytes]).unwrap();
+
+    assert!(events.lock().unwrap().is_disconnected);
+    assert_eq!(header.get_client_job(), ClientJob::HeartbeatRequest);
+}
+
+#[test]
+fn test_silent_player_is_removed_and_others_are_informed() {
+    let mut server = create_server();
+    server.set_connection_timeout(Duration::from_millis(300));
+    // session of the silent player is not held, player leaves at once.
+    server.set_session_grace_period(Duration::from_millis(0));
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let events = create_events();
+    let mut client = create_client_with_heartbeat(server_addr.clone());
+    client::run(&mut client, Arc::clone(&events));
+    let protocols = Protocol::new();
+    let _result = client.send_request(
+        ClientJob::PlayerEnterRequest,
+        &mut datahelpers::create_player_request(&protocols, "Heartbeating".to_string()).unwrap(),
+    );
+    while events.lock().unwrap().player.is_none() {
+        pause(50);
+    }
+
+    // player enters and goes silent.
+    let silent_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    silent_socket.connect(server_addr).unwrap();
+    silent_socket
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .unwrap();
+    let cookie = request_challenge_cookie(&silent_socket, "Silent");
+    silent_socket
+        .send(&create_player_enter_request(1, "Silent", &cookie))
+        .unwrap();
+
+    for _i in 0..40 {
+        if !events.lock().unwrap().left_players.is_empty() {
+            break;
+        }
+        pause(50);
+    }
+
+    let events = events.lock().unwrap();
+    let player_number = events.player.as_ref().unwrap().player_number;
+    // heartbeating player stays, silent one leaves.
+    assert_eq!(events.left_players.len(), 1);
+    assert_ne!(events.left_players[0].player_number, player_number);
+    assert!(!events.is_disconnected);
+}
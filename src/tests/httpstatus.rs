@@ -0,0 +1,81 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+use crate::client;
+
+fn get(addr: SocketAddr, method: &str, path: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .unwrap();
+    write!(
+        stream,
+        "{} {} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        method, path
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn test_status_endpoint_serves_health_status_and_metrics() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    let status_addr = server.run_status_http("127.0.0.1:0".to_string()).unwrap();
+
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, "Status \"quoted\"".to_string())
+                .unwrap(),
+        )
+        .unwrap();
+    let player_number = match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    };
+
+    let health = get(status_addr, "GET", "/healthz");
+    assert!(health.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(health.ends_with("\r\n\r\nok\n"));
+
+    let status = get(status_addr, "GET", "/status");
+    assert!(status.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(status.contains("Content-Type: application/json\r\n"));
+    assert!(status.contains("\"players\":1,"));
+    assert!(status.contains(&format!("\"player_number\":{},", player_number)));
+    assert!(status.contains("\"player_name\":\"Status \\\"quoted\\\"\""));
+
+    let metrics = get(status_addr, "GET", "/metrics");
+    assert!(metrics.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(metrics.contains("# TYPE gum_packets_received_total counter\n"));
+    assert!(metrics.contains("\ngum_players 1\n"));
+    assert!(metrics.contains("\ngum_packets_failed_total 0\n"));
+    assert!(metrics.contains(&format!(
+        "\ngum_connection_jitter_seconds{{player=\"{}\",name=\"Status \\\"quoted\\\"\"}} ",
+        player_number
+    )));
+
+    assert!(get(status_addr, "GET", "/unknown").starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(get(status_addr, "POST", "/status").starts_with("HTTP/1.1 405 Method Not Allowed\r\n"));
+
+    client.die();
+    server.die();
+}
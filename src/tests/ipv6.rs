@@ -0,0 +1,182 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_client(
+    local_addr: &str,
+    server_addr: SocketAddr,
+    events: Arc<Mutex<RecordingEvents>>,
+) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect(local_addr.to_string(), server_addr.to_string())
+        .unwrap();
+    client::run(&mut client, events);
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> PlayerCreatedResponseData {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_addresses_of_both_families_are_carried() {
+    let addrs: Vec<SocketAddr> = vec![
+        "127.0.0.1:11111".parse().unwrap(),
+        "[::1]:11111".parse().unwrap(),
+        "[2001:db8::ff00:42:8329]:65535".parse().unwrap(),
+    ];
+    for addr in addrs {
+        let (ip, port) = datahelpers::get_ip_and_port_from_socket_addr(addr);
+        assert_eq!(
+            datahelpers::create_addr_from_ip_and_port(ip.to_vec(), port),
+            Ok(addr)
+        );
+    }
+}
+
+#[test]
+fn test_ipv4_and_ipv6_players_see_each_other() {
+    let mut server =
+        server::run_on_addrs(vec!["127.0.0.1:0".to_string(), "[::1]:0".to_string()], 3).unwrap();
+    let server_addrs = server.get_local_addrs();
+    let server_ipv4_addr = *server_addrs.iter().find(|addr| addr.is_ipv4()).unwrap();
+    let server_ipv6_addr = *server_addrs.iter().find(|addr| addr.is_ipv6()).unwrap();
+
+    let ipv6_events = create_events();
+    let mut ipv6_client = create_client("[::1]:0", server_ipv6_addr, Arc::clone(&ipv6_events));
+    enter_player(&ipv6_client, "Six");
+
+    let ipv4_events = create_events();
+    let mut ipv4_client = create_client("127.0.0.1:0", server_ipv4_addr, ipv4_events);
+    let response_data = enter_player(&ipv4_client, "Four");
+
+    // IPv6 address of the other player is in player created response.
+    let other_player = response_data
+        .others_players
+        .iter()
+        .find(|player| player.player_name == "Six")
+        .unwrap();
+    assert!(other_player.addr.unwrap().ip().is_loopback());
+    assert!(other_player.addr.unwrap().is_ipv6());
+
+    // IPv4 address of the new player is pushed over IPv6 socket.
+    let mut entered_addr = None;
+    for _i in 0..40 {
+        entered_addr = ipv6_events
+            .lock()
+            .unwrap()
+            .other_players
+            .iter()
+            .find(|player| player.player_name == "Four")
+            .and_then(|player| player.addr);
+        if entered_addr.is_some() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(entered_addr.unwrap().ip().to_string(), "127.0.0.1");
+
+    ipv4_client.die();
+    ipv6_client.die();
+    server.die();
+}
+
+#[test]
+fn test_dual_stack_socket_serves_both_families() {
+    let mut server = server::run_on_addrs(vec!["[::]:0".to_string()], 3).unwrap();
+    let port = server.get_port().unwrap();
+    let server_ipv4_addr: SocketAddr = format!("127.0.0.1:{}", port).parse().unwrap();
+    let server_ipv6_addr: SocketAddr = format!("[::1]:{}", port).parse().unwrap();
+
+    let ipv6_events = create_events();
+    let mut ipv6_client = create_client("[::1]:0", server_ipv6_addr, Arc::clone(&ipv6_events));
+    enter_player(&ipv6_client, "Six");
+
+    let ipv4_events = create_events();
+    let mut ipv4_client = create_client("127.0.0.1:0", server_ipv4_addr, ipv4_events);
+    let response_data = enter_player(&ipv4_client, "Four");
+    assert!(response_data
+        .others_players
+        .iter()
+        .any(|player| player.player_name == "Six"));
+
+    // IPv4-mapped address of the new player is pushed as IPv4 address.
+    let mut entered_addr = None;
+    for _i in 0..40 {
+        entered_addr = ipv6_events
+            .lock()
+            .unwrap()
+            .other_players
+            .iter()
+            .find(|player| player.player_name == "Four")
+            .and_then(|player| player.addr);
+        if entered_addr.is_some() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(entered_addr.unwrap().ip().to_string(), "127.0.0.1");
+
+    ipv4_client.die();
+    ipv6_client.die();
+    server.die();
+}
+
+#[test]
+fn test_players_are_answered_from_the_socket_they_sent_to() {
+    let mut server = server::run_on_addrs(
+        vec!["127.0.0.1:0".to_string(), "127.0.0.1:0".to_string()],
+        3,
+    )
+    .unwrap();
+    let server_addrs = server.get_local_addrs();
+
+    let mut first_client = create_client("127.0.0.1:0", server_addrs[0], create_events());
+    enter_player(&first_client, "First");
+    // second client accepts only datagrams from the second socket.
+    let second_events = create_events();
+    let mut second_client =
+        create_client("127.0.0.1:0", server_addrs[1], Arc::clone(&second_events));
+    enter_player(&second_client, "Second");
+
+    first_client
+        .send_request(ClientJob::DataPushRequest, &mut b"hello".to_vec())
+        .unwrap();
+    let mut push = None;
+    for _i in 0..40 {
+        push = second_events.lock().unwrap().pushes.pop();
+        if push.is_some() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(push, Some((1, b"hello".to_vec())));
+
+    first_client.die();
+    second_client.die();
+    server.die();
+}
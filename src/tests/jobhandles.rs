@@ -0,0 +1,50 @@
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::jobs::Jobs;
+use crate::requests::{ClientJob, Job, ServerJob};
+
+fn create_job(handle: u16) -> Job {
+    let header = Header::new(
+        handle,
+        (ServerJob::NoServerAction, ClientJob::PingRequest),
+        DeliveryMode::ReliableUnordered,
+        0,
+    );
+    Job::new(header, &mut vec![])
+}
+
+#[test]
+fn test_job_handles_are_not_reused_while_in_flight() {
+    let jobs = Jobs::new();
+
+    // job in flight with handle 1 is skipped.
+    jobs.insert_job(1, create_job(1));
+    assert_eq!(jobs.get_next_job_handle(), 0);
+    assert_eq!(jobs.get_next_job_handle(), 2);
+
+    // handles are not wrapped at u8 range.
+    for _i in 3..300 {
+        let handle = jobs.get_next_job_handle();
+        jobs.insert_job(handle, create_job(handle));
+    }
+    assert_eq!(jobs.get_next_job_handle(), 300);
+}
+
+#[test]
+fn test_header_holds_16_bit_handle_and_sequence() {
+    let header = Header::new(
+        0xABCD,
+        (ServerJob::DataPush, ClientJob::DataPushRequest),
+        DeliveryMode::UnreliableSequenced,
+        0x1234,
+    );
+    let parsed = Header::from_raw_data(&header.get_raw_data()).unwrap();
+
+    assert_eq!(parsed.handle, 0xABCD);
+    assert_eq!(parsed.sequence, 0x1234);
+    assert_eq!(parsed.delivery_mode, DeliveryMode::UnreliableSequenced);
+    assert_eq!(
+        parsed.job_type,
+        (ServerJob::DataPush, ClientJob::DataPushRequest)
+    );
+}
@@ -0,0 +1,140 @@
+use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
+use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
+use crate::client::datahandlers::structs::player::PlayerData;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
+use crate::client::RequestEvents;
+use crate::protocol::Protocol;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::fragmentation::{create_datagrams, Reassembler, MAX_DATAGRAM_SIZE};
+use crate::requests::header::Header;
+use crate::tests::common::init_test_environment;
+use crate::tests::common::pause;
+use crate::tests::common::testclient::create_client;
+use crate::tests::common::testcounter::add_finished_count;
+use std::time::Duration;
+
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use crate::client;
+use crate::protocol::datahelpers;
+use crate::requests::{ClientJob, ServerJob};
+
+struct Eventful {
+    pub player: Option<PlayerData>,
+    pub received_data: Option<Vec<u8>>,
+}
+
+impl RequestEvents for Eventful {
+    fn on_data_push_action(&mut self, _raw_data: Vec<u8>) {}
+    fn on_pong(&mut self, _time: Duration) {}
+    fn on_data_push_received(&mut self, _player: u8, raw_data: Vec<u8>) {
+        self.received_data = Some(raw_data);
+    }
+    fn on_data_request(&mut self, _: std::vec::Vec<u8>) {}
+    fn on_player_enter_push(&mut self, _player: PlayerData) {}
+    fn on_player_created(
+        &mut self,
+        player_created_response_data_result: Result<
+            PlayerCreatedResponseData,
+            PlayerCreatedServerError,
+        >,
+    ) {
+        if let Ok(player_created_response_data) = player_created_response_data_result {
+            self.player = Some(player_created_response_data.player);
+        }
+    }
+    fn on_player_leave(&mut self, _player: PlayerLeaveData) {}
+    fn on_error(&mut self) {}
+    fn on_connection_state_change(&mut self, _error_state: bool) {}
+}
+
+fn create_large_data() -> Vec<u8> {
+    (0..5000).map(|index| (index % 251) as u8).collect()
+}
+
+#[test]
+fn test_fragments_are_reassembled_in_any_order() {
+    let header = Header::new(
+        7,
+        (ServerJob::DataPush, ClientJob::DataPushRequest),
+        DeliveryMode::ReliableOrdered,
+        3,
+    );
+    let mut raw_data = header.get_raw_data();
+    raw_data.append(&mut create_large_data());
+
+    let mut datagrams = create_datagrams(&raw_data);
+    assert!(datagrams.len() > 1);
+    assert!(datagrams
+        .iter()
+        .all(|datagram| datagram.len() <= MAX_DATAGRAM_SIZE));
+
+    // last fragment first, and one fragment twice.
+    datagrams.reverse();
+    let duplicate = datagrams[1].clone();
+    datagrams.insert(2, duplicate);
+
+    let mut reassembler = Reassembler::<u8>::new();
+    let mut reassembled = None;
+    for datagram in datagrams {
+        let fragment_header = Header::from_raw_data(&datagram).unwrap();
+        assert!(fragment_header.is_fragment());
+        if let Some(message) = reassembler.receive(1, &fragment_header, &datagram) {
+            assert!(reassembled.is_none());
+            reassembled = Some(message);
+        }
+    }
+
+    assert_eq!(reassembled.unwrap(), raw_data);
+}
+
+#[test]
+fn test_large_data_push_is_received_by_other_player() {
+    init_test_environment();
+
+    let sender_events = Arc::new(Mutex::new(Eventful {
+        player: None,
+        received_data: None,
+    }));
+    let receiver_events = Arc::new(Mutex::new(Eventful {
+        player: None,
+        received_data: None,
+    }));
+    let protocols = Protocol::new();
+    let mut sender = create_client();
+    let mut receiver = create_client();
+    client::run(&mut sender, Arc::clone(&sender_events));
+    client::run(&mut receiver, Arc::clone(&receiver_events));
+
+    let _result = sender.send_request(
+        ClientJob::PlayerEnterRequest,
+        &mut datahelpers::create_player_request(&protocols, "Large sender".to_string()).unwrap(),
+    );
+    let _result = receiver.send_request(
+        ClientJob::PlayerEnterRequest,
+        &mut datahelpers::create_player_request(&protocols, "Large receiver".to_string()).unwrap(),
+    );
+
+    while sender_events.lock().unwrap().player.is_none()
+        || receiver_events.lock().unwrap().player.is_none()
+    {
+        pause(100);
+    }
+
+    let result = sender.send_request(ClientJob::DataPushRequest, &mut create_large_data());
+
+    let mut received_data = None;
+    for _i in 1..50 {
+        pause(100);
+        received_data = receiver_events.lock().unwrap().received_data.take();
+        if received_data.is_some() {
+            break;
+        }
+    }
+
+    add_finished_count();
+
+    assert!(result.is_ok());
+    assert_eq!(received_data, Some(create_large_data()));
+}
@@ -0,0 +1,116 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::ClientJob;
+use crate::server::server::Server;
+use crate::socket::memory::MemoryNetwork;
+use crate::socket::Transport;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_client(
+    network: &MemoryNetwork,
+    server_addr: SocketAddr,
+    events: Arc<Mutex<RecordingEvents>>,
+) -> Client {
+    let transport = network.bind("10.0.0.2:0".parse().unwrap()).unwrap();
+    let mut client = Client::new(3);
+    client
+        .connect_with_transport(Arc::new(transport), server_addr)
+        .unwrap();
+    client::run(&mut client, events);
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> u8 {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_memory_transport_delivers_between_bound_addresses() {
+    let network = MemoryNetwork::new();
+    let first = network.bind("10.0.0.1:0".parse().unwrap()).unwrap();
+    let second = network.bind("10.0.0.1:0".parse().unwrap()).unwrap();
+    let second_addr = second.local_addr().unwrap();
+    assert_ne!(first.local_addr().unwrap(), second_addr);
+    assert_eq!(
+        network.bind(second_addr).err().map(|error| error.kind()),
+        Some(ErrorKind::AddrInUse)
+    );
+
+    first.send_to(b"hello", second_addr).unwrap();
+    let mut buffer = [0; 16];
+    let (number_of_bytes, src_addr) = second.recv_from(&mut buffer).unwrap();
+    assert_eq!(&buffer[..number_of_bytes], b"hello");
+    assert_eq!(src_addr, first.local_addr().unwrap());
+
+    // nothing more to receive, read timeout ends the wait.
+    second
+        .set_read_timeout(Some(Duration::from_millis(10)))
+        .unwrap();
+    assert_eq!(
+        second
+            .recv_from(&mut buffer)
+            .err()
+            .map(|error| error.kind()),
+        Some(ErrorKind::WouldBlock)
+    );
+
+    // address is free again after shutdown.
+    second.shutdown();
+    assert!(network.bind(second_addr).is_ok());
+}
+
+#[test]
+fn test_players_play_over_memory_network() {
+    let network = MemoryNetwork::new();
+    let server_transport = network.bind("10.0.0.1:11111".parse().unwrap()).unwrap();
+    let server_addr = server_transport.local_addr().unwrap();
+    let mut server = Server::new(3);
+    server
+        .connect_with_transport(Arc::new(server_transport))
+        .unwrap();
+    server.run();
+
+    let first_events = create_events();
+    let mut first = create_client(&network, server_addr, Arc::clone(&first_events));
+    let first_number = enter_player(&first, "First");
+    let second_events = create_events();
+    let mut second = create_client(&network, server_addr, Arc::clone(&second_events));
+    enter_player(&second, "Second");
+
+    first
+        .send_request(ClientJob::DataPushRequest, &mut b"in memory".to_vec())
+        .unwrap();
+    let mut received_push = None;
+    for _i in 0..60 {
+        received_push = second_events.lock().unwrap().pushes.pop();
+        if received_push.is_some() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(received_push, Some((first_number, b"in memory".to_vec())));
+
+    first.die();
+    second.die();
+    server.die();
+}
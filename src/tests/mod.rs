@@ -1,6 +1,30 @@
+mod abandonedrequest;
+mod asyncrequest;
+mod batching;
+mod challenge;
 mod closeserver;
 mod common;
+mod congestioncontrol;
+mod connectionstats;
 pub mod createplayerrequest;
+mod datastore;
+mod deliverymodes;
+mod heartbeat;
+mod httpstatus;
+mod ipv6;
+mod jobhandles;
+mod largedatapush;
+mod memorytransport;
 pub mod otherplayernames;
+mod p2p;
+mod playerleave;
+mod reliablepush;
+mod requesthandle;
+mod resentrequest;
+mod rooms;
+mod rttestimator;
+mod session;
+mod shutdown;
+mod targetedpush;
 
-pub static TESTCOUNT: usize = 2;
+pub static TESTCOUNT: usize = 4;
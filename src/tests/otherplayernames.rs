@@ -1,6 +1,7 @@
 use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
 use crate::client::datahandlers::structs::player::PlayerCreatedResponseData;
 use crate::client::datahandlers::structs::player::PlayerData;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
 use crate::client::RequestEvents;
 use crate::protocol::Protocol;
 use crate::tests::common::init_test_environment;
@@ -63,7 +64,7 @@ impl RequestEvents for Eventful {
         }
     }
 
-    fn on_player_leave(&mut self, _: std::vec::Vec<u8>) {
+    fn on_player_leave(&mut self, _player: PlayerLeaveData) {
         todo!()
     }
     fn on_error(&mut self) {
@@ -148,29 +149,32 @@ pub fn test_create_player_request_other_player_names() {
         }
     }
 
-    let other_players = &(*events1).lock().unwrap().other_players;
+    // Events must be released, since listener thread uses them until client dies.
+    let events_changer = (*events1).lock().unwrap();
+    let other_players = &events_changer.other_players;
 
     let has_names_for_events1 = check_other_player_names(other_players, "Tester 1".to_string())
         && check_other_player_names(other_players, "Tester 2".to_string())
         && check_other_player_names(other_players, "Tester 3".to_string());
-    drop(other_players);
+    drop(events_changer);
 
-    let other_players = &(*events2).lock().unwrap().other_players;
+    let events_changer = (*events2).lock().unwrap();
+    let other_players = &events_changer.other_players;
     let has_names_for_events2 = check_other_player_names(other_players, "Tester 1".to_string())
         && check_other_player_names(other_players, "Tester 2".to_string())
         && check_other_player_names(other_players, "Tester 3".to_string());
-    drop(other_players);
+    drop(events_changer);
 
-    let other_players = &(*events3).lock().unwrap().other_players;
+    let events_changer = (*events3).lock().unwrap();
+    let other_players = &events_changer.other_players;
     let has_names_for_events3 = check_other_player_names(other_players, "Tester 1".to_string())
         && check_other_player_names(other_players, "Tester 2".to_string())
         && check_other_player_names(other_players, "Tester 3".to_string());
-    drop(other_players);
+    drop(events_changer);
 
-    // does not work.
-    //client1.die();
-    //client2.die();
-    //client3.die();
+    client1.die();
+    client2.die();
+    client3.die();
 
     add_finished_count();
 
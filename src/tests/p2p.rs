@@ -0,0 +1,151 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{Response, TargetedPushError};
+use crate::client::peers::PeerPath;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_client(
+    server_addr: String,
+    p2p_enabled: bool,
+    events: Arc<Mutex<RecordingEvents>>,
+) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client.set_p2p_enabled(p2p_enabled);
+    client::run(&mut client, events);
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> u8 {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+fn wait_for_path(client: &Client, player_number: u8, path: PeerPath) {
+    for _i in 0..60 {
+        if client.get_peer_path(player_number) == Some(path) {
+            return;
+        }
+        pause(50);
+    }
+    panic!(
+        "path to player {} is {:?}, not {:?}",
+        player_number,
+        client.get_peer_path(player_number),
+        path
+    );
+}
+
+fn wait_for_push(events: &Arc<Mutex<RecordingEvents>>) -> (u8, Vec<u8>) {
+    for _i in 0..60 {
+        if let Some(push) = events.lock().unwrap().pushes.pop() {
+            return push;
+        }
+        pause(50);
+    }
+    panic!("push not received");
+}
+
+#[test]
+fn test_peers_push_data_directly() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let first_events = create_events();
+    let mut first = create_client(server_addr.clone(), true, Arc::clone(&first_events));
+    let first_number = enter_player(&first, "First");
+    let second_events = create_events();
+    let mut second = create_client(server_addr, true, Arc::clone(&second_events));
+    let second_number = enter_player(&second, "Second");
+
+    // first learns address of second from enter push, second of first from response.
+    wait_for_path(&first, second_number, PeerPath::Direct);
+    wait_for_path(&second, first_number, PeerPath::Direct);
+
+    // server is closed, so that data can not be relayed.
+    server.die();
+    let (path, request) = first.send_peer_data_push(second_number, b"direct").unwrap();
+    assert_eq!(path, PeerPath::Direct);
+    assert!(request.is_none());
+    assert_eq!(
+        wait_for_push(&second_events),
+        (first_number, b"direct".to_vec())
+    );
+    assert!(first_events
+        .lock()
+        .unwrap()
+        .path_changes
+        .contains(&(second_number, PeerPath::Direct)));
+
+    first.die();
+    second.die();
+}
+
+#[test]
+fn test_unreachable_peer_falls_back_to_relay() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    // second does not answer punches, as if it was behind a strict NAT.
+    let first_events = create_events();
+    let mut first = create_client(server_addr.clone(), true, Arc::clone(&first_events));
+    let first_number = enter_player(&first, "Punching");
+    let second_events = create_events();
+    let mut second = create_client(server_addr, false, Arc::clone(&second_events));
+    let second_number = enter_player(&second, "Strict");
+
+    wait_for_path(&first, second_number, PeerPath::Relay);
+    assert!(first_events
+        .lock()
+        .unwrap()
+        .path_changes
+        .contains(&(second_number, PeerPath::Relay)));
+
+    let (path, request) = first
+        .send_peer_data_push(second_number, b"relayed")
+        .unwrap();
+    assert_eq!(path, PeerPath::Relay);
+    assert!(matches!(
+        request.unwrap().wait(Duration::from_secs(3)),
+        Ok(Response::TargetedPushDone(Ok(())))
+    ));
+    assert_eq!(
+        wait_for_push(&second_events),
+        (first_number, b"relayed".to_vec())
+    );
+    // relay to a player, who does not exist, is rejected.
+    let (_path, request) = first.send_peer_data_push(99, b"relayed").unwrap();
+    match request.unwrap().wait(Duration::from_secs(3)) {
+        Ok(Response::TargetedPushDone(result)) => {
+            assert_eq!(result, Err(TargetedPushError::UnknownTargets(vec![99])))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+    // second has no peers, its data is relayed too.
+    assert_eq!(second.get_peer_path(first_number), None);
+
+    first.die();
+    second.die();
+    server.die();
+}
@@ -0,0 +1,120 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::player::PlayerLeaveData;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::leave::LeaveReason;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+fn create_client(server_addr: String, events: Arc<Mutex<RecordingEvents>>) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, events);
+    client
+}
+
+fn enter_player(client: &Client, name: &str) -> Result<u8, String> {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => Ok(response_data.player.player_number),
+        result => Err(format!("{:?}", result)),
+    }
+}
+
+fn wait_for_leave(events: &Arc<Mutex<RecordingEvents>>) -> PlayerLeaveData {
+    for _i in 0..60 {
+        if let Some(player) = events.lock().unwrap().left_players.pop() {
+            return player;
+        }
+        pause(50);
+    }
+    panic!("player leave not received");
+}
+
+#[test]
+fn test_left_player_is_removed_and_others_are_told() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let leaver_events = create_events();
+    let mut leaver = create_client(server_addr.clone(), Arc::clone(&leaver_events));
+    let leaver_number = enter_player(&leaver, "Leaver").unwrap();
+    let stayer_events = create_events();
+    let mut stayer = create_client(server_addr.clone(), Arc::clone(&stayer_events));
+    enter_player(&stayer, "Stayer").unwrap();
+
+    let response = leaver.leave().unwrap().wait(Duration::from_secs(3));
+    assert!(matches!(response, Ok(Response::PlayerLeave(Some(number))) if number == leaver_number));
+    assert_eq!(
+        wait_for_leave(&leaver_events),
+        PlayerLeaveData {
+            player_number: leaver_number,
+            reason: LeaveReason::Quit,
+        }
+    );
+    assert_eq!(
+        wait_for_leave(&stayer_events),
+        PlayerLeaveData {
+            player_number: leaver_number,
+            reason: LeaveReason::Quit,
+        }
+    );
+    assert!(server.connection_stats(leaver_number).is_none());
+
+    // player number and name are free again.
+    let mut newcomer = create_client(server_addr, create_events());
+    assert_eq!(enter_player(&newcomer, "Leaver"), Ok(leaver_number));
+
+    // leave of a client, which is not a player, is answered without player number.
+    let response = leaver.leave().unwrap().wait(Duration::from_secs(3));
+    assert!(matches!(response, Ok(Response::PlayerLeave(None))));
+
+    leaver.die();
+    stayer.die();
+    newcomer.die();
+    server.die();
+}
+
+#[test]
+fn test_kicked_player_and_others_are_told() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let kicked_events = create_events();
+    let mut kicked = create_client(server_addr.clone(), Arc::clone(&kicked_events));
+    let kicked_number = enter_player(&kicked, "Kicked").unwrap();
+    let stayer_events = create_events();
+    let mut stayer = create_client(server_addr, Arc::clone(&stayer_events));
+    enter_player(&stayer, "Stayer").unwrap();
+
+    assert!(server.kick(kicked_number));
+    let expected = PlayerLeaveData {
+        player_number: kicked_number,
+        reason: LeaveReason::Kicked,
+    };
+    assert_eq!(wait_for_leave(&kicked_events), expected);
+    assert_eq!(wait_for_leave(&stayer_events), expected);
+    assert!(server.connection_stats(kicked_number).is_none());
+    assert!(!server.kick(kicked_number));
+
+    kicked.die();
+    stayer.die();
+    server.die();
+}
@@ -0,0 +1,208 @@
+use crate::client;
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::batching::split_batch;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::header::Header;
+use crate::requests::{ClientJob, ServerJob};
+use crate::server;
+use crate::server::server::Server;
+use crate::socket::memory::{MemoryNetwork, MemoryTransport};
+use crate::socket::{SocketCombatible, Transport};
+use crate::tests::common::events::create_events;
+use crate::tests::common::pause;
+use crate::tests::common::rawclient::{create_player_enter_request, request_challenge_cookie};
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+// Transport of a client, which loses the first player enter push, until the push after
+// it has been received. Resends of the first push arrive only after the later push.
+struct LosingTransport {
+    transport: MemoryTransport,
+    is_later_push_received: AtomicBool,
+}
+
+impl LosingTransport {
+    fn is_lost(&self, raw_data: &[u8]) -> bool {
+        let mut is_lost = false;
+        for datagram in split_batch(raw_data.to_vec()) {
+            match Header::from_raw_data(&datagram) {
+                Some(header) if header.get_server_job() == ServerJob::PlayerEnterPush => {
+                    if header.sequence == 0 {
+                        is_lost = !self.is_later_push_received.load(Ordering::SeqCst);
+                    } else {
+                        self.is_later_push_received.store(true, Ordering::SeqCst);
+                    }
+                }
+                _ => {}
+            }
+        }
+        is_lost
+    }
+}
+
+impl Transport for LosingTransport {
+    fn send_to(&self, buf: &[u8], addr: SocketAddr) -> io::Result<usize> {
+        self.transport.send_to(buf, addr)
+    }
+
+    fn recv_from(&self, buf: &mut [u8]) -> io::Result<(usize, SocketAddr)> {
+        loop {
+            let (number_of_bytes, src_addr) = self.transport.recv_from(buf)?;
+            if !self.is_lost(&buf[..number_of_bytes]) {
+                return Ok((number_of_bytes, src_addr));
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.transport.local_addr()
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        self.transport.set_read_timeout(timeout)
+    }
+
+    fn shutdown(&self) {
+        self.transport.shutdown()
+    }
+}
+
+// Server of its own, so that pushes of other tests do not reach the players.
+// Port is chosen by the system, so that port of closed global test server is not reused.
+fn create_server() -> Server {
+    server::run("localhost".to_string(), "0".to_string(), 3).unwrap()
+}
+
+fn enter_player(server_addr: &str, name: &str) -> UdpSocket {
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket.connect(server_addr).unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(3)))
+        .unwrap();
+    let cookie = request_challenge_cookie(&socket, name);
+    socket
+        .send(&create_player_enter_request(1, name, &cookie))
+        .unwrap();
+
+    // player created response.
+    let mut buf = [0; 1500];
+    socket.recv(&mut buf).unwrap();
+    socket
+}
+
+fn enter_memory_player(client: &Client, name: &str) {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::PlayerCreated(Ok(_))) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+fn receive_header(socket: &UdpSocket) -> Option<Header> {
+    let mut buf = [0; 1500];
+    let number_of_bytes = socket.recv(&mut buf).ok()?;
+    Header::from_raw_data(&buf[..number_of_bytes])
+}
+
+#[test]
+fn test_push_is_resent_until_acknowledged() {
+    let server = create_server();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+
+    let first_player = enter_player(&server_addr, "Acking");
+    let _second_player = enter_player(&server_addr, "Entering");
+
+    // player enter push is reliable by default, unacknowledged push is resent.
+    let push = receive_header(&first_player).unwrap();
+    let resent_push = receive_header(&first_player).unwrap();
+    assert_eq!(push.get_server_job(), ServerJob::PlayerEnterPush);
+    assert_eq!(push.delivery_mode, DeliveryMode::ReliableOrdered);
+    assert_eq!(resent_push.get_server_job(), ServerJob::PlayerEnterPush);
+    assert_eq!(resent_push.handle, push.handle);
+    assert_eq!(resent_push.sequence, push.sequence);
+
+    let ack = Header::new(
+        push.handle,
+        (ServerJob::PlayerEnterPush, ClientJob::PushAck),
+        DeliveryMode::Unreliable,
+        push.sequence,
+    );
+    first_player.send(&ack.get_raw_data()).unwrap();
+
+    // acknowledged push is not sent again.
+    assert!(receive_header(&first_player).is_none());
+}
+
+#[test]
+fn test_lost_first_push_keeps_players_in_order() {
+    let network = MemoryNetwork::new();
+    let server_transport = network.bind("10.0.0.1:11111".parse().unwrap()).unwrap();
+    let server_addr = server_transport.local_addr().unwrap();
+    let mut server = Server::new(3);
+    server
+        .connect_with_transport(Arc::new(server_transport))
+        .unwrap();
+    server.run();
+
+    let events = create_events();
+    let transport = LosingTransport {
+        transport: network.bind("10.0.0.2:0".parse().unwrap()).unwrap(),
+        is_later_push_received: AtomicBool::new(false),
+    };
+    let mut losing = Client::new(3);
+    losing
+        .connect_with_transport(Arc::new(transport), server_addr)
+        .unwrap();
+    client::run(&mut losing, Arc::clone(&events));
+    enter_memory_player(&losing, "Losing");
+
+    let mut others = vec![];
+    for name in ["Second", "Third"] {
+        let mut other = Client::new(3);
+        other
+            .connect_with_transport(
+                Arc::new(network.bind("10.0.0.3:0".parse().unwrap()).unwrap()),
+                server_addr,
+            )
+            .unwrap();
+        client::run(&mut other, create_events());
+        enter_memory_player(&other, name);
+        others.push(other);
+    }
+
+    // push of the third player waits, until the lost push of the second one is resent.
+    let mut names = vec![];
+    for _i in 0..60 {
+        names = events
+            .lock()
+            .unwrap()
+            .other_players
+            .iter()
+            .map(|player| player.player_name.clone())
+            .filter(|name| name != "Losing")
+            .collect::<Vec<String>>();
+        if names.len() >= 2 {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(names, vec!["Second", "Third"]);
+
+    losing.die();
+    for mut other in others {
+        other.die();
+    }
+    server.die();
+}
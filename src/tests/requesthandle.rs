@@ -0,0 +1,97 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{RequestError, Response};
+use crate::requests::header::Header;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::client;
+
+fn create_client(server_addr: String) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, create_events());
+    client
+}
+
+// Socket that never answers.
+fn create_silent_server() -> (UdpSocket, String) {
+    let silent_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    silent_server
+        .set_read_timeout(Some(Duration::from_millis(500)))
+        .unwrap();
+    let server_addr = silent_server.local_addr().unwrap().to_string();
+    (silent_server, server_addr)
+}
+
+#[test]
+fn test_wait_returns_response_of_the_request() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let mut client = create_client(format!("localhost:{}", server.get_port().unwrap()));
+
+    let request = client
+        .send_request(ClientJob::PingRequest, &mut vec![])
+        .unwrap();
+
+    match request.wait(Duration::from_secs(3)) {
+        Ok(Response::Pong(_time)) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+    // result is given only once.
+    assert!(request.try_result().is_none());
+
+    client.die();
+    server.die();
+}
+
+#[test]
+fn test_unanswered_request_has_no_result() {
+    let (_silent_server, server_addr) = create_silent_server();
+    let mut client = create_client(server_addr);
+
+    let request = client
+        .send_request(ClientJob::PingRequest, &mut vec![])
+        .unwrap();
+
+    assert!(request.try_result().is_none());
+    match request.wait(Duration::from_millis(100)) {
+        Err(RequestError::Timeout) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    client.die();
+}
+
+#[test]
+fn test_cancelled_request_is_not_resent() {
+    let (silent_server, server_addr) = create_silent_server();
+    let mut client = create_client(server_addr);
+
+    let request = client
+        .send_request(ClientJob::PingRequest, &mut vec![])
+        .unwrap();
+    let handle = request.get_handle();
+
+    let mut buf = [0; 1500];
+    let number_of_bytes = silent_server.recv(&mut buf).unwrap();
+    let header = Header::from_raw_data(&buf[..number_of_bytes]).unwrap();
+    assert_eq!(header.handle, handle);
+
+    request.cancel();
+
+    // request would be resent after retransmission timeout, only heartbeats may arrive.
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_millis(1500) {
+        if let Ok(number_of_bytes) = silent_server.recv(&mut buf) {
+            let header = Header::from_raw_data(&buf[..number_of_bytes]).unwrap();
+            assert_ne!(header.get_client_job(), ClientJob::PingRequest);
+        }
+    }
+
+    client.die();
+}
@@ -0,0 +1,59 @@
+use crate::requests::header::{Header, HEADER_LENGTH};
+use crate::requests::ServerJob;
+use crate::tests::common::init_test_environment;
+use crate::tests::common::rawclient::{create_player_enter_request, request_challenge_cookie};
+use crate::tests::common::testcounter::add_finished_count;
+use crate::tests::common::testserver::get_server_port;
+use std::net::UdpSocket;
+use std::time::Duration;
+
+fn send_player_enter_request(
+    socket: &UdpSocket,
+    handle: u16,
+    name: &str,
+    cookie: &[u8],
+) -> Vec<u8> {
+    socket
+        .send(&create_player_enter_request(handle, name, cookie))
+        .unwrap();
+
+    // pushes of players entering in other tests are skipped.
+    let mut buf = [0; 2048];
+    loop {
+        let number_of_bytes = socket.recv(&mut buf).unwrap();
+        let response = buf[..number_of_bytes].to_vec();
+        let response_header = Header::from_raw_data(&response).unwrap();
+        if response_header.get_server_job() == ServerJob::PlayerCreatedResponse {
+            return response;
+        }
+    }
+}
+
+#[test]
+fn test_resent_request_is_answered_from_cache() {
+    init_test_environment();
+
+    let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+    socket
+        .connect(format!("localhost:{}", get_server_port().unwrap()))
+        .unwrap();
+    socket
+        .set_read_timeout(Some(Duration::from_secs(2)))
+        .unwrap();
+
+    let cookie = request_challenge_cookie(&socket, "Resender");
+    let response = send_player_enter_request(&socket, 7, "Resender", &cookie);
+    // same request again, as if the response was lost.
+    let resent_response = send_player_enter_request(&socket, 7, "Resender", &cookie);
+    // new request with the same name is handled, and the name is taken.
+    let new_response = send_player_enter_request(&socket, 8, "Resender", &cookie);
+
+    add_finished_count();
+
+    let header = Header::from_raw_data(&response).unwrap();
+    assert_eq!(header.get_server_job(), ServerJob::PlayerCreatedResponse);
+    assert_eq!(header.handle, 7);
+    assert!(response.len() > HEADER_LENGTH + 1);
+    assert_eq!(resent_response, response);
+    assert_eq!(new_response[HEADER_LENGTH..].to_vec(), vec![101]);
+}
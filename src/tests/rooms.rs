@@ -0,0 +1,272 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{
+    RequestError, Response, RoomError, TargetedPushError,
+};
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::room::{
+    create_room_response, parse_room_response, JoinedRoom, RoomEvent, RoomInfo, RoomPlayer,
+    RoomResponse, RoomStatus,
+};
+use crate::requests::ClientJob;
+use crate::server;
+use crate::server::rooms::Rooms;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+fn create_player(server_addr: String, name: &str) -> (Client, Arc<Mutex<RecordingEvents>>) {
+    let events = create_events();
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, Arc::clone(&events));
+
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    assert!(matches!(
+        request.wait(TIMEOUT),
+        Ok(Response::PlayerCreated(Ok(_)))
+    ));
+    (client, events)
+}
+
+fn wait_for_room_event(events: &Arc<Mutex<RecordingEvents>>) -> RoomEvent {
+    for _i in 0..60 {
+        let mut events_changer = events.lock().unwrap();
+        if !events_changer.room_events.is_empty() {
+            return events_changer.room_events.remove(0);
+        }
+        drop(events_changer);
+        pause(50);
+    }
+    panic!("room event not received");
+}
+
+fn wait_for_push(events: &Arc<Mutex<RecordingEvents>>) -> (u8, Vec<u8>) {
+    for _i in 0..60 {
+        let mut events_changer = events.lock().unwrap();
+        if !events_changer.pushes.is_empty() {
+            return events_changer.pushes.remove(0);
+        }
+        drop(events_changer);
+        pause(50);
+    }
+    panic!("data push not received");
+}
+
+fn get_names(joined_room: &JoinedRoom) -> Vec<(u8, &str)> {
+    joined_room
+        .players
+        .iter()
+        .map(|player| (player.player_number, player.player_name.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_rooms_number_players_and_close_when_empty() {
+    let mut rooms = Rooms::new();
+    let events = rooms.subscribe();
+    assert_eq!(
+        rooms.create("".to_string(), 2, false),
+        Err(RoomStatus::InvalidRequest)
+    );
+    let room = rooms.create("Match".to_string(), 2, false).unwrap();
+
+    assert_eq!(rooms.join(room.room_id, 7, "Seven".to_string()), Ok(1));
+    assert_eq!(
+        rooms.join(room.room_id, 7, "Seven".to_string()),
+        Err(RoomStatus::AlreadyInRoom)
+    );
+    assert_eq!(rooms.join(room.room_id, 9, "Nine".to_string()), Ok(2));
+    assert_eq!(
+        rooms.join(room.room_id, 3, "Three".to_string()),
+        Err(RoomStatus::Full)
+    );
+    assert!(rooms.is_in_lobby(3));
+
+    // freed player number is given to the next player.
+    assert_eq!(rooms.leave(7), Some((room.room_id, 1)));
+    assert_eq!(rooms.join(room.room_id, 3, "Three".to_string()), Ok(1));
+    assert_eq!(rooms.leave(3), Some((room.room_id, 1)));
+    assert_eq!(rooms.leave(9), Some((room.room_id, 2)));
+    assert_eq!(rooms.leave(9), None);
+    assert!(rooms.get_rooms().is_empty());
+
+    let events: Vec<RoomEvent> = events.try_iter().collect();
+    assert_eq!(events.first(), Some(&RoomEvent::Created(room.clone())));
+    assert_eq!(
+        events.last(),
+        Some(&RoomEvent::Closed {
+            room_id: room.room_id
+        })
+    );
+    assert_eq!(events.len(), 8);
+
+    let response = RoomResponse::Joined(JoinedRoom {
+        room,
+        player_number: 1,
+        players: vec![RoomPlayer {
+            player_number: 1,
+            player_name: "Seven".to_string(),
+        }],
+    });
+    assert_eq!(
+        parse_room_response(&create_room_response(&response)),
+        Some(response)
+    );
+}
+
+#[test]
+fn test_room_scopes_pushes_and_tells_members() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    let server_events = server.room_events();
+    let (mut host, host_events) = create_player(server_addr.clone(), "Host");
+    let (mut guest, guest_events) = create_player(server_addr.clone(), "Guest");
+    let (mut outsider, outsider_events) = create_player(server_addr, "Outsider");
+
+    let created = host.create_room("Match", 2, TIMEOUT).unwrap();
+    assert_eq!(created.player_number, 1);
+    let room_id = created.room.room_id;
+    assert_eq!(
+        guest.list_rooms(TIMEOUT).unwrap(),
+        vec![RoomInfo {
+            room_id,
+            name: "Match".to_string(),
+            capacity: 2,
+            player_count: 1,
+        }]
+    );
+
+    let joined = guest.join_room(room_id, TIMEOUT).unwrap();
+    assert_eq!(joined.player_number, 2);
+    assert_eq!(get_names(&joined), vec![(1, "Host"), (2, "Guest")]);
+    assert_eq!(
+        wait_for_room_event(&host_events),
+        RoomEvent::PlayerJoined {
+            room_id,
+            player: RoomPlayer {
+                player_number: 2,
+                player_name: "Guest".to_string(),
+            },
+        }
+    );
+    assert!(matches!(
+        outsider.join_room(room_id, TIMEOUT),
+        Err(RoomError::Full)
+    ));
+    assert!(matches!(
+        host.join_room(room_id, TIMEOUT),
+        Err(RoomError::AlreadyInRoom)
+    ));
+
+    // pushes stay in the room, with player numbers of the room.
+    host.send_request(ClientJob::DataPushRequest, &mut b"room".to_vec())
+        .unwrap();
+    assert_eq!(wait_for_push(&guest_events), (1, b"room".to_vec()));
+    outsider
+        .send_request(ClientJob::DataPushRequest, &mut b"lobby".to_vec())
+        .unwrap();
+    let response = guest
+        .send_targeted_push(&[1], b"targeted")
+        .unwrap()
+        .wait(TIMEOUT);
+    assert!(matches!(response, Ok(Response::TargetedPushDone(Ok(())))));
+    assert_eq!(wait_for_push(&host_events), (2, b"targeted".to_vec()));
+    // relay reaches only players of the room too, outsider is player 3 of server.
+    let (_path, request) = host.send_peer_data_push(3, b"relayed").unwrap();
+    match request.unwrap().wait(TIMEOUT) {
+        Ok(Response::TargetedPushDone(result)) => {
+            assert_eq!(result, Err(TargetedPushError::UnknownTargets(vec![3])))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+    pause(200);
+    assert!(host_events.lock().unwrap().pushes.is_empty());
+    assert!(guest_events.lock().unwrap().pushes.is_empty());
+    assert!(outsider_events.lock().unwrap().pushes.is_empty());
+
+    assert_eq!(guest.leave_room(TIMEOUT).unwrap(), room_id);
+    assert_eq!(
+        wait_for_room_event(&host_events),
+        RoomEvent::PlayerLeft {
+            room_id,
+            player_number: 2,
+        }
+    );
+    assert!(matches!(
+        guest.leave_room(TIMEOUT),
+        Err(RoomError::NotInRoom)
+    ));
+    // room of a player closes with its last player.
+    assert_eq!(host.leave_room(TIMEOUT).unwrap(), room_id);
+    assert!(server.rooms().is_empty());
+    let events: Vec<RoomEvent> = server_events.try_iter().collect();
+    assert!(matches!(events.first(), Some(RoomEvent::Created(_))));
+    assert_eq!(events.last(), Some(&RoomEvent::Closed { room_id }));
+
+    host.die();
+    guest.die();
+    outsider.die();
+    server.die();
+}
+
+#[test]
+fn test_server_room_stays_until_server_closes_it() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    let room = server.create_room("Arena".to_string(), 4).unwrap();
+    let (mut player, player_events) = create_player(server_addr.clone(), "Player");
+
+    // room request of a client, which is not a player, is not answered.
+    let mut stranger = Client::new(3);
+    stranger
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut stranger, create_events());
+    assert!(matches!(
+        stranger.list_rooms(Duration::from_millis(300)),
+        Err(RoomError::Request(RequestError::Timeout))
+    ));
+    stranger.die();
+
+    player.join_room(room.room_id, TIMEOUT).unwrap();
+    let members = server.room_members(room.room_id).unwrap();
+    assert_eq!(members.len(), 1);
+    assert_eq!(members[0].player_number, 1);
+    assert_eq!(members[0].player_name, "Player");
+    player.leave_room(TIMEOUT).unwrap();
+    assert_eq!(server.rooms().len(), 1);
+
+    player.join_room(room.room_id, TIMEOUT).unwrap();
+    assert!(server.close_room(room.room_id));
+    assert_eq!(
+        wait_for_room_event(&player_events),
+        RoomEvent::Closed {
+            room_id: room.room_id
+        }
+    );
+    assert!(server.rooms().is_empty());
+    assert!(!server.close_room(room.room_id));
+    assert!(matches!(
+        player.leave_room(TIMEOUT),
+        Err(RoomError::NotInRoom)
+    ));
+
+    player.die();
+    server.die();
+}
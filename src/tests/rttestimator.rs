@@ -0,0 +1,44 @@
+use crate::requests::rtt::RttEstimator;
+use std::time::Duration;
+
+#[test]
+fn test_retransmission_timeout_follows_rtt_samples() {
+    let mut rtt_estimator = RttEstimator::new();
+    assert_eq!(
+        rtt_estimator.get_retransmission_timeout(),
+        Duration::from_secs(1)
+    );
+
+    // first sample, timeout is rtt + 4 * rtt / 2.
+    rtt_estimator.add_sample(Duration::from_millis(100));
+    assert_eq!(
+        rtt_estimator.get_retransmission_timeout(),
+        Duration::from_millis(300)
+    );
+
+    // steady rtt shrinks variance, and timeout closes in on rtt.
+    for _i in 0..50 {
+        rtt_estimator.add_sample(Duration::from_millis(100));
+    }
+    assert!(rtt_estimator.get_retransmission_timeout() < Duration::from_millis(110));
+}
+
+#[test]
+fn test_retransmission_timeout_backs_off_exponentially() {
+    let mut rtt_estimator = RttEstimator::new();
+    rtt_estimator.add_sample(Duration::from_millis(100));
+
+    assert_eq!(
+        rtt_estimator.get_backed_off_timeout(0),
+        Duration::from_millis(300)
+    );
+    assert_eq!(
+        rtt_estimator.get_backed_off_timeout(2),
+        Duration::from_millis(1200)
+    );
+    // backoff is limited.
+    assert_eq!(
+        rtt_estimator.get_backed_off_timeout(40),
+        Duration::from_secs(10)
+    );
+}
@@ -0,0 +1,172 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::playercreatedresponse::PlayerCreatedServerError;
+use crate::client::datahandlers::structs::request::Response;
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::session::SessionToken;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::server::server::Server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+const CONNECTION_TIMEOUT: Duration = Duration::from_millis(300);
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(3);
+
+// Server of its own, since global test server must keep default timeout.
+fn create_server(session_grace_period: Duration) -> (Server, String) {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    server.set_connection_timeout(CONNECTION_TIMEOUT);
+    server.set_session_grace_period(session_grace_period);
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    (server, server_addr)
+}
+
+// Client heartbeats often, so that it is not timed out.
+fn create_client(server_addr: String, events: Arc<Mutex<RecordingEvents>>) -> Client {
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client.set_keepalive_interval(Duration::from_millis(50));
+    client::run(&mut client, events);
+    client
+}
+
+// Returns player number and session token of the entered player.
+fn enter_player(client: &Client, name: &str) -> (u8, SessionToken) {
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(RESPONSE_TIMEOUT) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => (
+            response_data.player.player_number,
+            response_data.session_token,
+        ),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+fn resume_session(client: &Client, session_token: SessionToken) -> Option<u8> {
+    let request = client.resume_session(session_token).unwrap();
+    match request.wait(RESPONSE_TIMEOUT) {
+        Ok(Response::SessionResumed(player_number)) => player_number,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_player_resumes_session_from_new_address() {
+    let (mut server, server_addr) = create_server(Duration::from_secs(30));
+    let observer_events = create_events();
+    let mut observer = create_client(server_addr.clone(), Arc::clone(&observer_events));
+    enter_player(&observer, "Observer");
+
+    let mut roaming = create_client(server_addr.clone(), create_events());
+    let (player_number, session_token) = enter_player(&roaming, "Roaming");
+
+    // address of the player is lost, player times out.
+    roaming.die();
+    pause(CONNECTION_TIMEOUT.as_millis() as u64 * 3);
+
+    // slot of the player is held, name is still taken.
+    let mut other = create_client(server_addr.clone(), create_events());
+    let protocols = Protocol::new();
+    let request = other
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, "Roaming".to_string()).unwrap(),
+        )
+        .unwrap();
+    match request.wait(RESPONSE_TIMEOUT) {
+        Ok(Response::PlayerCreated(Err(PlayerCreatedServerError::NameIsTaken))) => {}
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let mut resumed = create_client(server_addr, create_events());
+    assert_eq!(resume_session(&resumed, session_token), Some(player_number));
+
+    for _i in 0..40 {
+        if !observer_events
+            .lock()
+            .unwrap()
+            .reconnected_players
+            .is_empty()
+        {
+            break;
+        }
+        pause(50);
+    }
+    let events = observer_events.lock().unwrap();
+    assert_eq!(events.reconnected_players, vec![player_number]);
+    assert!(events.left_players.is_empty());
+    drop(events);
+
+    resumed.die();
+    other.die();
+    observer.die();
+    server.die();
+}
+
+#[test]
+fn test_expired_session_is_not_resumed() {
+    let (mut server, server_addr) = create_server(Duration::from_millis(0));
+    let observer_events = create_events();
+    let mut observer = create_client(server_addr.clone(), Arc::clone(&observer_events));
+    enter_player(&observer, "Observer");
+
+    let mut leaving = create_client(server_addr.clone(), create_events());
+    let (player_number, session_token) = enter_player(&leaving, "Leaving");
+
+    leaving.die();
+    for _i in 0..40 {
+        if !observer_events.lock().unwrap().left_players.is_empty() {
+            break;
+        }
+        pause(50);
+    }
+    let events = observer_events.lock().unwrap();
+    assert_eq!(events.left_players.len(), 1);
+    assert_eq!(events.left_players[0].player_number, player_number);
+    drop(events);
+
+    let mut resumed = create_client(server_addr, create_events());
+    assert_eq!(resume_session(&resumed, session_token), None);
+    // forged token is not accepted either.
+    assert_eq!(
+        resume_session(&resumed, session_token.wrapping_add(1)),
+        None
+    );
+
+    resumed.die();
+    observer.die();
+    server.die();
+}
+
+#[test]
+fn test_session_is_not_resumed_over_another_player() {
+    let (mut server, server_addr) = create_server(Duration::from_secs(5));
+    let mut first = create_client(server_addr.clone(), create_events());
+    let (first_number, _first_token) = enter_player(&first, "First");
+    let mut second = create_client(server_addr, create_events());
+    let (second_number, second_token) = enter_player(&second, "Second");
+
+    // address of first player is not taken over by the session of second player.
+    assert_eq!(resume_session(&first, second_token), None);
+    assert!(server.connection_stats(first_number).is_some());
+    assert!(server.connection_stats(second_number).is_some());
+
+    first.die();
+    second.die();
+    server.die();
+}
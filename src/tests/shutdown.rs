@@ -0,0 +1,46 @@
+use crate::client::client::Client;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::create_events;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+use crate::client;
+
+// Threads wake up at least this often, so closing must not take longer.
+const MAX_CLOSING_TIME: Duration = Duration::from_millis(500);
+
+#[test]
+fn test_client_dies_without_packets_from_outside() {
+    // socket that never answers.
+    let silent_server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let server_addr = silent_server.local_addr().unwrap().to_string();
+    let mut clients = vec![];
+    for _i in 0..3 {
+        let mut client = Client::new(3);
+        client
+            .connect("127.0.0.1:0".to_string(), server_addr.clone())
+            .unwrap();
+        client::run(&mut client, create_events());
+        clients.push(client);
+    }
+
+    let start = Instant::now();
+    for client in &mut clients {
+        assert!(client.die());
+        assert!(!client.is_running());
+    }
+
+    assert!(start.elapsed() < MAX_CLOSING_TIME * 3);
+}
+
+#[test]
+fn test_server_dies_without_packets_from_outside() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+
+    let start = Instant::now();
+    assert!(server.die());
+
+    assert!(start.elapsed() < MAX_CLOSING_TIME);
+    assert!(!server.is_running());
+}
@@ -0,0 +1,134 @@
+use crate::client::client::Client;
+use crate::client::datahandlers::structs::request::{RequestError, Response, TargetedPushError};
+use crate::protocol::datahelpers;
+use crate::protocol::Protocol;
+use crate::requests::delivery::DeliveryMode;
+use crate::requests::targetedpush::create_targeted_push_request;
+use crate::requests::ClientJob;
+use crate::server;
+use crate::socket::SocketCombatible;
+use crate::tests::common::events::{create_events, RecordingEvents};
+use crate::tests::common::pause;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::client;
+
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+fn create_player(server_addr: String, name: &str) -> (Client, u8, Arc<Mutex<RecordingEvents>>) {
+    let events = create_events();
+    let mut client = Client::new(3);
+    client
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut client, Arc::clone(&events));
+
+    let protocols = Protocol::new();
+    let request = client
+        .send_request(
+            ClientJob::PlayerEnterRequest,
+            &mut datahelpers::create_player_request(&protocols, name.to_string()).unwrap(),
+        )
+        .unwrap();
+    let player_number = match request.wait(TIMEOUT) {
+        Ok(Response::PlayerCreated(Ok(response_data))) => response_data.player.player_number,
+        result => panic!("unexpected result: {:?}", result),
+    };
+    (client, player_number, events)
+}
+
+fn push_targets(client: &Client, player_numbers: &[u8]) -> Result<(), TargetedPushError> {
+    match client
+        .send_targeted_push(player_numbers, b"secret")
+        .unwrap()
+        .wait(TIMEOUT)
+    {
+        Ok(Response::TargetedPushDone(result)) => result,
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_push_is_routed_only_to_targets() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    let (mut sender, sender_number, _sender_events) = create_player(server_addr.clone(), "Sender");
+    let (mut target, target_number, target_events) = create_player(server_addr.clone(), "Target");
+    let (mut other, _other_number, other_events) = create_player(server_addr, "Other");
+
+    assert_eq!(push_targets(&sender, &[target_number]), Ok(()));
+    for _i in 0..60 {
+        if !target_events.lock().unwrap().pushes.is_empty() {
+            break;
+        }
+        pause(50);
+    }
+    assert_eq!(
+        target_events.lock().unwrap().pushes,
+        vec![(sender_number, b"secret".to_vec())]
+    );
+
+    // rejected push is not forwarded to anyone.
+    assert_eq!(
+        push_targets(&sender, &[target_number, 99, sender_number]),
+        Err(TargetedPushError::UnknownTargets(vec![99, sender_number]))
+    );
+    pause(200);
+    assert_eq!(target_events.lock().unwrap().pushes.len(), 1);
+    assert!(other_events.lock().unwrap().pushes.is_empty());
+
+    assert!(sender.send_targeted_push(&[], b"secret").is_err());
+    let request = sender
+        .send_request(ClientJob::TargetedPushRequest, &mut vec![0])
+        .unwrap();
+    assert!(matches!(
+        request.wait(TIMEOUT),
+        Ok(Response::TargetedPushDone(Err(
+            TargetedPushError::InvalidRequest
+        )))
+    ));
+
+    sender.die();
+    target.die();
+    other.die();
+    server.die();
+}
+
+#[test]
+fn test_unreliable_push_to_unknown_target_is_answered() {
+    let mut server = server::run("localhost".to_string(), "0".to_string(), 3).unwrap();
+    let server_addr = format!("localhost:{}", server.get_port().unwrap());
+    let (mut sender, _sender_number, _sender_events) = create_player(server_addr.clone(), "Sender");
+
+    let request = sender
+        .send_request_with_mode(
+            ClientJob::TargetedPushRequest,
+            &mut create_targeted_push_request(&[42], b"secret"),
+            DeliveryMode::Unreliable,
+        )
+        .unwrap();
+    match request.wait(TIMEOUT) {
+        Ok(Response::TargetedPushDone(result)) => {
+            assert_eq!(result, Err(TargetedPushError::UnknownTargets(vec![42])))
+        }
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    // push of a client, which is not a player, is not answered.
+    let mut stranger = Client::new(3);
+    stranger
+        .connect("127.0.0.1:0".to_string(), server_addr)
+        .unwrap();
+    client::run(&mut stranger, create_events());
+    let response = stranger
+        .send_targeted_push(&[1], b"secret")
+        .unwrap()
+        .wait(Duration::from_millis(300));
+    assert!(matches!(response, Err(RequestError::Timeout)));
+
+    sender.die();
+    stranger.die();
+    server.die();
+}